@@ -0,0 +1,325 @@
+//! Cell animations.
+//!
+//! The board changes instantly; animations only change how cells are drawn for a short while
+//! afterwards. They are started from the controller's events and moved along by update events.
+
+use std::collections::{HashMap, VecDeque};
+
+use gameboard::{CellState, Gameboard};
+use gameboard_controller::GameEvent;
+
+/// Longest a reveal wave or a chain of explosions takes to spread, in seconds. Bigger areas spread
+/// faster instead of taking longer.
+pub const MAX_SPREAD_TIME: f64 = 1.0;
+/// Most cells one reveal flips over. The cells furthest out just appear, so a huge flood fill
+/// doesn't track a running animation for every cell.
+pub const MAX_FLIPS: usize = 2000;
+
+/// Which animations to play, and how fast.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AnimationSettings {
+    /// Flip cells over when they open.
+    pub flip: bool,
+    /// Seconds a cell takes to flip over.
+    pub flip_time: f64,
+    /// Open flood-filled cells in waves spreading from the clicked cell.
+    pub flood_wave: bool,
+    /// Seconds between one ring of a flood fill and the next.
+    pub wave_step: f64,
+    /// Drop flags in from above.
+    pub flag_drop: bool,
+    /// Seconds a flag takes to land.
+    pub flag_time: f64,
+    /// Set off every mine in a chain after a loss.
+    pub explosion: bool,
+    /// Seconds between one ring of mines going off and the next.
+    pub explosion_step: f64,
+    /// Seconds a single explosion lasts.
+    pub explosion_time: f64,
+    /// Throw confetti over the board after a win.
+    pub confetti: bool,
+    /// Pieces of confetti thrown per win.
+    pub confetti_count: usize,
+}
+
+impl AnimationSettings {
+    /// Creates animation settings with everything turned on.
+    pub fn new() -> AnimationSettings {
+        AnimationSettings {
+            flip: true,
+            flip_time: 0.15,
+            flood_wave: true,
+            wave_step: 0.04,
+            flag_drop: true,
+            flag_time: 0.2,
+            explosion: true,
+            explosion_step: 0.12,
+            explosion_time: 0.4,
+            confetti: true,
+            confetti_count: 150,
+        }
+    }
+}
+
+/// What a cell is doing.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AnimationKind {
+    /// Turning over from hidden to open.
+    Flip,
+    /// A flag falling into place.
+    FlagDrop,
+    /// A mine going off.
+    Explosion,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct CellAnimation {
+    kind: AnimationKind,
+    start: f64,
+    duration: f64,
+}
+
+/// Running animations, at most one per cell.
+pub struct Animations {
+    /// Which animations to play, and how fast.
+    pub settings: AnimationSettings,
+    /// Seconds since these animations were created.
+    time: f64,
+    cells: HashMap<[usize; 2], CellAnimation>,
+}
+
+impl Animations {
+    /// Creates an empty set of animations.
+    pub fn new(settings: AnimationSettings) -> Animations {
+        Animations {
+            settings: settings,
+            time: 0.0,
+            cells: HashMap::new(),
+        }
+    }
+
+    /// Starts animations for something that happened on `board`.
+    pub fn handle(&mut self, event: &GameEvent, board: &Gameboard) {
+        let settings = self.settings;
+        match *event {
+            GameEvent::Revealed { origin, ref cells } if settings.flip => {
+                if !settings.flood_wave {
+                    for &pos in cells.iter().take(MAX_FLIPS) {
+                        self.start(pos, AnimationKind::Flip, 0.0, settings.flip_time);
+                    }
+                    return;
+                }
+                let rings = rings(board, origin, Some(cells));
+                let mut opened: Vec<([usize; 2], usize)> =
+                    cells.iter().map(|&pos| (pos, rings[pos[1]][pos[0]])).collect();
+                opened.sort_by_key(|&(_, ring)| ring);
+                opened.truncate(MAX_FLIPS);
+                let step = spread_step(settings.wave_step, &opened);
+                for (pos, ring) in opened {
+                    self.start(pos, AnimationKind::Flip, ring as f64 * step, settings.flip_time);
+                }
+            }
+            GameEvent::Flagged(pos) if settings.flag_drop => {
+                self.start(pos, AnimationKind::FlagDrop, 0.0, settings.flag_time);
+            }
+            GameEvent::Unflagged(pos) => {
+                self.cells.remove(&pos);
+            }
+            GameEvent::Exploded(origin) if settings.explosion => {
+                let rings = rings(board, origin, None);
+                let mut mines = Vec::new();
+                for (y, row) in board.cells.iter().enumerate() {
+                    for (x, cell) in row.iter().enumerate() {
+                        match *cell {
+                            CellState::HiddenBomb | CellState::QuestionBomb | CellState::Bomb => {
+                                mines.push(([x, y], rings[y][x]))
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                let step = spread_step(settings.explosion_step, &mines);
+                for (pos, ring) in mines {
+                    let delay = ring as f64 * step;
+                    self.start(pos, AnimationKind::Explosion, delay, settings.explosion_time);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn start(&mut self, pos: [usize; 2], kind: AnimationKind, delay: f64, duration: f64) {
+        if duration <= 0.0 {
+            return;
+        }
+        self.cells.insert(pos,
+                          CellAnimation {
+                              kind: kind,
+                              start: self.time + delay,
+                              duration: duration,
+                          });
+    }
+
+    /// Moves time forward, dropping animations that are done.
+    pub fn update(&mut self, dt: f64) {
+        self.time += dt;
+        let time = self.time;
+        self.cells.retain(|_, anim| time < anim.start + anim.duration);
+    }
+
+    /// Whether anything is still moving or waiting to start.
+    pub fn is_running(&self) -> bool {
+        !self.cells.is_empty()
+    }
+
+    /// Stops every animation.
+    pub fn clear(&mut self) {
+        self.cells.clear();
+    }
+
+    /// How far along a cell's animation is: below 0 while waiting to start, then 0 to 1.
+    pub fn progress(&self, pos: [usize; 2]) -> Option<(AnimationKind, f64)> {
+        self.cells.get(&pos).map(|anim| {
+            let t = (self.time - anim.start) / anim.duration;
+            (anim.kind, if t < 0.0 { t } else { t.min(1.0) })
+        })
+    }
+}
+
+/// How many neighbour steps every cell is from `origin`, following the board's own neighbours so
+/// wrapping and odd-shaped boards spread the way their flood fills do. With `only` given, steps
+/// only go through those cells, like the flood fill that opened them. Cells that can't be reached
+/// count as one ring past the last one that can.
+fn rings(board: &Gameboard, origin: [usize; 2], only: Option<&[[usize; 2]]>) -> Vec<Vec<usize>> {
+    let mut rings = vec![vec![usize::max_value(); board.width]; board.height];
+    let mut allowed = vec![vec![only.is_none(); board.width]; board.height];
+    for &pos in only.unwrap_or(&[]) {
+        allowed[pos[1]][pos[0]] = true;
+    }
+    let mut queue = VecDeque::new();
+    rings[origin[1]][origin[0]] = 0;
+    queue.push_back(origin);
+    let mut furthest = 0;
+    while let Some(pos) = queue.pop_front() {
+        let ring = rings[pos[1]][pos[0]];
+        furthest = ring;
+        for next in board.neighbours(pos) {
+            if allowed[next[1]][next[0]] && rings[next[1]][next[0]] == usize::max_value() {
+                rings[next[1]][next[0]] = ring + 1;
+                queue.push_back(next);
+            }
+        }
+    }
+    for row in &mut rings {
+        for ring in row.iter_mut().filter(|ring| **ring == usize::max_value()) {
+            *ring = furthest + 1;
+        }
+    }
+    rings
+}
+
+/// Seconds between one ring and the next, shortened when the furthest ring would otherwise start
+/// later than `MAX_SPREAD_TIME`.
+fn spread_step(step: f64, cells: &[([usize; 2], usize)]) -> f64 {
+    let furthest = cells.iter().map(|&(_, ring)| ring).max().unwrap_or(0);
+    if furthest as f64 * step > MAX_SPREAD_TIME {
+        MAX_SPREAD_TIME / furthest as f64
+    } else {
+        step
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gameboard::Gameboard;
+    use gameboard_controller::GameEvent;
+    use topology::Topology;
+
+    fn delay(animations: &Animations, pos: [usize; 2]) -> f64 {
+        let (kind, progress) = animations.progress(pos).unwrap();
+        let duration = match kind {
+            AnimationKind::Explosion => animations.settings.explosion_time,
+            _ => animations.settings.flip_time,
+        };
+        -progress * duration
+    }
+
+    fn revealed(board: &Gameboard, origin: [usize; 2]) -> GameEvent {
+        let mut cells = Vec::new();
+        for y in 0..board.height {
+            for x in 0..board.width {
+                cells.push([x, y]);
+            }
+        }
+        GameEvent::Revealed {
+            origin: origin,
+            cells: cells,
+        }
+    }
+
+    #[test]
+    fn waves_wrap_around_a_torus() {
+        let mut board = Gameboard::from_rows(&["......", "......", "......", "......", "......",
+                                               "..*..."])
+            .unwrap();
+        board.topology = Topology::Torus;
+        let mut animations = Animations::new(AnimationSettings::new());
+        animations.handle(&revealed(&board, [0, 0]), &board);
+        let step = animations.settings.wave_step;
+        assert_eq!(delay(&animations, [0, 0]), 0.0);
+        assert!((delay(&animations, [5, 5]) - step).abs() < 1e-9);
+        assert!((delay(&animations, [3, 3]) - 3.0 * step).abs() < 1e-9);
+    }
+
+    #[test]
+    fn waves_only_spread_through_opened_cells() {
+        let board = Gameboard::from_rows(&["...", "**.", "..."]).unwrap();
+        let event = GameEvent::Revealed {
+            origin: [0, 0],
+            cells: vec![[0, 0], [1, 0], [2, 0], [2, 1], [2, 2], [1, 2], [0, 2]],
+        };
+        let mut animations = Animations::new(AnimationSettings::new());
+        animations.handle(&event, &board);
+        let step = animations.settings.wave_step;
+        assert!((delay(&animations, [0, 2]) - 4.0 * step).abs() < 1e-9);
+    }
+
+    #[test]
+    fn explosions_spread_by_neighbour_steps() {
+        let mut board = Gameboard::from_rows(&["*....*", "......", "......", "......", "......",
+                                               "*....*"])
+            .unwrap();
+        board.topology = Topology::Torus;
+        let mut animations = Animations::new(AnimationSettings::new());
+        animations.handle(&GameEvent::Exploded([0, 0]), &board);
+        let step = animations.settings.explosion_step;
+        for &pos in &[[5, 0], [0, 5], [5, 5]] {
+            assert!((delay(&animations, pos) - step).abs() < 1e-9);
+        }
+        assert_eq!(animations.progress([1, 1]), None);
+    }
+
+    #[test]
+    fn long_waves_finish_within_the_spread_time() {
+        let board = Gameboard::from_rows(&[".".repeat(500), format!("*{}", ".".repeat(499))])
+            .unwrap();
+        let mut animations = Animations::new(AnimationSettings::new());
+        animations.handle(&revealed(&board, [0, 0]), &board);
+        assert!((delay(&animations, [499, 0]) - MAX_SPREAD_TIME).abs() < 1e-9);
+        animations.update(MAX_SPREAD_TIME + animations.settings.flip_time + 0.01);
+        assert!(!animations.is_running());
+    }
+
+    #[test]
+    fn huge_reveals_flip_only_the_nearest_cells() {
+        let mut rows = vec![".".repeat(100); 100];
+        rows[50] = format!("*{}", ".".repeat(99));
+        let board = Gameboard::from_rows(&rows).unwrap();
+        let mut animations = Animations::new(AnimationSettings::new());
+        animations.handle(&revealed(&board, [0, 0]), &board);
+        assert_eq!(animations.cells.len(), MAX_FLIPS);
+        assert!(animations.progress([1, 1]).is_some());
+        assert_eq!(animations.progress([99, 99]), None);
+    }
+}
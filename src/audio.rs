@@ -0,0 +1,251 @@
+//! Sound effects.
+//!
+//! Sounds are picked from the controller's events and handed to an `AudioBackend`. The null
+//! backend plays nothing, so the game runs the same without a sound card, and the `sound` feature
+//! adds one that plays short synthesized tones through rodio.
+
+use gameboard_controller::GameEvent;
+
+/// A sound cue.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Sound {
+    /// A single cell was opened.
+    Reveal,
+    /// Several cells were opened at once.
+    FloodFill,
+    /// A flag was placed.
+    Flag,
+    /// A flag was removed.
+    Unflag,
+    /// A bomb went off.
+    Explosion,
+    /// The game was won.
+    Victory,
+}
+
+/// Something that can play sounds.
+pub trait AudioBackend {
+    /// Starts playing a sound at `volume`, from 0 to 1, without waiting for it to finish.
+    fn play(&mut self, sound: Sound, volume: f32);
+}
+
+/// A backend that plays nothing.
+pub struct NullAudio;
+
+impl AudioBackend for NullAudio {
+    fn play(&mut self, _sound: Sound, _volume: f32) {}
+}
+
+/// How loud to play sounds.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SoundSettings {
+    /// Volume from 0 to 1.
+    pub volume: f32,
+    /// Play nothing at all.
+    pub muted: bool,
+}
+
+impl SoundSettings {
+    /// Creates sound settings with sound on at full volume.
+    pub fn new() -> SoundSettings {
+        SoundSettings {
+            volume: 1.0,
+            muted: false,
+        }
+    }
+
+    /// Steps the volume down by a fifth, going back to full after the lowest step.
+    pub fn next_volume(&mut self) {
+        let steps = (self.volume * 5.0).round() as i32;
+        self.volume = if steps <= 1 { 1.0 } else { (steps - 1) as f32 / 5.0 };
+    }
+}
+
+/// Plays a sound for each thing that happens on the board.
+pub struct Sounds {
+    /// Volume and mute.
+    pub settings: SoundSettings,
+    backend: Box<dyn AudioBackend>,
+}
+
+impl Sounds {
+    /// Creates a sound player on top of a backend.
+    pub fn new(backend: Box<dyn AudioBackend>) -> Sounds {
+        Sounds {
+            settings: SoundSettings::new(),
+            backend: backend,
+        }
+    }
+
+    /// Plays the sound that goes with an event.
+    pub fn handle(&mut self, event: &GameEvent) {
+        let sound = match *event {
+            GameEvent::Revealed { ref cells, .. } if cells.len() > 1 => Sound::FloodFill,
+            GameEvent::Revealed { .. } => Sound::Reveal,
+            GameEvent::Flagged(_) => Sound::Flag,
+            GameEvent::Unflagged(_) => Sound::Unflag,
+            GameEvent::Exploded(_) => Sound::Explosion,
+            GameEvent::Won => Sound::Victory,
+            GameEvent::GuessNeeded => return,
+        };
+        self.play(sound);
+    }
+
+    /// Plays a sound at the chosen volume, unless muted.
+    pub fn play(&mut self, sound: Sound) {
+        if self.settings.muted || self.settings.volume <= 0.0 {
+            return;
+        }
+        self.backend.play(sound, self.settings.volume);
+    }
+}
+
+/// The best backend available: rodio when built with the `sound` feature and an output device
+/// is found, otherwise the null backend.
+pub fn default_backend() -> Box<dyn AudioBackend> {
+    #[cfg(feature = "sound")]
+    {
+        if let Some(backend) = rodio_backend::RodioAudio::new() {
+            return Box::new(backend);
+        }
+        eprintln!("no audio output device found, playing without sound");
+    }
+    Box::new(NullAudio)
+}
+
+#[cfg(feature = "sound")]
+mod rodio_backend {
+    use std::time::Duration;
+
+    use rodio::{self, Endpoint, Source};
+    use rodio::source::SineWave;
+
+    use super::{AudioBackend, Sound};
+
+    /// Plays sounds as sine tones on the default output device.
+    pub struct RodioAudio {
+        endpoint: Endpoint,
+    }
+
+    impl RodioAudio {
+        /// Opens the default output device, if there is one.
+        pub fn new() -> Option<RodioAudio> {
+            rodio::get_default_endpoint().map(|endpoint| RodioAudio { endpoint: endpoint })
+        }
+
+        fn tone(&self, freq: u32, delay_ms: u64, length_ms: u64, volume: f32) {
+            let source = SineWave::new(freq)
+                .take_duration(Duration::from_millis(length_ms))
+                .delay(Duration::from_millis(delay_ms))
+                .amplify(volume * 0.2);
+            rodio::play_raw(&self.endpoint, source);
+        }
+    }
+
+    impl AudioBackend for RodioAudio {
+        fn play(&mut self, sound: Sound, volume: f32) {
+            match sound {
+                Sound::Reveal => self.tone(660, 0, 40, volume),
+                Sound::FloodFill => {
+                    self.tone(523, 0, 60, volume);
+                    self.tone(659, 50, 60, volume);
+                    self.tone(784, 100, 80, volume);
+                }
+                Sound::Flag => self.tone(880, 0, 60, volume),
+                Sound::Unflag => self.tone(440, 0, 60, volume),
+                Sound::Explosion => {
+                    self.tone(110, 0, 400, volume);
+                    self.tone(82, 0, 500, volume);
+                }
+                Sound::Victory => {
+                    for (i, &freq) in [523, 659, 784, 1047].iter().enumerate() {
+                        self.tone(freq, i as u64 * 120, 150, volume);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    /// A backend that writes down what it was asked to play.
+    struct Recorder(Rc<RefCell<Vec<(Sound, f32)>>>);
+
+    impl AudioBackend for Recorder {
+        fn play(&mut self, sound: Sound, volume: f32) {
+            self.0.borrow_mut().push((sound, volume));
+        }
+    }
+
+    fn recording() -> (Sounds, Rc<RefCell<Vec<(Sound, f32)>>>) {
+        let played = Rc::new(RefCell::new(Vec::new()));
+        (Sounds::new(Box::new(Recorder(played.clone()))), played)
+    }
+
+    #[test]
+    fn each_event_plays_its_sound() {
+        let (mut sounds, played) = recording();
+        let events = [GameEvent::Revealed {
+                          origin: [0, 0],
+                          cells: vec![[0, 0]],
+                      },
+                      GameEvent::Revealed {
+                          origin: [0, 0],
+                          cells: vec![[0, 0], [1, 0]],
+                      },
+                      GameEvent::Flagged([1, 1]),
+                      GameEvent::Unflagged([1, 1]),
+                      GameEvent::Exploded([2, 2]),
+                      GameEvent::Won];
+        for event in &events {
+            sounds.handle(event);
+        }
+        assert_eq!(*played.borrow(),
+                   vec![(Sound::Reveal, 1.0),
+                        (Sound::FloodFill, 1.0),
+                        (Sound::Flag, 1.0),
+                        (Sound::Unflag, 1.0),
+                        (Sound::Explosion, 1.0),
+                        (Sound::Victory, 1.0)]);
+    }
+
+    #[test]
+    fn muted_or_silent_plays_nothing() {
+        let (mut sounds, played) = recording();
+        sounds.settings.muted = true;
+        sounds.play(Sound::Flag);
+        sounds.settings.muted = false;
+        sounds.settings.volume = 0.0;
+        sounds.play(Sound::Flag);
+        assert!(played.borrow().is_empty());
+
+        sounds.settings.volume = 0.4;
+        sounds.play(Sound::Flag);
+        assert_eq!(*played.borrow(), vec![(Sound::Flag, 0.4)]);
+    }
+
+    #[test]
+    fn volume_steps_down_and_wraps_to_full() {
+        let mut settings = SoundSettings::new();
+        let mut volumes = Vec::new();
+        for _ in 0..6 {
+            settings.next_volume();
+            volumes.push(settings.volume);
+        }
+        assert_eq!(volumes, vec![0.8, 0.6, 0.4, 0.2, 1.0, 0.8]);
+
+        // Volumes between the steps snap to the nearest one on the way down.
+        settings.volume = 0.55;
+        settings.next_volume();
+        assert_eq!(settings.volume, 0.4);
+        settings.volume = 0.0;
+        settings.next_volume();
+        assert_eq!(settings.volume, 1.0);
+    }
+}
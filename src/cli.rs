@@ -0,0 +1,274 @@
+//! Command line options.
+
+use std::fs;
+
+use opengl_graphics::OpenGL;
+
+use gameboard::{Difficulty, Gameboard};
+use replay::{Move, Replay};
+use topology::Topology;
+use window::opengl_from_name;
+
+/// Text shown for `--help`.
+pub const USAGE: &'static str = "Usage: rustsweeper [options]
+
+Starts at the menu, or goes straight to a game when one is given.
+
+Options:
+  --width N        Columns on the board
+  --height N       Rows on the board
+  --mines N        Bombs on the board
+  --preset NAME    beginner, intermediate, expert or marathon
+  --cells SHAPE    square, hex, torus for edges that wrap around, triangle, knight for numbers
+                   that count a knight's move away or layers for a stack of boards
+  --seed N         Deal the same boards every time
+  --board FILE     Play a board drawn with * for bombs and . for empty cells
+  --replay FILE    Watch a game saved with --record
+  --record FILE    Save each game to FILE when it ends
+  --no-guess       Only deal boards that can be solved without guessing
+  --endless        Play on a minefield without edges, scoring the cells cleared before a bomb
+                   goes off
+  --headless       Play without a window, reading moves like `reveal 3 4` from stdin
+  --tui            Play in the terminal, with the keyboard or the mouse
+  --opengl VERSION Newest OpenGL version to try: 4.4, 4.1, 3.3, 3.2, 3.0 or 2.1
+  --render-board FILE
+                   Save a picture of the board as a PNG and quit, after any moves from --replay
+                   or --headless
+  --help           Show this text
+";
+
+/// Everything given on the command line.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    /// Columns on the board.
+    pub width: Option<usize>,
+    /// Rows on the board.
+    pub height: Option<usize>,
+    /// Bombs on the board.
+    pub mines: Option<usize>,
+    /// Preset to start from before applying the size options.
+    pub preset: Option<Difficulty>,
+    /// Shape of the cells.
+    pub topology: Option<Topology>,
+    /// Seed for dealing boards.
+    pub seed: Option<u64>,
+    /// Board file to play.
+    pub board: Option<String>,
+    /// Replay file to watch.
+    pub replay: Option<String>,
+    /// File to save each finished game to.
+    pub record: Option<String>,
+    /// Only deal boards that can be solved without guessing.
+    pub no_guess: bool,
+    /// Start in endless mode.
+    pub endless: bool,
+    /// Play on stdin and stdout instead of in a window.
+    pub headless: bool,
+    /// Play in the terminal instead of in a window.
+    pub tui: bool,
+    /// OpenGL version to start from when opening the window.
+    pub opengl: Option<OpenGL>,
+    /// PNG file to draw the board into instead of opening a window.
+    pub render_board: Option<String>,
+    /// Show the usage text and quit.
+    pub help: bool,
+}
+
+impl Options {
+    /// Reads options from the arguments after the program name. Values can be given as
+    /// `--seed 5` or `--seed=5`.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (name, mut inline) = match arg.find('=') {
+                Some(eq) if arg.starts_with("--") => {
+                    (arg[..eq].to_string(), Some(arg[eq + 1..].to_string()))
+                }
+                _ => (arg.clone(), None),
+            };
+            let mut value = || {
+                inline.take()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("{} needs a value", name))
+            };
+            match name.as_str() {
+                "--width" => options.width = Some(number(&value()?, "--width")?),
+                "--height" => options.height = Some(number(&value()?, "--height")?),
+                "--mines" => options.mines = Some(number(&value()?, "--mines")?),
+                "--preset" => {
+                    let name = value()?;
+                    options.preset = Some(Difficulty::preset(&name)
+                        .ok_or_else(|| format!("`{}` is not a preset", name))?);
+                }
+                "--cells" => {
+                    let name = value()?;
+                    // Custom neighbours need their steps, which only the settings file has.
+                    options.topology = Some(Topology::ALL.iter()
+                        .cloned()
+                        .find(|topology| topology.id() == name)
+                        .ok_or_else(|| format!("`{}` is not a cell shape", name))?);
+                }
+                "--seed" => options.seed = Some(number(&value()?, "--seed")?),
+                "--board" => options.board = Some(value()?),
+                "--replay" => options.replay = Some(value()?),
+                "--record" => options.record = Some(value()?),
+                "--no-guess" => options.no_guess = true,
+                "--endless" => options.endless = true,
+                "--headless" => options.headless = true,
+                "--tui" => options.tui = true,
+                "--render-board" => options.render_board = Some(value()?),
+                "--opengl" => {
+                    let name = value()?;
+                    options.opengl = Some(opengl_from_name(&name)
+                        .ok_or_else(|| format!("OpenGL {} is not supported", name))?);
+                }
+                "--help" | "-h" => options.help = true,
+                _ => return Err(format!("unknown option `{}`", arg)),
+            }
+        }
+        if options.board.is_some() && options.replay.is_some() {
+            return Err("--board and --replay can't be used together".to_string());
+        }
+        if options.headless && options.tui {
+            return Err("--headless and --tui can't be used together".to_string());
+        }
+        if options.endless &&
+           (options.headless || options.tui || options.render_board.is_some() ||
+            options.board.is_some() || options.replay.is_some()) {
+            return Err("--endless can only be played in the window".to_string());
+        }
+        Ok(options)
+    }
+
+    /// Whether a game was asked for, so the menu can be skipped.
+    pub fn starts_game(&self) -> bool {
+        self.width.is_some() || self.height.is_some() || self.mines.is_some() ||
+        self.preset.is_some() || self.topology.is_some() || self.seed.is_some() ||
+        self.board.is_some() ||
+        self.replay.is_some() || self.no_guess
+    }
+
+    /// The difficulty asked for, built on top of `base` when only some of it was given. A new
+    /// size without `--mines` keeps the bomb density of what it started from.
+    pub fn difficulty(&self, base: Difficulty) -> Result<Option<Difficulty>, String> {
+        if self.preset.is_none() && self.width.is_none() && self.height.is_none() &&
+           self.mines.is_none() {
+            return Ok(None);
+        }
+        let base = self.preset.unwrap_or(base);
+        let width = self.width.unwrap_or(base.width);
+        let height = self.height.unwrap_or(base.height);
+        let density = base.mines as f64 / (base.width * base.height) as f64;
+        let difficulty = Difficulty {
+            width: width,
+            height: height,
+            mines: self.mines
+                .unwrap_or_else(|| ((width * height) as f64 * density).round().max(1.0) as usize),
+        };
+        difficulty.validate()?;
+        Ok(Some(difficulty))
+    }
+
+    /// The board from `--board` or `--replay`, with the moves to play back for a replay. Board
+    /// files have square cells unless `--cells` says otherwise.
+    pub fn load_board(&self) -> Result<Option<(Gameboard, Vec<Move>)>, String> {
+        if let Some(ref path) = self.board {
+            let text = fs::read_to_string(path)
+                .map_err(|e| format!("could not read {}: {}", path, e))?;
+            let rows: Vec<&str> =
+                text.lines().map(str::trim).filter(|row| !row.is_empty()).collect();
+            let mut board =
+                Gameboard::from_rows(&rows).map_err(|e| format!("{}: {}", path, e))?;
+            let topology = self.topology.unwrap_or(Topology::Square);
+            topology.check_rows(board.height).map_err(|e| format!("{}: {}", path, e))?;
+            board.set_topology(topology);
+            return Ok(Some((board, Vec::new())));
+        }
+        if let Some(ref path) = self.replay {
+            let replay = Replay::load(path)?;
+            let board = replay.gameboard().map_err(|e| format!("{}: {}", path, e))?;
+            return Ok(Some((board, replay.moves)));
+        }
+        Ok(None)
+    }
+}
+
+fn number<T: ::std::str::FromStr>(text: &str, option: &str) -> Result<T, String> {
+    text.parse().map_err(|_| format!("{} needs a whole number, not `{}`", option, text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gameboard::{EXPERT, MARATHON};
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn values_follow_the_flag_or_an_equals_sign() {
+        let options = parse(&["--width", "20", "--height=12", "--mines", "30", "--seed=7",
+                              "--cells", "hex", "--no-guess", "--record", "game.toml"])
+            .unwrap();
+        assert_eq!(options.width, Some(20));
+        assert_eq!(options.height, Some(12));
+        assert_eq!(options.mines, Some(30));
+        assert_eq!(options.seed, Some(7));
+        assert_eq!(options.topology, Some(Topology::Hex));
+        assert!(options.no_guess);
+        assert_eq!(options.record, Some("game.toml".to_string()));
+        assert_eq!(options.difficulty(EXPERT),
+                   Ok(Some(Difficulty {
+                       width: 20,
+                       height: 12,
+                       mines: 30,
+                   })));
+    }
+
+    #[test]
+    fn new_sizes_keep_the_bomb_density() {
+        let options = parse(&["--preset", "Expert", "--width", "15"]).unwrap();
+        assert_eq!(options.preset, Some(EXPERT));
+        assert_eq!(options.difficulty(MARATHON),
+                   Ok(Some(Difficulty {
+                       width: 15,
+                       height: 16,
+                       mines: 50,
+                   })));
+        assert_eq!(parse(&[]).unwrap().difficulty(EXPERT), Ok(None));
+    }
+
+    #[test]
+    fn bad_arguments_are_reported() {
+        assert_eq!(parse(&["--bogus"]).unwrap_err(), "unknown option `--bogus`");
+        assert_eq!(parse(&["--seed"]).unwrap_err(), "--seed needs a value");
+        assert_eq!(parse(&["--width", "wide"]).unwrap_err(),
+                   "--width needs a whole number, not `wide`");
+        assert_eq!(parse(&["--preset=huge"]).unwrap_err(), "`huge` is not a preset");
+        assert_eq!(parse(&["--cells", "custom"]).unwrap_err(), "`custom` is not a cell shape");
+        assert_eq!(parse(&["--board", "a.txt", "--replay", "b.toml"]).unwrap_err(),
+                   "--board and --replay can't be used together");
+        assert_eq!(parse(&["--headless", "--tui"]).unwrap_err(),
+                   "--headless and --tui can't be used together");
+        assert_eq!(parse(&["--endless", "--tui"]).unwrap_err(),
+                   "--endless can only be played in the window");
+        assert!(parse(&["--width", "2000"]).unwrap().difficulty(EXPERT).is_err());
+    }
+
+    #[test]
+    fn only_game_options_skip_the_menu() {
+        for args in [&["--seed", "1"][..],
+                     &["--preset", "beginner"],
+                     &["--cells", "torus"],
+                     &["--board", "board.txt"],
+                     &["--no-guess"]]
+            .iter() {
+            assert!(parse(args).unwrap().starts_game(), "{:?}", args);
+        }
+        for args in [&[][..], &["--record", "game.toml"], &["--tui"], &["--help"]].iter() {
+            assert!(!parse(args).unwrap().starts_game(), "{:?}", args);
+        }
+    }
+}
@@ -0,0 +1,546 @@
+//! Player settings.
+//!
+//! Settings are kept in a TOML file next to the game, read at startup and written again whenever
+//! one is changed in the menu. Keys that are missing or can't be used keep their defaults, so an
+//! old or hand-edited file never stops the game from starting.
+
+use std::fs;
+use std::path::Path;
+
+use piston::input::keyboard::Key;
+
+use animation::AnimationSettings;
+use audio::SoundSettings;
+use gameboard::{Difficulty, FirstClick, BEGINNER};
+use gameboard_view::NumberPalette;
+use keybindings::{self, Action, KeyBindings};
+use theme::DEFAULT_THEME;
+use toml::{expect, quote, Document};
+use topology::{Offsets, Topology, DEFAULT_LAYERS, MAX_LAYERS};
+
+/// File the settings are read from and written to.
+pub const CONFIG_FILE: &'static str = "config.toml";
+
+/// Every setting the player can change.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// Board size and bomb count of new games.
+    pub difficulty: Difficulty,
+    /// Which cells the first click can't hit.
+    pub first_click: FirstClick,
+    /// Shape of the cells on new boards.
+    pub topology: Topology,
+    /// Steps to the cells counted on boards with custom neighbours.
+    pub neighbours: Offsets,
+    /// Whether flags turn into question marks before being cleared.
+    pub question_marks: bool,
+    /// File stem of the theme in use.
+    pub theme: String,
+    /// Colors for the numbers on the board.
+    pub number_palette: NumberPalette,
+    /// Which animations to play.
+    pub animations: AnimationSettings,
+    /// Volume and mute.
+    pub sound: SoundSettings,
+    /// What each key does on the board.
+    pub keys: KeyBindings,
+}
+
+impl Config {
+    /// Creates the default settings.
+    pub fn new() -> Config {
+        Config {
+            difficulty: BEGINNER,
+            first_click: FirstClick::Safe,
+            topology: Topology::Square,
+            neighbours: Offsets::default(),
+            question_marks: false,
+            theme: DEFAULT_THEME.to_string(),
+            number_palette: NumberPalette::Theme,
+            animations: AnimationSettings::new(),
+            sound: SoundSettings::new(),
+            keys: KeyBindings::new(),
+        }
+    }
+
+    /// Reads settings from TOML text, returning them along with a message for every key that
+    /// couldn't be used.
+    pub fn parse(text: &str) -> (Config, Vec<String>) {
+        let mut config = Config::new();
+        let doc = match Document::parse(text) {
+            Ok(doc) => doc,
+            Err(e) => return (config, vec![e]),
+        };
+
+        let mut results = Vec::new();
+        results.push(read_difficulty(&doc, &mut config.difficulty));
+        results.push(read_name(&doc,
+                               "game",
+                               "first_click",
+                               &mut config.first_click,
+                               FirstClick::from_id,
+                               "\"anything\", \"safe\" or \"opening\""));
+        results.push(read_neighbours(&doc, &mut config.neighbours));
+        let neighbours = config.neighbours;
+        results.push(read_name(&doc,
+                               "game",
+                               "cells",
+                               &mut config.topology,
+                               |name| Topology::from_id(name, neighbours),
+                               "\"square\", \"hex\", \"torus\", \"triangle\", \"knight\", \
+                                \"layers\" or \"custom\""));
+        results.push(read_layers(&doc, &mut config.topology));
+        results.push(read_bool(&doc, "game", "question_marks", &mut config.question_marks));
+
+        results.push(read_name(&doc,
+                               "look",
+                               "theme",
+                               &mut config.theme,
+                               |name| Some(name.to_string()),
+                               "a string"));
+        results.push(read_name(&doc,
+                               "look",
+                               "numbers",
+                               &mut config.number_palette,
+                               NumberPalette::from_name,
+                               "\"normal\" or \"colorblind\""));
+
+        let animations = &mut config.animations;
+        results.push(read_bool(&doc, "animations", "flip", &mut animations.flip));
+        results.push(read_bool(&doc, "animations", "flood_wave", &mut animations.flood_wave));
+        results.push(read_bool(&doc, "animations", "flag_drop", &mut animations.flag_drop));
+        results.push(read_bool(&doc, "animations", "explosion", &mut animations.explosion));
+        results.push(read_bool(&doc, "animations", "confetti", &mut animations.confetti));
+        results.push(read_seconds(&doc, "flip_time", &mut animations.flip_time));
+        results.push(read_seconds(&doc, "wave_step", &mut animations.wave_step));
+        results.push(read_seconds(&doc, "flag_time", &mut animations.flag_time));
+        results.push(read_seconds(&doc, "explosion_step", &mut animations.explosion_step));
+        results.push(read_seconds(&doc, "explosion_time", &mut animations.explosion_time));
+
+        results.push(read_volume(&doc, &mut config.sound.volume));
+        results.push(read_bool(&doc, "sound", "muted", &mut config.sound.muted));
+
+        results.extend(read_keys(&doc, &mut config.keys).into_iter().map(Err));
+
+        let problems = results.into_iter().filter_map(|result| result.err()).collect();
+        (config, problems)
+    }
+
+    /// Loads settings from a file, reporting keys that couldn't be used. A missing file gives the
+    /// defaults.
+    pub fn load<P: AsRef<Path>>(path: P) -> Config {
+        let path = path.as_ref();
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(_) => return Config::new(),
+        };
+        let (config, problems) = Config::parse(&text);
+        for problem in problems {
+            eprintln!("{}: {}", path.display(), problem);
+        }
+        config
+    }
+
+    /// Writes the settings as TOML text.
+    pub fn to_toml(&self) -> String {
+        let animations = &self.animations;
+        let mut text = format!("# Rustsweeper settings, rewritten whenever they change in the menu.
+
+[game]
+# \"beginner\", \"intermediate\", \"expert\", \"marathon\" or \"custom\" to use the size below.
+difficulty = {}
+width = {}
+height = {}
+mines = {}
+# \"anything\", \"safe\" or \"opening\".
+first_click = {}
+# \"square\", \"hex\", \"torus\" for edges that wrap around, \"triangle\", \"knight\" for
+# numbers that count a knight's move away, \"layers\" for a stack of boards or \"custom\" to
+# count the steps below.
+cells = {}
+# Boards in a stack, each of the size above, up to {}.
+layers = {}
+# Steps from a cell to the cells its number counts, each at most two away. Every step also
+# counts the other way.
+neighbours = {}
+question_marks = {}
+
+[look]
+theme = {}
+# \"normal\" or \"colorblind\".
+numbers = {}
+
+[animations]
+flip = {}
+flood_wave = {}
+flag_drop = {}
+explosion = {}
+confetti = {}
+# Speeds in seconds: how long a flip, a flag drop and an explosion take, and the time between
+# one ring of a flood fill or chain explosion and the next.
+flip_time = {}
+wave_step = {}
+flag_time = {}
+explosion_step = {}
+explosion_time = {}
+
+[sound]
+volume = {}
+muted = {}
+
+[keys]
+",
+                               quote(&self.difficulty.name().to_lowercase()),
+                               self.difficulty.width,
+                               self.difficulty.height,
+                               self.difficulty.mines,
+                               quote(self.first_click.id()),
+                               quote(self.topology.id()),
+                               MAX_LAYERS,
+                               match self.topology {
+                                   Topology::Layers(depth) => depth,
+                                   _ => DEFAULT_LAYERS,
+                               },
+                               self.neighbours.to_toml(),
+                               self.question_marks,
+                               quote(&self.theme),
+                               quote(&self.number_palette.name().to_lowercase()),
+                               animations.flip,
+                               animations.flood_wave,
+                               animations.flag_drop,
+                               animations.explosion,
+                               animations.confetti,
+                               animations.flip_time,
+                               animations.wave_step,
+                               animations.flag_time,
+                               animations.explosion_step,
+                               animations.explosion_time,
+                               self.sound.volume,
+                               self.sound.muted);
+        for &action in &Action::ALL {
+            let names: Vec<String> = self.keys
+                .keys(action)
+                .into_iter()
+                .filter_map(keybindings::key_name)
+                .map(quote)
+                .collect();
+            text.push_str(&format!("{} = [{}]\n", action.id(), names.join(", ")));
+        }
+        text
+    }
+
+    /// Writes the settings to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        fs::write(path, self.to_toml())
+            .map_err(|e| format!("could not write {}: {}", path.display(), e))
+    }
+}
+
+fn read_str<'a>(doc: &'a Document, section: &str, key: &str) -> Result<Option<&'a str>, String> {
+    match doc.get(section, key) {
+        Some(value) => expect(value.as_str(), section, key, "a string").map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Reads a string and turns it into a value with `parse`, which returns `None` for names it
+/// doesn't know. `kind` lists the names that work.
+fn read_name<T, F>(doc: &Document,
+                   section: &str,
+                   key: &str,
+                   target: &mut T,
+                   parse: F,
+                   kind: &str)
+                   -> Result<(), String>
+    where F: Fn(&str) -> Option<T>
+{
+    if let Some(name) = read_str(doc, section, key)? {
+        *target = expect(parse(name), section, key, kind)?;
+    }
+    Ok(())
+}
+
+fn read_bool(doc: &Document, section: &str, key: &str, target: &mut bool) -> Result<(), String> {
+    if let Some(value) = doc.get(section, key) {
+        *target = expect(value.as_bool(), section, key, "true or false")?;
+    }
+    Ok(())
+}
+
+fn read_count(doc: &Document, section: &str, key: &str, target: &mut usize) -> Result<(), String> {
+    if let Some(value) = doc.get(section, key) {
+        let number = expect(value.as_f64(), section, key, "a whole number")?;
+        if number < 0.0 || number.fract() != 0.0 {
+            return Err(format!("{}.{} should be a whole number", section, key));
+        }
+        *target = number as usize;
+    }
+    Ok(())
+}
+
+fn read_volume(doc: &Document, target: &mut f32) -> Result<(), String> {
+    if let Some(value) = doc.get("sound", "volume") {
+        let volume = expect(value.as_f64(), "sound", "volume", "a number")?;
+        if volume < 0.0 || volume > 1.0 {
+            return Err("sound.volume should be between 0 and 1".to_string());
+        }
+        *target = volume as f32;
+    }
+    Ok(())
+}
+
+/// Reads how long part of an animation takes.
+fn read_seconds(doc: &Document, key: &str, target: &mut f64) -> Result<(), String> {
+    if let Some(value) = doc.get("animations", key) {
+        let seconds = expect(value.as_f64(), "animations", key, "a number of seconds")?;
+        if seconds < 0.0 || !seconds.is_finite() {
+            return Err(format!("animations.{} should not be negative", key));
+        }
+        *target = seconds;
+    }
+    Ok(())
+}
+
+/// Reads how deep a stack is, when the cells are stacked.
+fn read_layers(doc: &Document, target: &mut Topology) -> Result<(), String> {
+    if let Topology::Layers(ref mut depth) = *target {
+        let mut count = *depth;
+        read_count(doc, "game", "layers", &mut count)?;
+        if count == 0 || count > MAX_LAYERS {
+            return Err(format!("game.layers should be from 1 to {}", MAX_LAYERS));
+        }
+        *depth = count;
+    }
+    Ok(())
+}
+
+/// Reads the steps for custom neighbours.
+fn read_neighbours(doc: &Document, target: &mut Offsets) -> Result<(), String> {
+    if let Some(value) = doc.get("game", "neighbours") {
+        let steps = expect(value.as_steps(), "game", "neighbours", "a list of [x, y] steps")?;
+        *target = Offsets::new(&steps).map_err(|e| format!("game.neighbours: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Reads a preset name, or a custom size when the name is `"custom"`.
+fn read_difficulty(doc: &Document, target: &mut Difficulty) -> Result<(), String> {
+    let name = match read_str(doc, "game", "difficulty")? {
+        Some(name) => name,
+        None => return Ok(()),
+    };
+    let difficulty = if name.eq_ignore_ascii_case("custom") {
+        let mut custom = *target;
+        read_count(doc, "game", "width", &mut custom.width)?;
+        read_count(doc, "game", "height", &mut custom.height)?;
+        read_count(doc, "game", "mines", &mut custom.mines)?;
+        custom
+    } else {
+        Difficulty::preset(name).ok_or_else(|| format!("game.difficulty `{}` is unknown", name))?
+    };
+    difficulty.validate().map_err(|e| format!("game: {}", e))?;
+    *target = difficulty;
+    Ok(())
+}
+
+/// Reads the list of keys for an action, if the file has one.
+fn read_key_list(doc: &Document, action: Action) -> Result<Option<Vec<Key>>, String> {
+    let id = action.id();
+    let names = match doc.get("keys", id) {
+        Some(value) => expect(value.as_array(), "keys", id, "a list of key names")?,
+        None => return Ok(None),
+    };
+    let mut list = Vec::new();
+    for name in names {
+        let name = expect(name.as_str(), "keys", id, "a list of key names")?;
+        let key = keybindings::key_from_name(name)
+            .ok_or_else(|| format!("keys.{}: `{}` is not a key", id, name))?;
+        list.push(key);
+    }
+    if list.is_empty() && action == Action::Menu {
+        return Err("keys.menu needs at least one key".to_string());
+    }
+    Ok(Some(list))
+}
+
+/// Reads the keys for every action, returning a message for each list that can't be used. A key
+/// can only do one thing, so a list taking a key that another action keeps, from the file or by
+/// default, is left out and its action keeps its default keys.
+fn read_keys(doc: &Document, keys: &mut KeyBindings) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut lists = Vec::new();
+    for &action in &Action::ALL {
+        match read_key_list(doc, action) {
+            Ok(Some(list)) => lists.push((action, list)),
+            Ok(None) => {}
+            Err(e) => problems.push(e),
+        }
+    }
+
+    // Leaving a list out hands its action's default keys back, which can clash with lists that
+    // were fine before, so start over after each one until none clash.
+    let defaults = KeyBindings::new();
+    let mut i = 0;
+    while i < lists.len() {
+        let clash = {
+            let (action, ref list) = lists[i];
+            let holder = |key: Key| {
+                Action::ALL.iter().cloned().find(|&other| {
+                    other != action &&
+                    match lists.iter().position(|&(a, _)| a == other) {
+                        // Lists in the file take keys in order, so only earlier ones win.
+                        Some(j) => j < i && lists[j].1.contains(&key),
+                        None => defaults.keys(other).contains(&key),
+                    }
+                })
+            };
+            list.iter().filter_map(|&key| holder(key).map(|other| (key, other))).next()
+        };
+        match clash {
+            Some((key, other)) => {
+                problems.push(format!("keys.{}: {} is already used for {}",
+                                      lists[i].0.id(),
+                                      keybindings::key_name(key).unwrap_or("the key"),
+                                      other.id()));
+                lists.remove(i);
+                i = 0;
+            }
+            None => i += 1,
+        }
+    }
+    for (action, list) in lists {
+        keys.set(action, &list);
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_file_gives_the_defaults() {
+        assert_eq!(Config::parse(""), (Config::new(), Vec::new()));
+        let keys = KeyBindings::new();
+        assert_eq!(keys.action(Key::F), Some(Action::Flag));
+        assert_eq!(keys.action(Key::Space), Some(Action::Reveal));
+        assert_eq!(keys.keys(Action::Menu), vec![Key::Escape]);
+    }
+
+    #[test]
+    fn bad_values_keep_their_defaults() {
+        let (config, problems) = Config::parse("[game]\n\
+                                                difficulty = \"huge\"\n\
+                                                first_click = 3\n\
+                                                question_marks = \"yes\"\n\
+                                                [look]\n\
+                                                numbers = \"pink\"\n\
+                                                [animations]\n\
+                                                flip_time = -1\n\
+                                                [sound]\n\
+                                                volume = 2\n");
+        assert_eq!(config, Config::new());
+        assert_eq!(problems,
+                   vec!["game.difficulty `huge` is unknown",
+                        "game.first_click should be a string",
+                        "game.question_marks should be true or false",
+                        "look.numbers should be \"normal\" or \"colorblind\"",
+                        "animations.flip_time should not be negative",
+                        "sound.volume should be between 0 and 1"]);
+    }
+
+    #[test]
+    fn custom_sizes_are_checked() {
+        let (config, problems) = Config::parse("[game]\n\
+                                                difficulty = \"custom\"\n\
+                                                width = 2000\n\
+                                                height = 10\n\
+                                                mines = 5\n");
+        assert_eq!(config.difficulty, BEGINNER);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("game: board size 2000x10"), "{}", problems[0]);
+    }
+
+    #[test]
+    fn stacks_have_a_limited_depth() {
+        let layers = |depth: &str| {
+            Config::parse(&format!("[game]\ncells = \"layers\"\nlayers = {}\n", depth))
+        };
+        assert_eq!(layers("6").0.topology, Topology::Layers(6));
+        for depth in ["0", "11", "2.5"].iter() {
+            let (config, problems) = layers(depth);
+            assert_eq!(config.topology, Topology::Layers(DEFAULT_LAYERS));
+            assert_eq!(problems.len(), 1, "{}", depth);
+        }
+    }
+
+    #[test]
+    fn unknown_keys_are_reported() {
+        let (config, problems) = Config::parse("[keys]\n\
+                                                up = [\"Up\", \"Nope\"]\n\
+                                                down = \"S\"\n\
+                                                menu = []\n");
+        assert_eq!(config.keys, KeyBindings::new());
+        assert_eq!(problems,
+                   vec!["keys.up: `Nope` is not a key",
+                        "keys.down should be a list of key names",
+                        "keys.menu needs at least one key"]);
+    }
+
+    #[test]
+    fn keys_kept_by_other_actions_are_refused() {
+        // F still flags by default, so it can't reveal as well.
+        let (config, problems) = Config::parse("[keys]\nreveal = [\"F\"]\n");
+        assert_eq!(problems, vec!["keys.reveal: F is already used for flag"]);
+        assert_eq!(config.keys, KeyBindings::new());
+
+        // The first list to name a key keeps it.
+        let (config, problems) = Config::parse("[keys]\nreveal = [\"G\"]\nflag = [\"G\"]\n");
+        assert_eq!(problems, vec!["keys.flag: G is already used for reveal"]);
+        assert_eq!(config.keys.keys(Action::Reveal), vec![Key::G]);
+        assert_eq!(config.keys.keys(Action::Flag), vec![Key::F]);
+    }
+
+    #[test]
+    fn keys_can_be_swapped() {
+        let (config, problems) = Config::parse("[keys]\nreveal = [\"F\"]\nflag = [\"Space\"]\n");
+        assert!(problems.is_empty(), "{:?}", problems);
+        assert_eq!(config.keys.action(Key::F), Some(Action::Reveal));
+        assert_eq!(config.keys.action(Key::Space), Some(Action::Flag));
+        assert_eq!(config.keys.action(Key::Return), None);
+    }
+
+    #[test]
+    fn settings_survive_a_round_trip() {
+        let mut config = Config::new();
+        config.difficulty = Difficulty {
+            width: 20,
+            height: 10,
+            mines: 30,
+        };
+        config.first_click = FirstClick::Opening;
+        config.neighbours = Offsets::new(&[[2, 0], [1, -2]]).unwrap();
+        config.topology = Topology::Layers(3);
+        config.question_marks = true;
+        config.theme = "dark".to_string();
+        config.number_palette = NumberPalette::HighContrast;
+        config.animations.flood_wave = false;
+        config.animations.confetti = false;
+        config.animations.flip_time = 0.25;
+        config.animations.wave_step = 0.0;
+        config.animations.explosion_time = 1.5;
+        config.sound.volume = 0.6;
+        config.sound.muted = true;
+        config.keys.set(Action::Reveal, &[Key::F]);
+        config.keys.set(Action::Flag, &[Key::Space, Key::G]);
+
+        let (parsed, problems) = Config::parse(&config.to_toml());
+        assert!(problems.is_empty(), "{:?}", problems);
+        for &action in &Action::ALL {
+            assert_eq!(parsed.keys.keys(action), config.keys.keys(action), "{:?}", action);
+        }
+        // The bindings are stored in the order they were set, which the file doesn't keep.
+        assert_eq!(Config { keys: config.keys.clone(), ..parsed }, config);
+    }
+}
@@ -0,0 +1,623 @@
+//! Endless mode, played on a minefield that goes on in every direction.
+//!
+//! The field is cut into square chunks. The bombs in a chunk only depend on the seed and the
+//! chunk's coordinates, so chunks are made when the player first reaches them and always come out
+//! the same.
+
+use std::collections::HashMap;
+use std::mem;
+
+use graphics::types::Color;
+use graphics::ImageSize;
+use piston::input::GenericEvent;
+use rand::Rng;
+
+use field::{self, Field};
+use gameboard::{is_bomb, seeded_rng, CellState};
+use gameboard_controller::ZOOM_STEP;
+use gameboard_view::{draw_tile, GameboardViewSettings};
+use keybindings::{Action, KeyBindings};
+use layout::{MAX_CELL_SIZE, MIN_CELL_SIZE, START_CELL_SIZE};
+use pointer::{Click, Pointer};
+use sprites::{SpriteSheet, Tile};
+use state::State;
+use topology::CellShape;
+use traits::{Canvas, EventHandler, Renderer};
+
+/// Number of columns and rows in a chunk.
+pub const CHUNK_SIZE: i64 = 16;
+/// Number of bombs in each chunk, as dense as the intermediate board.
+pub const CHUNK_MINES: usize = 40;
+
+/// A minefield without edges.
+pub struct EndlessBoard {
+    /// Seed the chunks are made from.
+    pub seed: u64,
+    /// Chunks made so far, by chunk coordinates. Each holds its cells row by row.
+    chunks: HashMap<[i64; 2], Vec<CellState>>,
+    /// Number of cells opened without a bomb, which is the score.
+    pub cleared: usize,
+    /// Whether a bomb has been opened.
+    pub lost: bool,
+}
+
+impl EndlessBoard {
+    /// Creates a field where nothing has been made or opened yet.
+    pub fn new(seed: u64) -> EndlessBoard {
+        EndlessBoard {
+            seed: seed,
+            chunks: HashMap::new(),
+            cleared: 0,
+            lost: false,
+        }
+    }
+
+    /// What a cell holds, or `None` if its chunk hasn't been made yet. Every cell of a chunk
+    /// that wasn't made is still hidden.
+    pub fn peek(&self, pos: [i64; 2]) -> Option<CellState> {
+        let (chunk, index) = locate(pos);
+        self.chunks.get(&chunk).map(|cells| cells[index])
+    }
+
+    /// The cells of a chunk, made first if they don't exist yet.
+    fn chunk(&mut self, chunk: [i64; 2]) -> &mut Vec<CellState> {
+        let seed = self.seed;
+        self.chunks.entry(chunk).or_insert_with(|| make_chunk(seed, chunk))
+    }
+}
+
+impl Field for EndlessBoard {
+    type Pos = [i64; 2];
+
+    fn cell(&mut self, pos: [i64; 2]) -> CellState {
+        let (chunk, index) = locate(pos);
+        self.chunk(chunk)[index]
+    }
+
+    /// Changes a cell, keeping the score and `lost` up to date.
+    fn set_cell(&mut self, pos: [i64; 2], cell: CellState) {
+        let (chunk, index) = locate(pos);
+        let old = mem::replace(&mut self.chunk(chunk)[index], cell);
+        match (old, cell) {
+            (_, CellState::Bomb) => self.lost = true,
+            (CellState::EmptyBlank, _) |
+            (CellState::EmptyNumber(_), _) => {}
+            (_, CellState::EmptyBlank) |
+            (_, CellState::EmptyNumber(_)) => self.cleared += 1,
+            _ => {}
+        }
+    }
+
+    fn neighbours(&mut self, pos: [i64; 2]) -> Vec<[i64; 2]> {
+        let mut result = Vec::with_capacity(8);
+        for y in pos[1] - 1..pos[1] + 2 {
+            for x in pos[0] - 1..pos[0] + 2 {
+                if [x, y] != pos {
+                    result.push([x, y]);
+                }
+            }
+        }
+        result
+    }
+
+    /// Counts the bombs around a cell, making the chunks around it if needed.
+    fn adjacent_mines(&mut self, pos: [i64; 2]) -> u8 {
+        let neighbours = self.neighbours(pos);
+        neighbours.into_iter().filter(|&pos| is_bomb(self.cell(pos))).count() as u8
+    }
+}
+
+/// Splits a cell position into the chunk holding it and its index in that chunk.
+fn locate(pos: [i64; 2]) -> ([i64; 2], usize) {
+    let chunk = [pos[0].div_euclid(CHUNK_SIZE), pos[1].div_euclid(CHUNK_SIZE)];
+    let x = pos[0].rem_euclid(CHUNK_SIZE);
+    let y = pos[1].rem_euclid(CHUNK_SIZE);
+    (chunk, (y * CHUNK_SIZE + x) as usize)
+}
+
+/// Places the bombs of a chunk. The cell at the origin and the ones around it never get one, so
+/// the game can start there.
+fn make_chunk(seed: u64, chunk: [i64; 2]) -> Vec<CellState> {
+    // Mix both coordinates into the seed so that neighbouring chunks look nothing alike.
+    let key = seed ^ (chunk[0] as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) ^
+              (chunk[1] as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f);
+    let mut rng = seeded_rng(key);
+    let area = (CHUNK_SIZE * CHUNK_SIZE) as usize;
+    let mut spots: Vec<usize> = (0..area)
+        .filter(|&index| {
+            let x = chunk[0] * CHUNK_SIZE + index as i64 % CHUNK_SIZE;
+            let y = chunk[1] * CHUNK_SIZE + index as i64 / CHUNK_SIZE;
+            x.abs() > 1 || y.abs() > 1
+        })
+        .collect();
+    rng.shuffle(&mut spots);
+    let mut cells = vec![CellState::HiddenBlank; area];
+    for &index in spots.iter().take(CHUNK_MINES) {
+        cells[index] = CellState::HiddenBomb;
+    }
+    cells
+}
+
+/// Which part of the field is shown. Unlike `Camera` there are no edges to keep inside.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Viewport {
+    /// Size of a cell in pixels.
+    pub cell_size: f64,
+    /// Field position shown in the middle of the window, counted in cells.
+    pub center: [f64; 2],
+}
+
+impl Viewport {
+    /// Shows the starting cell in the middle of the window.
+    pub fn new() -> Viewport {
+        Viewport {
+            cell_size: START_CELL_SIZE,
+            center: [0.5, 0.5],
+        }
+    }
+
+    /// Window position of a cell's left-top corner.
+    pub fn cell_pos(&self, pos: [i64; 2], window_size: (f64, f64)) -> [f64; 2] {
+        [window_size.0 / 2.0 + (pos[0] as f64 - self.center[0]) * self.cell_size,
+         window_size.1 / 2.0 + (pos[1] as f64 - self.center[1]) * self.cell_size]
+    }
+
+    /// Finds the cell under a window coordinate.
+    pub fn cell_at(&self, pos: [f64; 2], window_size: (f64, f64)) -> [i64; 2] {
+        let field = self.field_pos(pos, window_size);
+        [field[0].floor() as i64, field[1].floor() as i64]
+    }
+
+    /// Columns and rows at least partly inside the window, as `[x1, y1, x2, y2]` with the ends
+    /// left out.
+    pub fn visible_cells(&self, window_size: (f64, f64)) -> [i64; 4] {
+        let [x1, y1] = self.cell_at([0.0, 0.0], window_size);
+        let [x2, y2] = self.cell_at([window_size.0, window_size.1], window_size);
+        [x1, y1, x2 + 1, y2 + 1]
+    }
+
+    /// Scales cells by `factor`, keeping the field under the window position `anchor` in place.
+    pub fn zoom(&mut self, factor: f64, anchor: [f64; 2], window_size: (f64, f64)) {
+        let field = self.field_pos(anchor, window_size);
+        self.cell_size = (self.cell_size * factor).max(MIN_CELL_SIZE).min(MAX_CELL_SIZE);
+        self.center = [field[0] - (anchor[0] - window_size.0 / 2.0) / self.cell_size,
+                       field[1] - (anchor[1] - window_size.1 / 2.0) / self.cell_size];
+    }
+
+    /// Moves the field by a distance in pixels, like dragging it.
+    pub fn pan(&mut self, delta: [f64; 2]) {
+        self.center[0] -= delta[0] / self.cell_size;
+        self.center[1] -= delta[1] / self.cell_size;
+    }
+
+    /// Moves as little as needed to show a whole cell.
+    pub fn show_cell(&mut self, cell: [i64; 2], window_size: (f64, f64)) {
+        let half = [window_size.0 / self.cell_size / 2.0, window_size.1 / self.cell_size / 2.0];
+        for axis in 0..2 {
+            let low = cell[axis] as f64 + 1.0 - half[axis];
+            let high = cell[axis] as f64 + half[axis];
+            self.center[axis] = self.center[axis].max(low).min(high);
+        }
+    }
+
+    /// Field position under a window coordinate, counted in cells.
+    fn field_pos(&self, pos: [f64; 2], window_size: (f64, f64)) -> [f64; 2] {
+        [self.center[0] + (pos[0] - window_size.0 / 2.0) / self.cell_size,
+         self.center[1] + (pos[1] - window_size.1 / 2.0) / self.cell_size]
+    }
+}
+
+/// Handles events for endless mode.
+pub struct EndlessController {
+    /// The field being played.
+    pub board: EndlessBoard,
+    /// Which part of the field is shown.
+    pub viewport: Viewport,
+    /// Selected cell.
+    pub selected_cell: Option<[i64; 2]>,
+    /// Cell under the mouse cursor.
+    pub hovered_cell: Option<[i64; 2]>,
+    /// What each key does.
+    pub keys: KeyBindings,
+    /// Whether flagging a flagged cell marks it with a question mark before clearing it.
+    pub question_marks: bool,
+    /// The mouse cursor and buttons.
+    pointer: Pointer,
+}
+
+impl EndlessController {
+    /// Starts a game on a new field, with the area around the origin already open.
+    pub fn new(seed: u64) -> EndlessController {
+        let mut board = EndlessBoard::new(seed);
+        field::open_cells(&mut board, vec![[0, 0]]);
+        EndlessController {
+            board: board,
+            viewport: Viewport::new(),
+            selected_cell: None,
+            hovered_cell: None,
+            keys: KeyBindings::new(),
+            question_marks: false,
+            pointer: Pointer::new(),
+        }
+    }
+
+    /// Opens a cell, unless it is flagged.
+    pub fn reveal(&mut self, pos: [i64; 2]) {
+        match self.board.cell(pos) {
+            CellState::FlaggedBlank | CellState::FlaggedBomb => {}
+            _ => {
+                field::open_cells(&mut self.board, vec![pos]);
+            }
+        }
+    }
+
+    /// Flags a hidden cell, or takes the flag off again.
+    pub fn flag(&mut self, pos: [i64; 2]) {
+        field::toggle_flag(&mut self.board, pos, self.question_marks);
+    }
+
+    /// Opens the hidden neighbours of a revealed number once enough flags are placed around it.
+    pub fn chord(&mut self, pos: [i64; 2]) {
+        field::chord(&mut self.board, pos);
+    }
+
+    /// Whether a cell should be drawn pushed down by a held mouse button.
+    pub fn is_pressed(&self, pos: [i64; 2]) -> bool {
+        let hovered = match self.hovered_cell {
+            Some(hovered) => hovered,
+            None => return false,
+        };
+        let in_reach = self.pointer.is_pressing(pos == hovered, || {
+            (pos[0] - hovered[0]).abs() <= 1 && (pos[1] - hovered[1]).abs() <= 1
+        });
+        match self.board.peek(pos).unwrap_or(CellState::HiddenBlank) {
+            CellState::HiddenBlank |
+            CellState::HiddenBomb |
+            CellState::QuestionBlank |
+            CellState::QuestionBomb => in_reach,
+            _ => false,
+        }
+    }
+
+    /// Handles keyboard play. The cursor starts on the origin.
+    pub fn key_press(&mut self, action: Action) {
+        let pos = match self.selected_cell {
+            Some(pos) => pos,
+            None => {
+                self.selected_cell = Some([0, 0]);
+                return;
+            }
+        };
+        match action {
+            Action::Up => self.selected_cell = Some([pos[0], pos[1] - 1]),
+            Action::Down => self.selected_cell = Some([pos[0], pos[1] + 1]),
+            Action::Left => self.selected_cell = Some([pos[0] - 1, pos[1]]),
+            Action::Right => self.selected_cell = Some([pos[0] + 1, pos[1]]),
+            Action::Reveal => self.reveal(pos),
+            Action::Flag => self.flag(pos),
+            Action::Chord => self.chord(pos),
+            Action::Menu | Action::Screenshot | Action::ZoomIn | Action::ZoomOut |
+            Action::LayerUp | Action::LayerDown => {}
+        }
+    }
+}
+
+impl EventHandler for EndlessController {
+    /// Handles events.
+    fn event<E: GenericEvent>(&mut self, size: (f64, f64), e: &E) -> State {
+        use piston::input::{Button, MouseButton};
+
+        if let Some(pos) = e.mouse_cursor_args() {
+            let delta = self.pointer.move_to(pos);
+            if let Some(moved) = self.pointer.drag(delta, true) {
+                self.viewport.pan(moved);
+            }
+        }
+
+        // Looking around works after losing too.
+        if let Some([_, scroll]) = e.mouse_scroll_args() {
+            self.viewport.zoom(ZOOM_STEP.powf(scroll), self.pointer.cursor_pos, size);
+        }
+        if let Some(Button::Mouse(MouseButton::Left)) = e.press_args() {
+            self.pointer.start_drag();
+        }
+        if let Some(Button::Mouse(MouseButton::Left)) = e.release_args() {
+            self.pointer.end_drag();
+        }
+
+        self.hovered_cell = Some(self.viewport.cell_at(self.pointer.cursor_pos, size));
+
+        let action = match e.press_args() {
+            Some(Button::Keyboard(key)) => self.keys.action(key),
+            _ => None,
+        };
+
+        let middle = [size.0 / 2.0, size.1 / 2.0];
+        match action {
+            // Exit to main menu.
+            Some(Action::Menu) => return State::MainMenu,
+            Some(Action::ZoomIn) => self.viewport.zoom(ZOOM_STEP, middle, size),
+            Some(Action::ZoomOut) => self.viewport.zoom(1.0 / ZOOM_STEP, middle, size),
+            _ => {}
+        }
+
+        // The field stays as it was after a bomb goes off, so the player can see what happened.
+        if self.board.lost {
+            self.pointer.let_go();
+            return State::Endless;
+        }
+
+        // Keyboard cursor movement and actions. The view follows the cursor.
+        if let Some(action) = action {
+            self.key_press(action);
+            if let Some(cell) = self.selected_cell {
+                self.viewport.show_cell(cell, size);
+            }
+        }
+
+        match self.pointer.click(e, self.hovered_cell) {
+            Some(Click::Reveal(pos)) => self.reveal(pos),
+            Some(Click::Flag(pos)) => self.flag(pos),
+            Some(Click::Chord(pos)) => self.chord(pos),
+            None => {}
+        }
+
+        State::Endless
+    }
+}
+
+/// Stores visual information about an endless field.
+pub struct EndlessView<T> {
+    /// Cell colors and sizes, shared with the normal board.
+    pub settings: GameboardViewSettings,
+    /// The endless controller.
+    pub controller: EndlessController,
+    /// Tile atlas to draw cells from. Cells are drawn as shapes when this is `None`.
+    pub sprites: Option<SpriteSheet<T>>,
+    /// Color of the score.
+    pub text_color: Color,
+    /// Font size of the score.
+    pub font_size: u32,
+}
+
+impl<T> EndlessView<T> {
+    /// Creates a new endless view.
+    pub fn new(settings: GameboardViewSettings, controller: EndlessController) -> EndlessView<T> {
+        EndlessView {
+            settings: settings,
+            controller: controller,
+            sprites: None,
+            text_color: [1.0; 4],
+            font_size: 24,
+        }
+    }
+}
+
+impl<T: ImageSize> Renderer<T> for EndlessView<T> {
+    /// Draws the part of the field in the window, and the score over it.
+    fn draw<C: Canvas<Texture = T>>(&self, canvas: &mut C) {
+        let settings = &self.settings;
+        let controller = &self.controller;
+        let viewport = &controller.viewport;
+        let view_size = canvas.size();
+        let window_size = (view_size[0], view_size[1]);
+        let cell_size = viewport.cell_size;
+        let [x1, y1, x2, y2] = viewport.visible_cells(window_size);
+
+        canvas.rectangle(settings.background_color, [0.0, 0.0, view_size[0], view_size[1]], 0.0);
+
+        // Draw cell borders.
+        for x in x1 + 1..x2 {
+            let [left, _] = viewport.cell_pos([x, 0], window_size);
+            let vline = [left, 0.0, left, view_size[1]];
+            canvas.line(settings.cell_edge_color, vline, settings.cell_edge_radius);
+        }
+        for y in y1 + 1..y2 {
+            let [_, top] = viewport.cell_pos([0, y], window_size);
+            let hline = [0.0, top, view_size[0], top];
+            canvas.line(settings.cell_edge_color, hline, settings.cell_edge_radius);
+        }
+
+        // Draw each cell. Chunks nobody has reached yet are all hidden.
+        let lost = controller.board.lost;
+        for y in y1..y2 {
+            for x in x1..x2 {
+                let [cell_x, cell_y] = viewport.cell_pos([x, y], window_size);
+                let rect = [cell_x, cell_y, cell_size, cell_size];
+                let cell = controller.board.peek([x, y]).unwrap_or(CellState::HiddenBlank);
+                let tile = if controller.is_pressed([x, y]) {
+                    Tile::Revealed
+                } else {
+                    Tile::for_cell(cell, lost)
+                };
+                let hovered = controller.hovered_cell == Some([x, y]);
+                draw_tile(settings,
+                          self.sprites.as_ref(),
+                          CellShape::Square,
+                          tile,
+                          rect,
+                          1.0,
+                          hovered,
+                          canvas);
+            }
+        }
+
+        // Draw keyboard cursor
+        if let Some(pos) = controller.selected_cell {
+            let padding = settings.cell_padding * cell_size;
+            let [cell_x, cell_y] = viewport.cell_pos(pos, window_size);
+            let cell_rect = [cell_x + padding / 2.0,
+                             cell_y + padding / 2.0,
+                             cell_size - padding,
+                             cell_size - padding];
+            canvas.border(settings.selected_cell_border_color,
+                          cell_rect,
+                          settings.selected_cell_border_radius);
+        }
+
+        // Draw the score on a strip along the top.
+        let score = if lost {
+            format!("Boom! Cleared {}", controller.board.cleared)
+        } else {
+            format!("Cleared {}", controller.board.cleared)
+        };
+        let height = self.font_size as f64 * 1.5;
+        let width = canvas.text_width(self.font_size, &score) + height;
+        canvas.rectangle(settings.background_color, [0.0, 0.0, width, height], 0.0);
+        canvas.text(self.text_color,
+                    self.font_size,
+                    &score,
+                    [height / 2.0, height / 2.0 + self.font_size as f64 / 3.0]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use piston::input::{Key, MouseButton};
+
+    use config::Config;
+    use mainmenu::MainMenu;
+    use software::Image;
+    use testing::{self, Script};
+    use theme::ThemeSet;
+    use super::*;
+
+    const SIZE: (f64, f64) = (200.0, 200.0);
+
+    /// A cell somewhere around the origin holding a hidden bomb.
+    fn find_bomb(board: &mut EndlessBoard) -> [i64; 2] {
+        for y in -CHUNK_SIZE..CHUNK_SIZE {
+            for x in -CHUNK_SIZE..CHUNK_SIZE {
+                if board.cell([x, y]) == CellState::HiddenBomb {
+                    return [x, y];
+                }
+            }
+        }
+        panic!("no bombs around the origin");
+    }
+
+    #[test]
+    fn positions_split_into_chunks() {
+        assert_eq!(locate([0, 0]), ([0, 0], 0));
+        assert_eq!(locate([15, 1]), ([0, 0], 31));
+        assert_eq!(locate([16, 0]), ([1, 0], 0));
+        assert_eq!(locate([-1, -1]), ([-1, -1], 255));
+        assert_eq!(locate([-16, -17]), ([-1, -2], 15 * 16));
+    }
+
+    #[test]
+    fn chunks_are_made_the_same_from_the_same_seed() {
+        for &chunk in &[[0, 0], [3, -2], [-100, 7]] {
+            assert_eq!(make_chunk(42, chunk), make_chunk(42, chunk));
+            assert!(make_chunk(42, chunk) != make_chunk(43, chunk));
+        }
+        assert!(make_chunk(42, [1, 0]) != make_chunk(42, [0, 1]));
+    }
+
+    #[test]
+    fn every_chunk_has_the_same_number_of_bombs() {
+        for cy in -3..3 {
+            for cx in -3..3 {
+                let bombs = make_chunk(7, [cx, cy]).into_iter().filter(|&cell| is_bomb(cell));
+                assert_eq!(bombs.count(), CHUNK_MINES);
+            }
+        }
+    }
+
+    #[test]
+    fn game_starts_with_the_origin_open() {
+        for seed in 0..20 {
+            let controller = EndlessController::new(seed);
+            assert_eq!(controller.board.peek([0, 0]), Some(CellState::EmptyBlank));
+            assert!(controller.board.cleared >= 9);
+            assert!(!controller.board.lost);
+        }
+    }
+
+    #[test]
+    fn chunks_are_only_made_when_reached() {
+        let mut board = EndlessBoard::new(1);
+        assert_eq!(board.peek([0, 0]), None);
+        let cell = board.cell([1000, -1000]);
+        assert_eq!(board.peek([1000, -1000]), Some(cell));
+        assert!(board.peek([992, -1008]).is_some());
+        assert_eq!(board.peek([1000 - CHUNK_SIZE, -1000]), None);
+        assert_eq!(board.peek([0, 0]), None);
+    }
+
+    #[test]
+    fn numbers_count_bombs_across_chunk_edges() {
+        let mut board = EndlessBoard::new(3);
+        for &pos in &[[-1, -1], [0, -1], [15, 15], [16, 16], [-17, 31]] {
+            if is_bomb(board.cell(pos)) {
+                continue;
+            }
+            let expected = board.neighbours(pos)
+                .into_iter()
+                .filter(|&pos| is_bomb(make_chunk(3, locate(pos).0)[locate(pos).1]))
+                .count() as u8;
+            field::open_cells(&mut board, vec![pos]);
+            match board.cell(pos) {
+                CellState::EmptyNumber(num) => assert_eq!(num, expected),
+                CellState::EmptyBlank => assert_eq!(expected, 0),
+                other => panic!("{:?} at {:?} didn't open", other, pos),
+            }
+        }
+    }
+
+    #[test]
+    fn opening_a_bomb_ends_the_game_and_keeps_the_score() {
+        let mut controller = EndlessController::new(5);
+        let bomb = find_bomb(&mut controller.board);
+        let cleared = controller.board.cleared;
+        controller.reveal(bomb);
+        assert!(controller.board.lost);
+        assert_eq!(controller.board.cleared, cleared);
+        assert_eq!(controller.board.peek(bomb), Some(CellState::Bomb));
+    }
+
+    #[test]
+    fn flags_protect_cells_from_clicks() {
+        let mut controller = EndlessController::new(5);
+        let bomb = find_bomb(&mut controller.board);
+        controller.flag(bomb);
+        controller.reveal(bomb);
+        assert!(!controller.board.lost);
+        assert_eq!(controller.board.peek(bomb), Some(CellState::FlaggedBomb));
+    }
+
+    #[test]
+    fn clicking_reveals_and_dragging_pans() {
+        let mut controller = EndlessController::new(9);
+        let far = [(SIZE.0 - 1.0), (SIZE.1 - 1.0)];
+        let target = controller.viewport.cell_at(far, SIZE);
+        let safe = !is_bomb(controller.board.cell(target));
+        Script::new().click_at(far[0], far[1], MouseButton::Left).run(&mut controller, SIZE);
+        assert_eq!(controller.board.lost, !safe);
+
+        let mut controller = EndlessController::new(9);
+        let cleared = controller.board.cleared;
+        let states = Script::new()
+            .move_to(100.0, 100.0)
+            .press(MouseButton::Left)
+            .move_to(160.0, 130.0)
+            .release(MouseButton::Left)
+            .key(Key::Escape)
+            .run(&mut controller, SIZE);
+        assert_eq!(controller.viewport.center, [0.5 - 60.0 / 24.0, 0.5 - 30.0 / 24.0]);
+        assert_eq!(controller.board.cleared, cleared);
+        // The escape key is pressed and let go as the last two events.
+        assert_eq!(states[states.len() - 2], State::MainMenu);
+        assert!(states[..states.len() - 2].iter().all(|&state| state == State::Endless));
+    }
+
+    #[test]
+    fn endless_board_matches_golden_image() {
+        let menu = MainMenu::new(ThemeSet::find(""), Config::new());
+        let mut view = EndlessView::<Image>::new(menu.board_settings(), EndlessController::new(2));
+        view.text_color = menu.settings.text_color;
+        let size = [SIZE.0 as u32, SIZE.1 as u32];
+        let image = testing::render(&view, size, menu.settings.background_color);
+        testing::check_golden("endless_board", &image);
+    }
+}
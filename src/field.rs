@@ -0,0 +1,96 @@
+//! The rules for opening, flagging and chording, for any kind of board.
+
+use std::collections::VecDeque;
+
+use gameboard::CellState;
+
+/// A board the rules can be played on, whatever its shape or size.
+pub trait Field {
+    /// Identifies a cell.
+    type Pos: Copy + PartialEq;
+
+    /// What a cell holds.
+    fn cell(&mut self, pos: Self::Pos) -> CellState;
+
+    /// Changes what a cell holds.
+    fn set_cell(&mut self, pos: Self::Pos, cell: CellState);
+
+    /// The cells touching a cell.
+    fn neighbours(&mut self, pos: Self::Pos) -> Vec<Self::Pos>;
+
+    /// Number of bombs around a cell.
+    fn adjacent_mines(&mut self, pos: Self::Pos) -> u8;
+}
+
+/// Opens cells, spreading out from every blank one, and returns them in the order they were
+/// opened.
+///
+/// The spreading goes through a queue instead of recursing, so big empty areas can't run out of
+/// stack.
+pub fn open_cells<F: Field>(field: &mut F, start: Vec<F::Pos>) -> Vec<F::Pos> {
+    let mut opened = Vec::new();
+    let mut queue: VecDeque<F::Pos> = start.into_iter().collect();
+    while let Some(pos) = queue.pop_front() {
+        match field.cell(pos) {
+            CellState::HiddenBomb | CellState::QuestionBomb => {
+                field.set_cell(pos, CellState::Bomb);
+                opened.push(pos);
+            }
+            CellState::HiddenBlank | CellState::QuestionBlank => {
+                opened.push(pos);
+                match field.adjacent_mines(pos) {
+                    0 => {
+                        field.set_cell(pos, CellState::EmptyBlank);
+                        let neighbours = field.neighbours(pos);
+                        queue.extend(neighbours);
+                    }
+                    adjacent => field.set_cell(pos, CellState::EmptyNumber(adjacent)),
+                }
+            }
+            _ => {}
+        }
+    }
+    opened
+}
+
+/// Flags a hidden cell, or takes the flag off again. With question marks on, a flag turns into a
+/// question mark first.
+///
+/// Returns `Some(true)` when a flag was placed and `Some(false)` when one was taken off.
+pub fn toggle_flag<F: Field>(field: &mut F, pos: F::Pos, question_marks: bool) -> Option<bool> {
+    use gameboard::CellState::*;
+    let (state, flagged) = match field.cell(pos) {
+        HiddenBomb => (FlaggedBomb, Some(true)),
+        HiddenBlank => (FlaggedBlank, Some(true)),
+        FlaggedBomb if question_marks => (QuestionBomb, Some(false)),
+        FlaggedBlank if question_marks => (QuestionBlank, Some(false)),
+        FlaggedBomb => (HiddenBomb, Some(false)),
+        FlaggedBlank => (HiddenBlank, Some(false)),
+        QuestionBomb => (HiddenBomb, None),
+        QuestionBlank => (HiddenBlank, None),
+        _ => return None,
+    };
+    field.set_cell(pos, state);
+    flagged
+}
+
+/// Opens the hidden neighbours of a revealed number once enough flags are placed around it, and
+/// returns the cells opened.
+pub fn chord<F: Field>(field: &mut F, pos: F::Pos) -> Vec<F::Pos> {
+    let number = match field.cell(pos) {
+        CellState::EmptyNumber(num) => num,
+        _ => return Vec::new(),
+    };
+    let neighbours = field.neighbours(pos);
+    let mut flags = 0;
+    for &neighbour in &neighbours {
+        match field.cell(neighbour) {
+            CellState::FlaggedBomb | CellState::FlaggedBlank => flags += 1,
+            _ => {}
+        }
+    }
+    if flags != number {
+        return Vec::new();
+    }
+    open_cells(field, neighbours)
+}
@@ -1,10 +1,30 @@
 //! Game board logic.
 
-/// Size of the game board.
-pub const BOARDSIZE: usize = 10;
-pub const BOMBCOUNT: usize = 12;
+use std::mem;
 
-#[derive(Copy,Clone)]
+use rand::{Rng, SeedableRng, XorShiftRng};
+
+use field::Field;
+use solver;
+use topology::Topology;
+
+/// Smallest board edge allowed.
+pub const MIN_SIZE: usize = 2;
+/// Largest board edge allowed.
+pub const MAX_SIZE: usize = 1000;
+/// Layouts tried when looking for a board that can be solved without guessing.
+const NO_GUESS_ATTEMPTS: usize = 1000;
+/// Cells the layouts tried for a no-guess board may add up to, so big boards try fewer layouts
+/// instead of taking minutes to deal.
+const NO_GUESS_CELLS: usize = 1_000_000;
+
+/// Creates a random generator that always gives the same numbers for the same seed.
+pub fn seeded_rng(seed: u64) -> XorShiftRng {
+    // XorShift can't start from all zeroes, so half of its state is fixed.
+    XorShiftRng::from_seed([seed as u32, (seed >> 32) as u32, 0x9e37_79b9, 0x243f_6a88])
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 /// Represents the different cell states.
 pub enum CellState {
     // Hidden blank cell.
@@ -14,36 +34,772 @@ pub enum CellState {
     // Empty blank cell.
     EmptyBlank,
     // Empty cell with number of adjacent bombs.
-    EmptyNumber(char),
+    EmptyNumber(u8),
     // An exploding bomb!
     Bomb,
     // A flagged bomb.
     FlaggedBomb,
     // A flagged empty space. Mistakes were made.
     FlaggedBlank,
+    // A hidden bomb marked with a question mark.
+    QuestionBomb,
+    // A hidden empty space marked with a question mark.
+    QuestionBlank,
+}
+
+/// Board size and number of bombs.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Difficulty {
+    /// Number of columns.
+    pub width: usize,
+    /// Number of rows.
+    pub height: usize,
+    /// Number of bombs.
+    pub mines: usize,
+}
+
+/// 9x9 with 10 bombs.
+pub const BEGINNER: Difficulty = Difficulty {
+    width: 9,
+    height: 9,
+    mines: 10,
+};
+/// 16x16 with 40 bombs.
+pub const INTERMEDIATE: Difficulty = Difficulty {
+    width: 16,
+    height: 16,
+    mines: 40,
+};
+/// 30x16 with 99 bombs.
+pub const EXPERT: Difficulty = Difficulty {
+    width: 30,
+    height: 16,
+    mines: 99,
+};
+
+/// 500x500 with 40000 bombs, far too big to fit the window.
+pub const MARATHON: Difficulty = Difficulty {
+    width: 500,
+    height: 500,
+    mines: 40000,
+};
+
+/// The classic difficulties and the marathon board, by name.
+pub const PRESETS: [(&'static str, Difficulty); 4] = [("Beginner", BEGINNER),
+                                                      ("Intermediate", INTERMEDIATE),
+                                                      ("Expert", EXPERT),
+                                                      ("Marathon", MARATHON)];
+
+impl Difficulty {
+    /// Looks up a preset by name, ignoring case.
+    pub fn preset(name: &str) -> Option<Difficulty> {
+        PRESETS.iter()
+            .find(|&&(preset, _)| preset.eq_ignore_ascii_case(name))
+            .map(|&(_, difficulty)| difficulty)
+    }
+
+    /// Name of the matching preset, or `"Custom"`.
+    pub fn name(&self) -> &'static str {
+        PRESETS.iter()
+            .find(|&&(_, preset)| preset == *self)
+            .map_or("Custom", |&(name, _)| name)
+    }
+
+    /// The preset after this one, for cycling through them. Custom sizes go to the first preset.
+    pub fn next(&self) -> Difficulty {
+        let index = PRESETS.iter().position(|&(_, preset)| preset == *self);
+        match index {
+            Some(index) => PRESETS[(index + 1) % PRESETS.len()].1,
+            None => PRESETS[0].1,
+        }
+    }
+
+    /// The board and bombs repeated for every layer of a stack.
+    pub fn stacked(&self, layers: usize) -> Difficulty {
+        Difficulty {
+            width: self.width,
+            height: self.height * layers,
+            mines: self.mines * layers,
+        }
+    }
+
+    /// Checks that the board fits the size limits and has room for its bombs.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.width < MIN_SIZE || self.width > MAX_SIZE || self.height < MIN_SIZE ||
+           self.height > MAX_SIZE {
+            return Err(format!("board size {}x{} is outside {}x{} to {}x{}",
+                               self.width,
+                               self.height,
+                               MIN_SIZE,
+                               MIN_SIZE,
+                               MAX_SIZE,
+                               MAX_SIZE));
+        }
+        if self.mines == 0 || self.mines >= self.width * self.height {
+            return Err(format!("{} bombs don't fit on a {}x{} board",
+                               self.mines,
+                               self.width,
+                               self.height));
+        }
+        Ok(())
+    }
+}
+
+/// Which cells the first click is guaranteed not to hit.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FirstClick {
+    /// Bombs are placed without looking at the first click.
+    Anything,
+    /// The first cell opened is never a bomb.
+    Safe,
+    /// The first cell opened and its neighbours are never bombs, so it opens an area.
+    Opening,
+}
+
+impl FirstClick {
+    /// Every policy, for cycling and parsing.
+    pub const ALL: [FirstClick; 3] = [FirstClick::Anything, FirstClick::Safe, FirstClick::Opening];
+
+    /// Name used in the config file.
+    pub fn id(&self) -> &'static str {
+        match *self {
+            FirstClick::Anything => "anything",
+            FirstClick::Safe => "safe",
+            FirstClick::Opening => "opening",
+        }
+    }
+
+    /// Short name shown in the menu.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            FirstClick::Anything => "Risky start",
+            FirstClick::Safe => "Safe start",
+            FirstClick::Opening => "Open start",
+        }
+    }
+
+    /// Looks up a policy by its config file name.
+    pub fn from_id(id: &str) -> Option<FirstClick> {
+        FirstClick::ALL.iter().cloned().find(|policy| policy.id() == id)
+    }
+
+    /// The policy after this one, for cycling through them.
+    pub fn next(&self) -> FirstClick {
+        match *self {
+            FirstClick::Anything => FirstClick::Safe,
+            FirstClick::Safe => FirstClick::Opening,
+            FirstClick::Opening => FirstClick::Anything,
+        }
+    }
 }
 
 /// Stores game board information.
 pub struct Gameboard {
-    /// Stores the content of the cells.
-    pub cells: [[CellState; BOARDSIZE]; BOARDSIZE],
+    /// Number of columns.
+    pub width: usize,
+    /// Number of rows.
+    pub height: usize,
+    /// Number of bombs, placed or not.
+    pub mines: usize,
+    /// Which cells the first click can't hit.
+    pub first_click: FirstClick,
+    /// Only place bombs so that the board can be solved without guessing.
+    pub no_guess: bool,
+    /// Set when no layout of a no-guess board could be solved without guessing, so the one
+    /// dealt may need a guess.
+    pub needs_guess: bool,
+    /// Shape of the cells. Use `set_topology` to change it once the bombs are placed.
+    pub topology: Topology,
+    /// Seed for placing the bombs.
+    pub seed: u64,
+    /// Whether the bombs have been placed yet. They are placed on the first reveal.
+    pub mines_placed: bool,
+    /// Stores the content of the cells, indexed by row and then by column. Change cells through
+    /// `Field::set_cell`, which keeps the counts behind `is_won` and `is_lost` up to date.
+    pub cells: Vec<Vec<CellState>>,
+    /// Number of bombs around each cell, indexed like `cells`. Counted once the bombs are placed.
+    adjacent: Vec<Vec<u8>>,
+    /// Number of empty cells opened so far.
+    opened: usize,
+    /// Number of bombs opened so far.
+    exploded: usize,
 }
 
 impl Gameboard {
-    /// Creates a new gameboard.
-    pub fn new() -> Gameboard {
-        use rand;
-        use rand::Rng;
+    /// Creates a new gameboard. Bombs are placed from `seed` when the first cell is opened.
+    pub fn new(difficulty: Difficulty, first_click: FirstClick, seed: u64) -> Gameboard {
+        Gameboard {
+            width: difficulty.width,
+            height: difficulty.height,
+            mines: difficulty.mines,
+            first_click: first_click,
+            no_guess: false,
+            needs_guess: false,
+            topology: Topology::Square,
+            seed: seed,
+            mines_placed: false,
+            cells: vec![vec![CellState::HiddenBlank; difficulty.width]; difficulty.height],
+            adjacent: vec![vec![0; difficulty.width]; difficulty.height],
+            opened: 0,
+            exploded: 0,
+        }
+    }
+
+    /// Creates a board with the bombs already placed, from rows of `*` for bombs and `.` for
+    /// empty cells.
+    pub fn from_rows<S: AsRef<str>>(rows: &[S]) -> Result<Gameboard, String> {
+        let mut cells: Vec<Vec<CellState>> = Vec::new();
+        for (y, row) in rows.iter().enumerate() {
+            let row = row.as_ref().trim();
+            let mut cell_row = Vec::new();
+            for ch in row.chars() {
+                cell_row.push(match ch {
+                    '*' => CellState::HiddenBomb,
+                    '.' => CellState::HiddenBlank,
+                    _ => return Err(format!("row {}: `{}` is not `*` or `.`", y + 1, ch)),
+                });
+            }
+            if !cells.is_empty() && cell_row.len() != cells[0].len() {
+                return Err(format!("row {} is {} cells long, the first row is {}",
+                                   y + 1,
+                                   cell_row.len(),
+                                   cells[0].len()));
+            }
+            cells.push(cell_row);
+        }
+        let difficulty = Difficulty {
+            width: cells.first().map_or(0, |row| row.len()),
+            height: cells.len(),
+            mines: cells.iter()
+                .map(|row| row.iter().filter(|&&cell| cell == CellState::HiddenBomb).count())
+                .sum(),
+        };
+        difficulty.validate()?;
+        let mut board = Gameboard::new(difficulty, FirstClick::Anything, 0);
+        board.cells = cells;
+        board.mines_placed = true;
+        board.count_adjacent();
+        Ok(board)
+    }
+
+    /// Rows of `*` for bombs and `.` for everything else, as read by `from_rows`.
+    pub fn mine_rows(&self) -> Vec<String> {
+        self.cells
+            .iter()
+            .map(|row| row.iter().map(|&cell| if is_bomb(cell) { '*' } else { '.' }).collect())
+            .collect()
+    }
+
+    /// Size and bomb count of this board.
+    pub fn difficulty(&self) -> Difficulty {
+        Difficulty {
+            width: self.width,
+            height: self.height,
+            mines: self.mines,
+        }
+    }
+
+    /// Places the bombs, keeping them off the cells the first click policy protects around
+    /// `first`. If there's no room for that, protects fewer cells.
+    ///
+    /// No-guess boards always keep the first cell's neighbours free, and try layouts until one
+    /// can be solved from there. If none is found, the last one tried is used and `needs_guess`
+    /// is set.
+    pub fn place_mines(&mut self, first: [usize; 2]) {
+        let mut rng = seeded_rng(self.seed);
+        let reach = match self.first_click {
+            _ if self.no_guess => Some(1),
+            FirstClick::Anything => None,
+            FirstClick::Safe => Some(0),
+            FirstClick::Opening => Some(1),
+        };
+        let around_first = self.neighbours(first);
+        let protected = |pos: [usize; 2]| match reach {
+            Some(0) => pos == first,
+            Some(_) => pos == first || around_first.contains(&pos),
+            None => false,
+        };
+        let mut all = Vec::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                all.push([x, y]);
+            }
+        }
+        let mut candidates: Vec<[usize; 2]> =
+            all.iter().cloned().filter(|&pos| !protected(pos)).collect();
+        if candidates.len() < self.mines {
+            candidates = all.into_iter().filter(|&pos| pos != first).collect();
+        }
+        let attempts = if self.no_guess {
+            (NO_GUESS_CELLS / (self.width * self.height)).max(1).min(NO_GUESS_ATTEMPTS)
+        } else {
+            1
+        };
+        self.needs_guess = self.no_guess;
+        for _ in 0..attempts {
+            rng.shuffle(&mut candidates);
+            if !self.no_guess ||
+               solver::is_solvable(self.topology,
+                                   (self.width, self.height),
+                                   &candidates[..self.mines],
+                                   first) {
+                self.needs_guess = false;
+                break;
+            }
+        }
+        for &[x, y] in candidates.iter().take(self.mines) {
+            self.cells[y][x] = match self.cells[y][x] {
+                CellState::FlaggedBlank => CellState::FlaggedBomb,
+                CellState::QuestionBlank => CellState::QuestionBomb,
+                _ => CellState::HiddenBomb,
+            };
+        }
+        self.mines_placed = true;
+        self.count_adjacent();
+    }
+
+    /// Changes the shape of the cells, counting the bombs around each cell again.
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+        if self.mines_placed {
+            self.count_adjacent();
+        }
+    }
+
+    /// Number of bombs around a cell. Always 0 before the bombs are placed.
+    pub fn adjacent_mines(&self, pos: [usize; 2]) -> u8 {
+        self.adjacent[pos[1]][pos[0]]
+    }
+
+    /// Counts the bombs around every cell, for `adjacent_mines`.
+    fn count_adjacent(&mut self) {
+        let mut mines = Vec::with_capacity(self.mines);
+        for (y, row) in self.cells.iter().enumerate() {
+            for (x, &cell) in row.iter().enumerate() {
+                if is_bomb(cell) {
+                    mines.push([x, y]);
+                }
+            }
+        }
+        self.adjacent = adjacent_counts(self.topology, (self.width, self.height), &mines);
+    }
+
+    /// The cells touching a cell.
+    pub fn neighbours(&self, pos: [usize; 2]) -> Vec<[usize; 2]> {
+        self.topology.neighbours(pos, (self.width, self.height))
+    }
+
+    /// Whether every empty cell has been opened without setting off a bomb.
+    pub fn is_won(&self) -> bool {
+        self.mines_placed && !self.is_lost() && self.opened == self.width * self.height - self.mines
+    }
 
-        let mut newcells: [[CellState; BOARDSIZE]; BOARDSIZE] =
-            [[CellState::HiddenBlank; BOARDSIZE]; BOARDSIZE];
-        let mut rng = rand::thread_rng();
-        for _ in 0..BOMBCOUNT {
-            let x = rng.gen_range::<usize>(0, BOARDSIZE);
-            let y = rng.gen_range::<usize>(0, BOARDSIZE);
-            newcells[y][x] = CellState::HiddenBomb;
+    /// Whether the game has been won or lost.
+    pub fn is_over(&self) -> bool {
+        self.is_lost() || self.is_won()
+    }
+
+    /// Whether a bomb has been opened.
+    pub fn is_lost(&self) -> bool {
+        self.exploded > 0
+    }
+}
+
+impl Field for Gameboard {
+    type Pos = [usize; 2];
+
+    fn cell(&mut self, pos: [usize; 2]) -> CellState {
+        self.cells[pos[1]][pos[0]]
+    }
+
+    fn set_cell(&mut self, pos: [usize; 2], cell: CellState) {
+        let old = mem::replace(&mut self.cells[pos[1]][pos[0]], cell);
+        match (old, cell) {
+            (CellState::Bomb, CellState::Bomb) => {}
+            (_, CellState::Bomb) => self.exploded += 1,
+            (CellState::Bomb, _) => self.exploded -= 1,
+            _ => {}
+        }
+        match (is_open(old), is_open(cell)) {
+            (false, true) => self.opened += 1,
+            (true, false) => self.opened -= 1,
+            _ => {}
+        }
+    }
+
+    fn neighbours(&mut self, pos: [usize; 2]) -> Vec<[usize; 2]> {
+        Gameboard::neighbours(self, pos)
+    }
+
+    fn adjacent_mines(&mut self, pos: [usize; 2]) -> u8 {
+        Gameboard::adjacent_mines(self, pos)
+    }
+}
+
+/// Number of bombs around every cell of a board, indexed by row and then by column.
+pub fn adjacent_counts(topology: Topology,
+                       cells: (usize, usize),
+                       mines: &[[usize; 2]])
+                       -> Vec<Vec<u8>> {
+    let mut counts = vec![vec![0; cells.0]; cells.1];
+    for &mine in mines {
+        // Cells touch both ways, so the cells around a bomb are the ones it is counted for.
+        for [x, y] in topology.neighbours(mine, cells) {
+            counts[y][x] += 1;
+        }
+    }
+    counts
+}
+
+/// Whether a cell holds a bomb, opened or not.
+pub fn is_bomb(cell: CellState) -> bool {
+    match cell {
+        CellState::HiddenBomb |
+        CellState::Bomb |
+        CellState::FlaggedBomb |
+        CellState::QuestionBomb => true,
+        _ => false,
+    }
+}
+
+/// Whether a cell is an empty one that has been opened.
+fn is_open(cell: CellState) -> bool {
+    match cell {
+        CellState::EmptyBlank | CellState::EmptyNumber(_) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use rand::{Rng, XorShiftRng};
+
+    use field;
+    use gameboard_controller::{GameEvent, GameboardController};
+    use replay::Move;
+    use super::*;
+
+    /// Boards tried by each property. Case `n` always deals the same board, so a failure names
+    /// the case to look at.
+    const CASES: u64 = 300;
+
+    /// A board of random size, bomb count, first click policy, topology and seed.
+    fn random_board(rng: &mut XorShiftRng) -> Gameboard {
+        let topology = *rng.choose(&Topology::ALL).unwrap();
+        let width = rng.gen_range(MIN_SIZE, 31);
+        let height = if topology.layers() > 1 {
+            rng.gen_range(MIN_SIZE, 9) * topology.layers()
+        } else {
+            rng.gen_range(MIN_SIZE, 31)
+        };
+        let difficulty = Difficulty {
+            width: width,
+            height: height,
+            mines: rng.gen_range(1, width * height),
+        };
+        let first_click = *rng.choose(&FirstClick::ALL).unwrap();
+        let mut board = Gameboard::new(difficulty, first_click, rng.next_u64());
+        board.topology = topology;
+        board
+    }
+
+    fn random_cell(rng: &mut XorShiftRng, board: &Gameboard) -> [usize; 2] {
+        [rng.gen_range(0, board.width), rng.gen_range(0, board.height)]
+    }
+
+    /// Bombs next to a cell, worked out from where the cells are drawn instead of from the
+    /// neighbour tables. Drawn cells touch when they share a corner, and knights jump to cells
+    /// whose centers are the square root of five apart. On wrapping boards cells touch when their
+    /// centers are less than one and a half cells apart, taken the short way around. Stacked
+    /// cells touch when they are at most one apart along each of the three axes.
+    fn neighbour_bombs(board: &Gameboard, x: usize, y: usize) -> usize {
+        let topology = board.topology;
+        let center = |pos: [usize; 2]| {
+            let origin = topology.cell_origin(pos, (board.width, board.height));
+            [origin[0] + 0.5, origin[1] + topology.cell_height() / 2.0]
+        };
+        let corners = |pos: [usize; 2]| {
+            let origin = topology.cell_origin(pos, (board.width, board.height));
+            let rect = [origin[0], origin[1], 1.0, topology.cell_height()];
+            topology.shape(pos).outline(rect).unwrap_or_else(|| {
+                vec![[rect[0], rect[1]], [rect[0] + 1.0, rect[1]], [rect[0], rect[1] + 1.0],
+                     [rect[0] + 1.0, rect[1] + 1.0]]
+            })
+        };
+        let apart = |a: f64, b: f64, count: usize| {
+            let apart = (a - b).abs();
+            if topology.wraps() {
+                apart.min(count as f64 - apart)
+            } else {
+                apart
+            }
+        };
+        let touch = |here: [usize; 2], there: [usize; 2]| {
+            let (a, b) = (center(here), center(there));
+            let distance = apart(a[0], b[0], board.width).hypot(apart(a[1], b[1], board.height));
+            let rows = topology.layer_rows((board.width, board.height));
+            let close = |a: usize, b: usize| (a as i64 - b as i64).abs() <= 1;
+            match topology {
+                Topology::Layers(_) => {
+                    close(here[0], there[0]) && close(here[1] % rows, there[1] % rows) &&
+                    close(here[1] / rows, there[1] / rows)
+                }
+                Topology::Knight => (distance - 5f64.sqrt()).abs() < 1e-9,
+                _ if topology.wraps() => distance < 1.5,
+                _ => {
+                    let theirs = corners(there);
+                    corners(here).iter().any(|a| {
+                        theirs.iter().any(|b| (a[0] - b[0]).hypot(a[1] - b[1]) < 1e-9)
+                    })
+                }
+            }
+        };
+        // Stacked cells can touch cells a whole layer of rows away, so every cell is looked at.
+        let reach = if topology.layers() > 1 { board.height as i64 } else { 2 };
+        let mut near = Vec::new();
+        for dy in -reach..reach + 1 {
+            for dx in -2..3 {
+                let (w, h) = (board.width as i64, board.height as i64);
+                let (mut nx, mut ny) = (x as i64 + dx, y as i64 + dy);
+                if topology.wraps() {
+                    nx = (nx + w * 2) % w;
+                    ny = (ny + h * 2) % h;
+                }
+                if nx >= 0 && nx < w && ny >= 0 && ny < h && !near.contains(&[nx, ny]) {
+                    near.push([nx, ny]);
+                }
+            }
+        }
+        let mut count = 0;
+        for [nx, ny] in near {
+            let (nx, ny) = (nx as usize, ny as usize);
+            if [nx, ny] != [x, y] && touch([x, y], [nx, ny]) && is_bomb(board.cells[ny][nx]) {
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Plays random reveals, flags and chords until the game ends or the moves run out.
+    fn play_randomly(rng: &mut XorShiftRng, controller: &mut GameboardController) {
+        for _ in 0..50 {
+            let pos = random_cell(rng, &controller.gameboard);
+            controller.apply(match rng.gen_range(0, 4) {
+                0 => Move::Flag(pos),
+                1 => Move::Chord(pos),
+                _ => Move::Reveal(pos),
+            });
+        }
+    }
+
+    #[test]
+    fn mine_count_is_exact() {
+        for case in 0..CASES {
+            let mut rng = seeded_rng(case);
+            let mut board = random_board(&mut rng);
+            let first = random_cell(&mut rng, &board);
+            board.place_mines(first);
+            let mines = board.cells
+                .iter()
+                .map(|row| row.iter().filter(|&&cell| is_bomb(cell)).count())
+                .sum::<usize>();
+            assert_eq!(mines, board.mines, "case {}", case);
+            if board.first_click != FirstClick::Anything {
+                assert!(!is_bomb(board.cells[first[1]][first[0]]), "case {}", case);
+            }
+        }
+    }
+
+    #[test]
+    fn numbers_match_neighbour_bombs() {
+        for case in 0..CASES {
+            let mut rng = seeded_rng(case);
+            let mut controller = GameboardController::new(random_board(&mut rng));
+            play_randomly(&mut rng, &mut controller);
+            let board = &controller.gameboard;
+            for y in 0..board.height {
+                for x in 0..board.width {
+                    let expected = match board.cells[y][x] {
+                        CellState::EmptyBlank => 0,
+                        CellState::EmptyNumber(n) => n as usize,
+                        _ => continue,
+                    };
+                    assert_eq!(neighbour_bombs(board, x, y),
+                               expected,
+                               "case {} at {}, {}",
+                               case,
+                               x,
+                               y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn adjacent_counts_match_neighbour_bombs() {
+        for case in 0..CASES {
+            let mut rng = seeded_rng(case);
+            let mut board = random_board(&mut rng);
+            let first = random_cell(&mut rng, &board);
+            board.place_mines(first);
+            for y in 0..board.height {
+                for x in 0..board.width {
+                    assert_eq!(board.adjacent_mines([x, y]) as usize,
+                               neighbour_bombs(&board, x, y),
+                               "case {} at {}, {}",
+                               case,
+                               x,
+                               y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn flood_fill_opens_safe_cells_only() {
+        for case in 0..CASES {
+            let mut rng = seeded_rng(case);
+            let mut controller = GameboardController::new(random_board(&mut rng));
+            controller.gameboard.first_click = FirstClick::Safe;
+            let first = random_cell(&mut rng, &controller.gameboard);
+            controller.apply(Move::Reveal(first));
+            let board = &controller.gameboard;
+            assert!(!board.is_lost(), "case {}", case);
+            for y in 0..board.height {
+                for x in 0..board.width {
+                    if board.cells[y][x] != CellState::EmptyBlank {
+                        continue;
+                    }
+                    // A blank cell opened by the fill opens everything around it too.
+                    for [nx, ny] in board.neighbours([x, y]) {
+                        assert!(is_open(board.cells[ny][nx]), "case {} at {}, {}", case, nx, ny);
+                    }
+                }
+            }
         }
+    }
+
+    #[test]
+    fn flagging_twice_changes_nothing() {
+        for case in 0..CASES {
+            let mut rng = seeded_rng(case);
+            let mut controller = GameboardController::new(random_board(&mut rng));
+            play_randomly(&mut rng, &mut controller);
+            if controller.gameboard.is_over() {
+                continue;
+            }
+            let before = controller.gameboard.cells.clone();
+            let pos = random_cell(&mut rng, &controller.gameboard);
+            controller.apply(Move::Flag(pos));
+            controller.apply(Move::Flag(pos));
+            assert_eq!(controller.gameboard.cells, before, "case {}", case);
+
+            // With question marks the cycle takes three steps.
+            controller.question_marks = true;
+            for _ in 0..3 {
+                controller.apply(Move::Flag(pos));
+            }
+            assert_eq!(controller.gameboard.cells, before, "case {}", case);
+        }
+    }
+
+    #[test]
+    fn won_games_have_every_safe_cell_open() {
+        for case in 0..CASES {
+            let mut rng = seeded_rng(case);
+            let mut controller = GameboardController::new(random_board(&mut rng));
+            let first = random_cell(&mut rng, &controller.gameboard);
+            controller.gameboard.place_mines(first);
+            let mut safe = Vec::new();
+            for y in 0..controller.gameboard.height {
+                for x in 0..controller.gameboard.width {
+                    if !is_bomb(controller.gameboard.cells[y][x]) {
+                        safe.push([x, y]);
+                    }
+                }
+            }
+            rng.shuffle(&mut safe);
+            for pos in safe {
+                if is_open(controller.gameboard.cells[pos[1]][pos[0]]) {
+                    continue;
+                }
+                assert!(!controller.gameboard.is_won(), "case {}", case);
+                controller.apply(Move::Reveal(pos));
+            }
+            let board = &controller.gameboard;
+            assert!(board.is_won(), "case {}", case);
+            for row in &board.cells {
+                for &cell in row {
+                    assert!(is_bomb(cell) || is_open(cell), "case {}", case);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn no_guess_boards_can_be_solved_from_the_first_click() {
+        for case in 0..20 {
+            let mut board = Gameboard::new(BEGINNER, FirstClick::Safe, case);
+            board.no_guess = true;
+            board.place_mines([4, 4]);
+            assert!(!board.needs_guess, "case {}", case);
+            let mut mines = Vec::new();
+            for y in 0..9 {
+                for x in 0..9 {
+                    if is_bomb(board.cells[y][x]) {
+                        mines.push([x, y]);
+                    }
+                }
+            }
+            assert!(solver::is_solvable(board.topology, (9, 9), &mines, [4, 4]), "case {}", case);
+        }
+    }
+
+    #[test]
+    fn crowded_no_guess_boards_say_they_may_need_a_guess() {
+        let crowded = Difficulty {
+            width: 8,
+            height: 8,
+            mines: 50,
+        };
+        let mut controller = GameboardController::new(Gameboard::new(crowded, FirstClick::Safe, 1));
+        controller.gameboard.no_guess = true;
+        controller.apply(Move::Reveal([0, 0]));
+        assert!(controller.gameboard.needs_guess);
+        assert!(controller.take_events().contains(&GameEvent::GuessNeeded));
+
+        // Boards that didn't ask for no guessing never say so.
+        let mut board = Gameboard::new(crowded, FirstClick::Safe, 1);
+        board.place_mines([0, 0]);
+        assert!(!board.needs_guess);
+    }
+
+    #[test]
+    fn win_and_loss_follow_the_cells_opened() {
+        let mut board = Gameboard::from_rows(&["*..", "...", "..*"]).unwrap();
+        assert!(!board.is_over());
+        field::open_cells(&mut board, vec![[2, 0]]);
+        assert!(!board.is_over());
+        field::open_cells(&mut board, vec![[0, 2], [1, 0], [0, 1], [1, 1], [2, 1], [1, 2]]);
+        assert!(board.is_won());
+
+        board.set_cell([0, 0], CellState::Bomb);
+        assert!(board.is_lost());
+        assert!(!board.is_won());
+        board.set_cell([2, 1], CellState::HiddenBlank);
+        board.set_cell([0, 0], CellState::HiddenBomb);
+        assert!(!board.is_over());
+    }
 
-        Gameboard { cells: newcells }
+    #[test]
+    fn no_guess_marathons_deal_in_bounded_time() {
+        let start = Instant::now();
+        let mut board = Gameboard::new(MARATHON, FirstClick::Safe, 3);
+        board.no_guess = true;
+        board.place_mines([250, 250]);
+        assert!(board.mines_placed);
+        assert!(start.elapsed() < Duration::from_secs(60), "took {:?}", start.elapsed());
     }
 }
@@ -1,138 +1,751 @@
-//! Gameboard controller.
-
-use piston::input::GenericEvent;
-use piston::input::keyboard::Key;
-use Gameboard;
-use gameboard::{CellState, BOARDSIZE};
-use traits::EventHandler;
-use state::State;
-
-/// Handles events for the game.
-pub struct GameboardController {
-    /// Stores the state of the board.
-    pub gameboard: Gameboard,
-    /// Selected cell.
-    pub selected_cell: Option<[usize; 2]>,
-    /// Stores last mouse cursor position.
-    cursor_pos: [f64; 2],
-}
-
-impl GameboardController {
-    /// Crates a new gameboard controller.
-    pub fn new(gameboard: Gameboard) -> GameboardController {
-        GameboardController {
-            gameboard: gameboard,
-            selected_cell: None,
-            cursor_pos: [0.0; 2],
-        }
-    }
-
-    fn open_cell(&mut self, x: usize, y: usize) {
-        match self.gameboard.cells[y][x] {
-            CellState::HiddenBomb => self.gameboard.cells[y][x] = CellState::Bomb,
-            CellState::HiddenBlank => {
-                let adjacent = self.count_adjacent_bombs(y, x);
-                match adjacent {
-                    0 => {
-                        self.gameboard.cells[y][x] = CellState::EmptyBlank;
-                        for dy in -1..2 as i8 {
-                            for dx in -1..2 as i8 {
-                                if self.is_valid_cell(x as i8 + dx, y as i8 + dy) {
-                                    self.open_cell((x as i8 + dx) as usize,
-                                                   (y as i8 + dy) as usize);
-                                }
-                            }
-                        }
-                    }
-                    _ => {
-                        self.gameboard.cells[y][x] = CellState::EmptyNumber((adjacent + 48) as char)
-                    }
-                }
-            }
-            _ => {}
-        }
-    }
-
-    fn flag_cell(&mut self, x: usize, y: usize) {
-        self.gameboard.cells[y][x] = match self.gameboard.cells[y][x] {
-            CellState::HiddenBomb => CellState::FlaggedBomb,
-            CellState::HiddenBlank => CellState::FlaggedBlank,
-            CellState::FlaggedBomb => CellState::HiddenBomb,
-            CellState::FlaggedBlank => CellState::HiddenBlank,
-            _ => self.gameboard.cells[y][x],
-        }
-    }
-
-    fn get_selected_cell(&self, size: f64) -> Option<(usize, usize)> {
-        // Find coordinates relative to upper left corner.
-        let x = self.cursor_pos[0];
-        let y = self.cursor_pos[1];
-        // Check that coordinates are inside the board.
-        if x >= 0.0 && x < size && y >= 0.0 && y < size {
-            let cell_x = (x / size * 10.0) as usize;
-            let cell_y = (y / size * 10.0) as usize;
-            return Some((cell_x, cell_y));
-        }
-        None
-    }
-
-    fn is_valid_cell(&self, x: i8, y: i8) -> bool {
-        x >= 0 && x < BOARDSIZE as i8 && y >= 0 && y < BOARDSIZE as i8
-    }
-
-    fn count_adjacent_bombs(&self, x: usize, y: usize) -> u8 {
-        use gameboard::CellState::*;
-        let mut count: u8 = 0;
-        for dy in -1..2 as i8 {
-            for dx in -1..2 as i8 {
-                let newx = x as i8 + dx;
-                let newy = y as i8 + dy;
-                if newx >= 0 && newy >= 0 && newx < BOARDSIZE as i8 && newy < BOARDSIZE as i8 &&
-                   (dx != 0 || dy != 0) {
-                    let celltype = self.gameboard.cells[newx as usize][newy as usize];
-                    count += match celltype {
-                        Bomb | HiddenBomb | FlaggedBomb => 1,
-                        _ => 0,
-                    }
-                }
-            }
-        }
-        count
-    }
-}
-
-impl EventHandler for GameboardController {
-    /// Handles events.
-    fn event<E: GenericEvent>(&mut self, size: (f64, f64), e: &E) -> State {
-        use piston::input::{Button, MouseButton};
-
-        if let Some(pos) = e.mouse_cursor_args() {
-            self.cursor_pos = pos;
-        }
-
-        // Left click
-        if let Some(Button::Mouse(MouseButton::Left)) = e.press_args() {
-
-            // Check that coordinates are inside the board.
-            if let Some(pos) = self.get_selected_cell(size.0) {
-                self.open_cell(pos.0, pos.1);
-            }
-        }
-
-        // Exit to main menu when you press ESC.
-        if let Some(Button::Keyboard(Key::Escape)) = e.press_args() {
-            return State::MainMenu;
-        }
-
-        // Right click
-        if let Some(Button::Mouse(MouseButton::Right)) = e.press_args() {
-
-            // Check that coordinates are inside the board.
-            if let Some(pos) = self.get_selected_cell(size.0) {
-                self.flag_cell(pos.0, pos.1);
-            }
-        }
-
-        State::GameBoard
-    }
-}
+//! Gameboard controller.
+
+use std::mem;
+
+use piston::input::GenericEvent;
+use Gameboard;
+use field;
+use gameboard::CellState;
+use keybindings::{Action, KeyBindings};
+use layout::{BoardLayout, Camera, Minimap};
+use pointer::{Click, Pointer};
+use replay::Move;
+use traits::EventHandler;
+use state::State;
+
+/// How much one step of the scroll wheel or the zoom keys scales the board.
+pub const ZOOM_STEP: f64 = 1.25;
+/// Printed when a no-guess board was asked for but none could be dealt.
+pub const GUESS_WARNING: &'static str =
+    "no board without guesses was found, this one may need a guess";
+
+/// Something that happened on the board, for animations and sounds to react to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameEvent {
+    /// Cells were opened, spreading out from `origin`. More than one cell means a flood fill.
+    Revealed {
+        /// The cell the player acted on.
+        origin: [usize; 2],
+        /// Every cell that was opened, bombs excluded.
+        cells: Vec<[usize; 2]>,
+    },
+    /// A flag was placed.
+    Flagged([usize; 2]),
+    /// A flag was removed.
+    Unflagged([usize; 2]),
+    /// A bomb was opened.
+    Exploded([usize; 2]),
+    /// The last empty cell was opened.
+    Won,
+    /// A no-guess board was asked for, but the bombs had to be placed in a way that may need a
+    /// guess.
+    GuessNeeded,
+}
+
+/// Handles events for the game.
+pub struct GameboardController {
+    /// Stores the state of the board.
+    pub gameboard: Gameboard,
+    /// Selected cell.
+    pub selected_cell: Option<[usize; 2]>,
+    /// Cell under the mouse cursor.
+    pub hovered_cell: Option<[usize; 2]>,
+    /// What each key does.
+    pub keys: KeyBindings,
+    /// Whether flagging a flagged cell marks it with a question mark before clearing it.
+    pub question_marks: bool,
+    /// Ignore the player's moves, for watching a replay.
+    pub locked: bool,
+    /// Every move made on the board so far.
+    pub moves: Vec<Move>,
+    /// Which part of the board is shown.
+    pub camera: Camera,
+    /// The layer being played on stacked boards. The others are only shown.
+    pub layer: usize,
+    /// The mouse cursor and buttons.
+    pointer: Pointer,
+    /// Whether the held left button moves the view around the minimap.
+    on_minimap: bool,
+    /// Events not yet taken by the view.
+    events: Vec<GameEvent>,
+}
+
+impl GameboardController {
+    /// Crates a new gameboard controller.
+    pub fn new(gameboard: Gameboard) -> GameboardController {
+        let camera = Camera::for_board((gameboard.width, gameboard.height), gameboard.topology);
+        GameboardController {
+            gameboard: gameboard,
+            selected_cell: None,
+            hovered_cell: None,
+            keys: KeyBindings::new(),
+            question_marks: false,
+            locked: false,
+            moves: Vec::new(),
+            camera: camera,
+            layer: 0,
+            pointer: Pointer::new(),
+            on_minimap: false,
+            events: Vec::new(),
+        }
+    }
+
+    /// Takes the events that happened since the last call.
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        mem::replace(&mut self.events, Vec::new())
+    }
+
+    /// Makes a move on the board, keeping it in `moves`, and returns the cells it opened. Does
+    /// nothing once the game is over.
+    pub fn apply(&mut self, mv: Move) -> Vec<[usize; 2]> {
+        let [x, y] = mv.pos();
+        if self.gameboard.is_over() || x >= self.gameboard.width || y >= self.gameboard.height {
+            return Vec::new();
+        }
+        self.moves.push(mv);
+        match mv {
+            Move::Reveal(_) => self.reveal(x, y),
+            Move::Flag(_) => {
+                self.flag_cell(x, y);
+                Vec::new()
+            }
+            Move::Chord(_) => self.chord_cell(x, y),
+        }
+    }
+
+    /// Whether a cell should be drawn pushed down by a held mouse button.
+    pub fn is_pressed(&self, x: usize, y: usize) -> bool {
+        let [hx, hy] = match self.hovered_cell {
+            Some(pos) => pos,
+            None => return false,
+        };
+        let in_reach = self.pointer.is_pressing([x, y] == [hx, hy], || {
+            self.gameboard.neighbours([hx, hy]).contains(&[x, y])
+        });
+        match self.gameboard.cells[y][x] {
+            CellState::HiddenBlank |
+            CellState::HiddenBomb |
+            CellState::QuestionBlank |
+            CellState::QuestionBomb => in_reach,
+            _ => false,
+        }
+    }
+
+    /// Opens a cell the player picked, placing the bombs first if this is the first one.
+    fn reveal(&mut self, x: usize, y: usize) -> Vec<[usize; 2]> {
+        match self.gameboard.cells[y][x] {
+            CellState::FlaggedBlank | CellState::FlaggedBomb => return Vec::new(),
+            _ => {}
+        }
+        if !self.gameboard.mines_placed {
+            self.gameboard.place_mines([x, y]);
+            if self.gameboard.needs_guess {
+                self.events.push(GameEvent::GuessNeeded);
+            }
+        }
+        let opened = field::open_cells(&mut self.gameboard, vec![[x, y]]);
+        self.finish_opening([x, y], &opened);
+        opened
+    }
+
+    /// Turns the cells opened by an action into events.
+    fn finish_opening(&mut self, origin: [usize; 2], opened: &[[usize; 2]]) {
+        let (bombs, cells): (Vec<[usize; 2]>, Vec<[usize; 2]>) =
+            opened.iter().cloned().partition(|pos| match self.gameboard.cells[pos[1]][pos[0]] {
+                CellState::Bomb => true,
+                _ => false,
+            });
+        if !cells.is_empty() {
+            self.events.push(GameEvent::Revealed {
+                origin: origin,
+                cells: cells,
+            });
+        }
+        for pos in bombs {
+            self.events.push(GameEvent::Exploded(pos));
+        }
+        if self.gameboard.is_won() {
+            self.events.push(GameEvent::Won);
+        }
+    }
+
+    /// Flags a hidden cell, or takes the flag off again.
+    fn flag_cell(&mut self, x: usize, y: usize) {
+        match field::toggle_flag(&mut self.gameboard, [x, y], self.question_marks) {
+            Some(true) => self.events.push(GameEvent::Flagged([x, y])),
+            Some(false) => self.events.push(GameEvent::Unflagged([x, y])),
+            None => {}
+        }
+    }
+
+    /// Opens the hidden neighbours of a revealed number once enough flags are placed around it.
+    fn chord_cell(&mut self, x: usize, y: usize) -> Vec<[usize; 2]> {
+        let opened = field::chord(&mut self.gameboard, [x, y]);
+        self.finish_opening([x, y], &opened);
+        opened
+    }
+
+    /// Moves the keyboard cursor, starting it in the middle of the board if it isn't shown yet.
+    fn move_selection(&mut self, dx: i64, dy: i64) {
+        self.selected_cell = match self.selected_cell {
+            Some([x, y]) => {
+                let mut newx = x as i64 + dx;
+                let mut newy = y as i64 + dy;
+                // The cursor goes over the edges of wrapping boards like everything else does.
+                if self.gameboard.topology.wraps() {
+                    let width = self.gameboard.width as i64;
+                    let height = self.gameboard.height as i64;
+                    newx = (newx + width) % width;
+                    newy = (newy + height) % height;
+                }
+                // It stays in its layer too, which only the layer keys change.
+                if self.is_valid_cell(newx, newy) &&
+                   self.layer_of([newx as usize, newy as usize]) == self.layer {
+                    Some([newx as usize, newy as usize])
+                } else {
+                    Some([x, y])
+                }
+            }
+            None => {
+                let rows = self.gameboard.topology.layer_rows(self.board_size());
+                Some([self.gameboard.width / 2, self.layer * rows + rows / 2])
+            }
+        }
+    }
+
+    /// Switches to another layer of a stacked board, taking the keyboard cursor along.
+    fn change_layer(&mut self, layer: usize) {
+        let topology = self.gameboard.topology;
+        let layer = layer.min(topology.layers() - 1);
+        let rows = topology.layer_rows(self.board_size());
+        if let Some([x, y]) = self.selected_cell {
+            self.selected_cell = Some([x, layer * rows + y % rows]);
+        }
+        self.layer = layer;
+    }
+
+    fn layer_of(&self, pos: [usize; 2]) -> usize {
+        self.gameboard.topology.layer_of(pos, self.board_size())
+    }
+
+    /// Centers the camera on the layer being played.
+    fn show_layer(&mut self, size: (f64, f64)) {
+        let cells = self.board_size();
+        let topology = self.gameboard.topology;
+        let rows = topology.layer_rows(cells);
+        let origin = topology.cell_origin([0, self.layer * rows], cells);
+        let middle = [origin[0] + cells.0 as f64 / 2.0, origin[1] + rows as f64 / 2.0];
+        self.camera.look_at(middle, size, cells, topology);
+    }
+
+    /// Handles keyboard play.
+    pub fn key_press(&mut self, action: Action) {
+        match action {
+            Action::Up => self.move_selection(0, -1),
+            Action::Down => self.move_selection(0, 1),
+            Action::Left => self.move_selection(-1, 0),
+            Action::Right => self.move_selection(1, 0),
+            Action::LayerUp => {
+                let layer = self.layer.saturating_sub(1);
+                self.change_layer(layer);
+            }
+            Action::LayerDown => {
+                let layer = self.layer + 1;
+                self.change_layer(layer);
+            }
+            Action::Reveal | Action::Flag | Action::Chord => {
+                if let Some(pos) = self.selected_cell {
+                    self.apply(match action {
+                        Action::Flag => Move::Flag(pos),
+                        Action::Chord => Move::Chord(pos),
+                        _ => Move::Reveal(pos),
+                    });
+                } else {
+                    self.move_selection(0, 0);
+                }
+            }
+            Action::Menu | Action::Screenshot | Action::ZoomIn | Action::ZoomOut => {}
+        }
+    }
+
+    fn get_selected_cell(&self, size: (f64, f64)) -> Option<[usize; 2]> {
+        if self.minimap_pos(size).is_some() {
+            return None;
+        }
+        BoardLayout::with_camera(size, self.board_size(), self.gameboard.topology, &self.camera)
+            .cell_at(self.pointer.cursor_pos)
+            .filter(|&cell| self.layer_of(cell) == self.layer)
+    }
+
+    /// The layer under the cursor, on stacked boards.
+    fn layer_under_cursor(&self, size: (f64, f64)) -> Option<usize> {
+        BoardLayout::with_camera(size, self.board_size(), self.gameboard.topology, &self.camera)
+            .cell_at(self.pointer.cursor_pos)
+            .map(|cell| self.layer_of(cell))
+    }
+
+    /// Board position under the cursor if it is on the minimap, which is only shown while zoomed
+    /// in.
+    fn minimap_pos(&self, size: (f64, f64)) -> Option<[f64; 2]> {
+        if !self.camera.is_zoomed() {
+            return None;
+        }
+        Minimap::new(size, self.board_size(), self.gameboard.topology)
+            .board_pos(self.pointer.cursor_pos)
+    }
+
+    fn board_size(&self) -> (usize, usize) {
+        (self.gameboard.width, self.gameboard.height)
+    }
+
+    /// Follows the mouse with the left button held, moving the view when it drags the board or
+    /// the minimap.
+    fn drag(&mut self, delta: [f64; 2], size: (f64, f64)) {
+        let cells = self.board_size();
+        let topology = self.gameboard.topology;
+        if self.on_minimap {
+            if let Some(pos) = self.minimap_pos(size) {
+                self.camera.look_at(pos, size, cells, topology);
+            }
+            return;
+        }
+        if let Some(moved) = self.pointer.drag(delta, self.camera.is_zoomed()) {
+            self.camera.pan(moved, size, cells, topology);
+        }
+    }
+
+    fn is_valid_cell(&self, x: i64, y: i64) -> bool {
+        x >= 0 && (x as usize) < self.gameboard.width && y >= 0 &&
+        (y as usize) < self.gameboard.height
+    }
+}
+
+impl EventHandler for GameboardController {
+    /// Handles events.
+    fn event<E: GenericEvent>(&mut self, size: (f64, f64), e: &E) -> State {
+        use piston::input::{Button, MouseButton};
+
+        let cells = self.board_size();
+        let topology = self.gameboard.topology;
+        if let Some(pos) = e.mouse_cursor_args() {
+            let delta = self.pointer.move_to(pos);
+            self.drag(delta, size);
+        }
+
+        // Looking around works on finished and locked boards too.
+        if let Some([_, scroll]) = e.mouse_scroll_args() {
+            let cursor = self.pointer.cursor_pos;
+            self.camera.zoom(ZOOM_STEP.powf(scroll), cursor, size, cells, topology);
+        }
+        if let Some(Button::Mouse(MouseButton::Left)) = e.press_args() {
+            if let Some(pos) = self.minimap_pos(size) {
+                self.on_minimap = true;
+                self.camera.look_at(pos, size, cells, topology);
+                self.hovered_cell = None;
+                return State::GameBoard;
+            }
+            // Clicking a layer that is only shown switches to it.
+            match self.layer_under_cursor(size) {
+                Some(layer) if layer != self.layer => {
+                    self.change_layer(layer);
+                    self.show_layer(size);
+                    self.hovered_cell = None;
+                    return State::GameBoard;
+                }
+                _ => {}
+            }
+            self.pointer.start_drag();
+        }
+        if let Some(Button::Mouse(MouseButton::Left)) = e.release_args() {
+            self.pointer.end_drag();
+            self.on_minimap = false;
+        }
+
+        // Recheck on every event since resizing the window or moving the camera moves the board
+        // too.
+        self.hovered_cell = self.get_selected_cell(size);
+
+        let action = match e.press_args() {
+            Some(Button::Keyboard(key)) => self.keys.action(key),
+            _ => None,
+        };
+
+        let middle = [size.0 / 2.0, size.1 / 2.0];
+        match action {
+            // Exit to main menu.
+            Some(Action::Menu) => return State::MainMenu,
+            Some(Action::ZoomIn) => self.camera.zoom(ZOOM_STEP, middle, size, cells, topology),
+            Some(Action::ZoomOut) => {
+                self.camera.zoom(1.0 / ZOOM_STEP, middle, size, cells, topology)
+            }
+            Some(action @ Action::LayerUp) | Some(action @ Action::LayerDown) => {
+                // Flat boards have no other layer to show, so the camera stays where it is.
+                if topology.layers() > 1 {
+                    self.key_press(action);
+                    self.show_layer(size);
+                }
+            }
+            _ => {}
+        }
+
+        // The board stays as it was once the game is over, so a loss shows what went wrong. Locked
+        // boards ignore the player too.
+        if self.locked || self.gameboard.is_over() {
+            self.pointer.let_go();
+            return State::GameBoard;
+        }
+
+        // Keyboard cursor movement and actions. The camera follows the cursor.
+        if let Some(action) = action.filter(|&action| {
+            action != Action::LayerUp && action != Action::LayerDown
+        }) {
+            self.key_press(action);
+            if let Some(cell) = self.selected_cell {
+                self.camera.show_cell(cell, size, cells, topology);
+            }
+        }
+
+        if let Some(click) = self.pointer.click(e, self.hovered_cell) {
+            self.apply(match click {
+                Click::Reveal(pos) => Move::Reveal(pos),
+                Click::Flag(pos) => Move::Flag(pos),
+                Click::Chord(pos) => Move::Chord(pos),
+            });
+        }
+
+        State::GameBoard
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use piston::input::{Key, MouseButton};
+
+    use gameboard::CellState::*;
+    use gameboard_view::{GameboardView, GameboardViewSettings};
+    use software::{render_board, Image};
+    use testing::{check_golden, Script};
+    use theme::Theme;
+    use topology::Topology;
+    use super::*;
+
+    /// A 4x4 board drawn with 10 pixel cells.
+    const SIZE: (f64, f64) = (40.0, 40.0);
+
+    fn controller() -> GameboardController {
+        GameboardController::new(Gameboard::from_rows(&["...*", "....", "*.*.", "...."]).unwrap())
+    }
+
+    /// Window coordinate of a cell's middle.
+    fn cell(x: usize, y: usize) -> (f64, f64) {
+        (x as f64 * 10.0 + 5.0, y as f64 * 10.0 + 5.0)
+    }
+
+    fn click(script: Script, pos: (f64, f64), button: MouseButton) -> Script {
+        script.click_at(pos.0, pos.1, button)
+    }
+
+    #[test]
+    fn left_click_reveals_on_release() {
+        let mut controller = controller();
+        let (x, y) = cell(0, 0);
+        Script::new().move_to(x, y).press(MouseButton::Left).run(&mut controller, SIZE);
+        assert_eq!(controller.gameboard.cells[0][0], HiddenBlank);
+        assert!(controller.is_pressed(0, 0));
+
+        Script::new().release(MouseButton::Left).run(&mut controller, SIZE);
+        assert_eq!(controller.gameboard.cells[0][0], EmptyBlank);
+        assert_eq!(controller.gameboard.cells[1][1], EmptyNumber(2));
+        assert_eq!(controller.moves, vec![Move::Reveal([0, 0])]);
+    }
+
+    #[test]
+    fn dragging_off_cancels_reveal() {
+        let mut controller = controller();
+        let (x, y) = cell(1, 1);
+        Script::new()
+            .move_to(x, y)
+            .press(MouseButton::Left)
+            .move_to(60.0, y)
+            .release(MouseButton::Left)
+            .run(&mut controller, SIZE);
+        assert!(controller.moves.is_empty());
+    }
+
+    #[test]
+    fn right_click_flags_and_unflags() {
+        let mut controller = controller();
+        click(Script::new(), cell(3, 0), MouseButton::Right).run(&mut controller, SIZE);
+        assert_eq!(controller.gameboard.cells[0][3], FlaggedBomb);
+        assert_eq!(controller.take_events(), vec![GameEvent::Flagged([3, 0])]);
+
+        click(Script::new(), cell(3, 0), MouseButton::Right).run(&mut controller, SIZE);
+        assert_eq!(controller.gameboard.cells[0][3], HiddenBomb);
+    }
+
+    #[test]
+    fn both_buttons_chord() {
+        let mut controller = controller();
+        let script = click(Script::new(), cell(2, 0), MouseButton::Left);
+        let script = click(script, cell(3, 0), MouseButton::Right);
+        let (x, y) = cell(2, 0);
+        script.move_to(x, y)
+            .press(MouseButton::Left)
+            .press(MouseButton::Right)
+            .release(MouseButton::Right)
+            .release(MouseButton::Left)
+            .run(&mut controller, SIZE);
+        assert_eq!(controller.moves,
+                   vec![Move::Reveal([2, 0]), Move::Flag([3, 0]), Move::Chord([2, 0])]);
+        assert_eq!(controller.gameboard.cells[1][3], EmptyNumber(2));
+    }
+
+    #[test]
+    fn keyboard_moves_cursor_and_reveals() {
+        let mut controller = controller();
+        Script::new()
+            .key(Key::Space)
+            .key(Key::Up)
+            .key(Key::Left)
+            .key(Key::Left)
+            .key(Key::Left)
+            .key(Key::Return)
+            .run(&mut controller, SIZE);
+        assert_eq!(controller.selected_cell, Some([0, 1]));
+        assert_eq!(controller.moves, vec![Move::Reveal([0, 1])]);
+    }
+
+    #[test]
+    fn escape_goes_to_menu() {
+        let states = Script::new().key(Key::Escape).run(&mut controller(), SIZE);
+        assert_eq!(states, vec![State::MainMenu, State::GameBoard]);
+    }
+
+    #[test]
+    fn finished_and_locked_boards_ignore_clicks() {
+        let mut controller = controller();
+        controller.locked = true;
+        click(Script::new(), cell(0, 0), MouseButton::Left).run(&mut controller, SIZE);
+        assert!(controller.moves.is_empty());
+
+        controller.locked = false;
+        let script = click(Script::new(), cell(0, 2), MouseButton::Left);
+        click(script, cell(0, 0), MouseButton::Left).run(&mut controller, SIZE);
+        assert!(controller.gameboard.is_lost());
+        assert_eq!(controller.moves, vec![Move::Reveal([0, 2])]);
+    }
+
+    #[test]
+    fn clicks_on_hexagons_follow_the_shifted_rows() {
+        let mut board = Gameboard::from_rows(&["...*", "....", "*.*.", "...."]).unwrap();
+        board.set_topology(Topology::Hex);
+        let mut controller = GameboardController::new(board);
+        let layout = BoardLayout::with_camera(SIZE, (4, 4), Topology::Hex, &controller.camera);
+        // Odd rows are pushed half a cell right, so the left edge of row 1 is empty.
+        let [x, y, _, height] = layout.cell_rect(0, 1);
+        let size = layout.cell_size();
+        click(Script::new(), (x - size / 4.0, y + height / 2.0), MouseButton::Left)
+            .run(&mut controller, SIZE);
+        assert!(controller.moves.is_empty());
+        click(Script::new(), (x + size / 2.0, y + height / 2.0), MouseButton::Left)
+            .run(&mut controller, SIZE);
+        assert_eq!(controller.moves, vec![Move::Reveal([0, 1])]);
+        // Six neighbours, two of them bombs.
+        assert_eq!(controller.gameboard.cells[1][0], EmptyNumber(1));
+    }
+
+    #[test]
+    fn ghost_cells_play_the_opposite_edge() {
+        let mut board = Gameboard::from_rows(&["...*", "....", "*.*.", "...."]).unwrap();
+        board.set_topology(Topology::Torus);
+        let mut controller = GameboardController::new(board);
+        // The board and its ghost ring are six cells across, so cells are drawn 40 / 6 wide.
+        let size = SIZE.0 / 6.0;
+        click(Script::new(), (size / 2.0, size * 1.5), MouseButton::Right)
+            .run(&mut controller, SIZE);
+        assert_eq!(controller.moves, vec![Move::Flag([3, 0])]);
+        // Numbers count across the edges: this cell touches the bomb in the first column.
+        controller.apply(Move::Reveal([3, 1]));
+        assert_eq!(controller.gameboard.cells[1][3], EmptyNumber(3));
+
+        controller.selected_cell = Some([0, 0]);
+        controller.key_press(Action::Left);
+        assert_eq!(controller.selected_cell, Some([3, 0]));
+    }
+
+    fn layered_controller() -> GameboardController {
+        let rows = ["...", "..*", "...", "*..", "...", "..."];
+        let mut board = Gameboard::from_rows(&rows).unwrap();
+        board.set_topology(Topology::Layers(2));
+        GameboardController::new(board)
+    }
+
+    #[test]
+    fn layer_keys_take_the_cursor_along() {
+        let mut controller = layered_controller();
+        Script::new().key(Key::Space).key(Key::PageDown).run(&mut controller, SIZE);
+        assert_eq!((controller.layer, controller.selected_cell), (1, Some([1, 4])));
+        // There is no layer below the last one, and the cursor can't walk out of its layer.
+        Script::new()
+            .key(Key::PageDown)
+            .key(Key::Up)
+            .key(Key::Up)
+            .run(&mut controller, SIZE);
+        assert_eq!((controller.layer, controller.selected_cell), (1, Some([1, 3])));
+        Script::new().key(Key::PageUp).key(Key::Return).run(&mut controller, SIZE);
+        assert_eq!(controller.moves, vec![Move::Reveal([1, 0])]);
+    }
+
+    #[test]
+    fn clicking_another_layer_switches_to_it() {
+        let mut controller = layered_controller();
+        let cells = (3, 6);
+        let layout = BoardLayout::with_camera(SIZE, cells, Topology::Layers(2), &controller.camera);
+        let center = |x, y| {
+            let [cell_x, cell_y, width, height] = layout.cell_rect(x, y);
+            (cell_x + width / 2.0, cell_y + height / 2.0)
+        };
+        click(Script::new(), center(0, 3), MouseButton::Left).run(&mut controller, SIZE);
+        assert_eq!(controller.layer, 1);
+        assert!(controller.moves.is_empty());
+        click(Script::new(), center(0, 0), MouseButton::Left).run(&mut controller, SIZE);
+        assert_eq!(controller.layer, 0);
+        click(Script::new(), center(0, 0), MouseButton::Left).run(&mut controller, SIZE);
+        // The bomb in the layer below counts too.
+        assert_eq!(controller.gameboard.cells[0][0], EmptyNumber(1));
+    }
+
+    #[test]
+    fn reveal_returns_opened_cells() {
+        let mut controller = controller();
+        assert_eq!(controller.apply(Move::Reveal([1, 1])), vec![[1, 1]]);
+        assert!(controller.apply(Move::Flag([3, 0])).is_empty());
+        let opened = controller.apply(Move::Reveal([0, 0]));
+        assert_eq!(opened[0], [0, 0]);
+        assert_eq!(opened.len(), 5);
+    }
+
+    #[test]
+    fn huge_empty_area_opens_in_one_reveal() {
+        let mut rows = vec![".".repeat(1000); 1000];
+        rows[999] = format!("{}*", ".".repeat(999));
+        let mut controller = GameboardController::new(Gameboard::from_rows(&rows).unwrap());
+        let opened = controller.apply(Move::Reveal([0, 0]));
+        assert_eq!(opened.len(), 1000 * 1000 - 1);
+        assert!(controller.gameboard.is_won());
+        assert_eq!(controller.gameboard.cells[998][998], EmptyNumber(1));
+    }
+
+    /// A board too big to fit the window, with a bomb in every tenth row and column.
+    fn big_board() -> GameboardController {
+        let rows: Vec<String> = (0..150)
+            .map(|y| {
+                (0..150)
+                    .map(|x| if x % 10 == 3 && y % 10 == 7 { '*' } else { '.' })
+                    .collect()
+            })
+            .collect();
+        GameboardController::new(Gameboard::from_rows(&rows).unwrap())
+    }
+
+    #[test]
+    fn scrolling_zooms_around_the_cursor() {
+        let mut controller = controller();
+        let (x, y) = cell(1, 1);
+        Script::new().move_to(x, y).scroll(3.0).run(&mut controller, SIZE);
+        assert!(controller.camera.is_zoomed());
+        assert_eq!(controller.hovered_cell, Some([1, 1]));
+
+        Script::new().scroll(-10.0).run(&mut controller, SIZE);
+        assert_eq!(controller.camera, Camera::fit());
+    }
+
+    #[test]
+    fn dragging_a_zoomed_board_pans_instead_of_revealing() {
+        let mut controller = controller();
+        Script::new().key(Key::Equals).key(Key::Equals).run(&mut controller, SIZE);
+        let before = controller.camera.center;
+        Script::new()
+            .move_to(5.0, 5.0)
+            .press(MouseButton::Left)
+            .move_to(15.0, 13.0)
+            .release(MouseButton::Left)
+            .run(&mut controller, SIZE);
+        assert!(controller.moves.is_empty());
+        assert!(controller.camera.center[0] < before[0]);
+        assert!(controller.camera.center[1] < before[1]);
+
+        // Clicks still land on the cell drawn under the cursor.
+        let layout = BoardLayout::with_camera(SIZE, (4, 4), Topology::Square, &controller.camera);
+        let cell = layout.cell_at([5.0, 5.0]).unwrap();
+        click(Script::new(), (5.0, 5.0), MouseButton::Left).run(&mut controller, SIZE);
+        assert_eq!(controller.moves, vec![Move::Reveal(cell)]);
+    }
+
+    #[test]
+    fn big_boards_start_zoomed_and_follow_the_cursor() {
+        let mut controller = big_board();
+        assert!(controller.camera.is_zoomed());
+        let size = (200.0, 200.0);
+        let mut script = Script::new().key(Key::Space);
+        for _ in 0..40 {
+            script = script.key(Key::Right).key(Key::Down);
+        }
+        script.run(&mut controller, size);
+        assert_eq!(controller.selected_cell, Some([115, 115]));
+        let layout =
+            BoardLayout::with_camera(size, (150, 150), Topology::Square, &controller.camera);
+        let [x1, y1, x2, y2] = layout.visible_cells(size)[0];
+        assert!(x1 <= 115 && 115 < x2 && y1 <= 115 && 115 < y2);
+        assert!(x2 - x1 < 20);
+    }
+
+    #[test]
+    fn layer_keys_leave_the_camera_alone_on_flat_boards() {
+        let mut controller = big_board();
+        let size = (200.0, 200.0);
+        let mut script = Script::new().key(Key::Space);
+        for _ in 0..40 {
+            script = script.key(Key::Right);
+        }
+        script.run(&mut controller, size);
+        let camera = controller.camera;
+        Script::new().key(Key::PageDown).key(Key::PageUp).run(&mut controller, size);
+        assert_eq!(controller.camera, camera);
+        assert_eq!(controller.layer, 0);
+    }
+
+    #[test]
+    fn minimap_click_moves_the_view() {
+        let mut controller = big_board();
+        let size = (200.0, 200.0);
+        let minimap = Minimap::new(size, (150, 150), Topology::Square);
+        let corner = [minimap.rect[0] + 1.0, minimap.rect[1] + 1.0];
+        click(Script::new(), (corner[0], corner[1]), MouseButton::Left).run(&mut controller, size);
+        assert!(controller.moves.is_empty());
+        let layout =
+            BoardLayout::with_camera(size, (150, 150), Topology::Square, &controller.camera);
+        assert_eq!(layout.position, (0.0, 0.0));
+    }
+
+    #[test]
+    fn zoomed_board_matches_golden() {
+        let mut controller = big_board();
+        controller.apply(Move::Reveal([75, 75]));
+        controller.apply(Move::Flag([73, 77]));
+        let settings = GameboardViewSettings::from_theme(&Theme::new());
+        let view = GameboardView::<Image>::new(settings, controller);
+        check_golden("zoomed_board", &render_board(&view, None, [200, 200], [1.0; 4]));
+    }
+
+    #[test]
+    fn played_board_matches_golden() {
+        let mut controller = controller();
+        let script = click(Script::new(), cell(0, 0), MouseButton::Left);
+        let script = click(script, cell(3, 0), MouseButton::Right);
+        click(script, cell(2, 2), MouseButton::Right).run(&mut controller, SIZE);
+        let settings = GameboardViewSettings::from_theme(&Theme::new());
+        let view = GameboardView::<Image>::new(settings, controller);
+        check_golden("played_board", &render_board(&view, None, [80, 80], [1.0; 4]));
+    }
+}
@@ -1,154 +1,587 @@
-//! Gameboard view.
-
-use graphics::types::Color;
-use graphics::{Context, Graphics};
-use graphics::character::CharacterCache;
-
-use GameboardController;
-use gameboard::BOARDSIZE;
-use traits::Renderer;
-
-/// Stores gameboard view settings.
-pub struct GameboardViewSettings {
-    /// Position from left-top corner.
-    pub position: (f64, f64),
-    /// Size of the gameboard along horizontal and vertical edge.
-    pub size: f64,
-    /// Background color.
-    pub background_color: Color,
-    /// Border color.
-    pub border_color: Color,
-    /// Edge color around the board.
-    pub board_edge_color: Color,
-    /// Edge color between cells.
-    pub cell_edge_color: Color,
-    /// Edge radius around the board.
-    pub board_edge_radius: f64,
-    /// Edge radius around the cells.
-    pub cell_edge_radius: f64,
-    /// Text color.
-    pub text_color: Color,
-    /// Cell corner rounding.
-    pub cell_corner_rounding: f64,
-    /// Padding within each cell.
-    pub cell_padding: f64,
-}
-
-impl GameboardViewSettings {
-    /// Creates a new gameboard view setting.
-    pub fn new() -> GameboardViewSettings {
-        GameboardViewSettings {
-            position: (0.0, 0.0),
-            size: 600.0,
-            background_color: [0.82, 0.9, 0.87, 1.0],
-            border_color: [0.0, 0.0, 0.2, 1.0],
-            board_edge_radius: 3.0,
-            board_edge_color: [0.3, 0.3, 0.5, 1.0],
-            cell_edge_color: [0.69, 0.76, 0.73, 1.0],
-            cell_edge_radius: 1.0,
-            cell_corner_rounding: 10.0,
-            cell_padding: 7.0,
-            text_color: [0.0, 0.0, 0.1, 1.0],
-        }
-    }
-}
-
-/// Stores visual information about a gameboard.
-pub struct GameboardView {
-    /// Stores gameboard view settings.
-    pub settings: GameboardViewSettings,
-    /// The gameboard controller
-    pub controller: GameboardController,
-}
-
-impl GameboardView {
-    /// Creates a new gameboard view.
-    pub fn new(settings: GameboardViewSettings, controller: GameboardController) -> GameboardView {
-        GameboardView {
-            settings: settings,
-            controller: controller,
-        }
-    }
-}
-
-impl Renderer for GameboardView {
-    /// Draw gameboard.
-    fn draw<G: Graphics, C>(&self, glyphs: &mut C, c: &Context, g: &mut G)
-        where C: CharacterCache<Texture = G::Texture>
-    {
-        use graphics::{Line, Rectangle, Image, Transformed};
-        use gameboard::CellState::*;
-
-        let settings = &self.settings;
-        let board_rect = [settings.position.0, settings.position.1, settings.size, settings.size];
-
-        // Draw background
-        Rectangle::new(settings.background_color).draw(board_rect, &c.draw_state, c.transform, g);
-
-        // Draw cell borders.
-        let cell_edge = Line::new(settings.cell_edge_color, settings.cell_edge_radius);
-        for i in 1..BOARDSIZE {
-            let x = settings.position.0 + i as f64 / BOARDSIZE as f64 * settings.size;
-            let y = settings.position.1 + i as f64 / BOARDSIZE as f64 * settings.size;
-            let x2 = settings.position.0 + settings.size;
-            let y2 = settings.position.1 + settings.size;
-
-            let vline = [x, settings.position.1, x, y2];
-            cell_edge.draw(vline, &c.draw_state, c.transform, g);
-
-            let hline = [settings.position.0, y, x2, y];
-            cell_edge.draw(hline, &c.draw_state, c.transform, g);
-        }
-
-        // Draw each cell
-        let cell_size = settings.size / BOARDSIZE as f64;
-        let text_image = Image::new_color(settings.text_color);
-        for y in 0..BOARDSIZE {
-            for x in 0..BOARDSIZE {
-                let color = match self.controller.gameboard.cells[y][x] {
-                    HiddenBlank | HiddenBomb => [0.161, 0.31, 0.427, 1.0],
-                    EmptyBlank | EmptyNumber(_) => [0.01, 0.52, 0.59, 1.0],
-                    Bomb => [1.0, 0.0, 0.247, 1.0],
-                    FlaggedBomb | FlaggedBlank => [0.1, 1.0, 0.1, 1.0],
-                };
-                let xpos = settings.position.0 + (x as f64) * (cell_size as f64) +
-                           settings.cell_padding;
-                let ypos = settings.position.1 + (y as f64) * (cell_size as f64) +
-                           settings.cell_padding;
-                let cell_rect = [xpos,
-                                 ypos,
-                                 cell_size - settings.cell_padding * 2.0,
-                                 cell_size - settings.cell_padding * 2.0];
-                let cell_rect_2 = [xpos,
-                                   ypos - 5.0,
-                                   cell_size - settings.cell_padding * 2.0,
-                                   cell_size - settings.cell_padding * 2.0];
-
-                match self.controller.gameboard.cells[y][x] {
-                    HiddenBlank | HiddenBomb => {
-                        Rectangle::new_round([0.01, 0.52, 0.59, 1.0],
-                                             settings.cell_corner_rounding)
-                            .draw(cell_rect, &c.draw_state, c.transform, g);
-                        Rectangle::new_round([0.01, 0.71, 0.81, 1.0],
-                                             settings.cell_corner_rounding)
-                            .draw(cell_rect_2, &c.draw_state, c.transform, g);
-                    }
-                    _ => {
-                        Rectangle::new_round(color, settings.cell_corner_rounding)
-                            .draw(cell_rect, &c.draw_state, c.transform, g)
-                    }
-                };
-
-                if let EmptyNumber(num) = self.controller.gameboard.cells[y][x] {
-                    let character = glyphs.character(34, num);
-                    let ch_x = xpos + character.left() + 13.0;
-                    let ch_y = ypos - character.top() + 33.0;
-                    text_image.draw(character.texture,
-                                    &c.draw_state,
-                                    c.transform.trans(ch_x, ch_y),
-                                    g);
-                }
-            }
-        }
-    }
-}
+//! Gameboard view.
+
+use graphics::types::Color;
+use graphics::ImageSize;
+
+use GameboardController;
+use animation::{AnimationKind, AnimationSettings, Animations};
+use gameboard::CellState;
+use gameboard_controller::GameEvent;
+use layout::{BoardLayout, Minimap};
+use particles::Confetti;
+use sprites::{SpriteSheet, Tile};
+use theme::Theme;
+use topology::CellShape;
+use traits::{Canvas, Renderer};
+
+/// Most blocks of cells the minimap shows along either edge.
+const MINIMAP_BLOCKS: usize = 75;
+/// How much of the background covers the ghost cells around wrapping boards, and the layers next
+/// to the one being played on stacked boards.
+const GHOST_FADE: f32 = 0.5;
+
+/// Color sets for the numbers on revealed cells.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum NumberPalette {
+    /// Whatever the theme picks.
+    Theme,
+    /// Okabe-Ito colors, which stay apart for all common kinds of color blindness.
+    HighContrast,
+}
+
+impl NumberPalette {
+    /// Short name shown in the menu.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            NumberPalette::Theme => "Normal",
+            NumberPalette::HighContrast => "Colorblind",
+        }
+    }
+
+    /// Looks up a palette by its menu name, ignoring case.
+    pub fn from_name(name: &str) -> Option<NumberPalette> {
+        [NumberPalette::Theme, NumberPalette::HighContrast]
+            .iter()
+            .cloned()
+            .find(|palette| palette.name().eq_ignore_ascii_case(name))
+    }
+
+    /// The palette after this one, for cycling through them.
+    pub fn next(&self) -> NumberPalette {
+        match *self {
+            NumberPalette::Theme => NumberPalette::HighContrast,
+            NumberPalette::HighContrast => NumberPalette::Theme,
+        }
+    }
+
+    /// Colors for the numbers 1 to 8, given the ones from the theme.
+    pub fn colors(&self, theme_colors: [Color; 8]) -> [Color; 8] {
+        match *self {
+            NumberPalette::Theme => theme_colors,
+            NumberPalette::HighContrast => {
+                [[0.0, 0.447, 0.698, 1.0],
+                 [0.0, 0.62, 0.451, 1.0],
+                 [0.835, 0.369, 0.0, 1.0],
+                 [0.8, 0.475, 0.655, 1.0],
+                 [0.902, 0.624, 0.0, 1.0],
+                 [0.337, 0.706, 0.914, 1.0],
+                 [0.941, 0.894, 0.259, 1.0],
+                 [0.0, 0.0, 0.0, 1.0]]
+            }
+        }
+    }
+}
+
+/// Stores gameboard view settings.
+pub struct GameboardViewSettings {
+    /// Background color.
+    pub background_color: Color,
+    /// Border color.
+    pub border_color: Color,
+    /// Edge color around the board.
+    pub board_edge_color: Color,
+    /// Edge color between cells.
+    pub cell_edge_color: Color,
+    /// Edge radius around the board.
+    pub board_edge_radius: f64,
+    /// Edge radius around the cells.
+    pub cell_edge_radius: f64,
+    /// Hidden cell, and the shadow under its raised top.
+    pub hidden_color: Color,
+    /// Raised top of a hidden cell.
+    pub hidden_top_color: Color,
+    /// Revealed cell.
+    pub revealed_color: Color,
+    /// Exploded bomb.
+    pub bomb_color: Color,
+    /// Flagged cell.
+    pub flag_color: Color,
+    /// Flag found on an empty cell after losing.
+    pub wrong_flag_color: Color,
+    /// Mark drawn on mines and wrong flags after losing.
+    pub mine_color: Color,
+    /// Color of each number, starting at 1.
+    pub number_colors: [Color; 8],
+    /// Cell corner rounding, relative to the cell size.
+    pub cell_corner_rounding: f64,
+    /// Padding within each cell, relative to the cell size.
+    pub cell_padding: f64,
+    /// How far hidden cells stick out, relative to the cell size.
+    pub cell_raise: f64,
+    /// Font size of the numbers, relative to the cell size.
+    pub font_scale: f64,
+    /// Color of a hidden cell under the mouse cursor.
+    pub hover_color: Color,
+    /// Outline color of the keyboard cursor.
+    pub selected_cell_border_color: Color,
+    /// Outline radius of the keyboard cursor.
+    pub selected_cell_border_radius: f64,
+}
+
+impl GameboardViewSettings {
+    /// Creates gameboard view settings from a theme.
+    pub fn from_theme(theme: &Theme) -> GameboardViewSettings {
+        GameboardViewSettings {
+            background_color: theme.board_background_color,
+            border_color: [0.0, 0.0, 0.2, 1.0],
+            board_edge_radius: 3.0,
+            board_edge_color: [0.3, 0.3, 0.5, 1.0],
+            cell_edge_color: theme.cell_edge_color,
+            cell_edge_radius: theme.cell_edge_radius,
+            hidden_color: theme.hidden_color,
+            hidden_top_color: theme.hidden_top_color,
+            revealed_color: theme.revealed_color,
+            bomb_color: theme.bomb_color,
+            flag_color: theme.flag_color,
+            wrong_flag_color: theme.wrong_flag_color,
+            mine_color: theme.mine_color,
+            cell_corner_rounding: theme.cell_corner_rounding,
+            cell_padding: theme.cell_padding,
+            cell_raise: theme.cell_raise,
+            font_scale: theme.font_scale,
+            number_colors: theme.number_colors,
+            hover_color: theme.hover_color,
+            selected_cell_border_color: theme.selected_cell_border_color,
+            selected_cell_border_radius: theme.selected_cell_border_radius,
+        }
+    }
+}
+
+/// Stores visual information about a gameboard.
+pub struct GameboardView<T> {
+    /// Stores gameboard view settings.
+    pub settings: GameboardViewSettings,
+    /// The gameboard controller
+    pub controller: GameboardController,
+    /// Tile atlas to draw cells from. Cells are drawn as shapes when this is `None`.
+    pub sprites: Option<SpriteSheet<T>>,
+    /// Cell animations in progress.
+    pub animations: Animations,
+    /// Confetti thrown after a win.
+    pub confetti: Confetti,
+}
+
+impl<T> GameboardView<T> {
+    /// Creates a new gameboard view.
+    pub fn new(settings: GameboardViewSettings,
+               controller: GameboardController)
+               -> GameboardView<T> {
+        GameboardView {
+            settings: settings,
+            controller: controller,
+            sprites: None,
+            animations: Animations::new(AnimationSettings::new()),
+            confetti: Confetti::new(0),
+        }
+    }
+
+    /// Starts animations for what the controller did since the last call, and returns the events
+    /// for anything else that wants to react to them.
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        let events = self.controller.take_events();
+        if events.is_empty() {
+            return events;
+        }
+        for event in &events {
+            self.animations.handle(event, &self.controller.gameboard);
+            if let GameEvent::Won = *event {
+                if self.animations.settings.confetti {
+                    self.confetti.burst(self.animations.settings.confetti_count,
+                                        &self.settings.number_colors);
+                }
+            }
+        }
+        events
+    }
+
+    /// Moves animations and confetti along.
+    pub fn update(&mut self, dt: f64) {
+        self.animations.update(dt);
+        self.confetti.update(dt);
+    }
+
+    /// Whether anything on the board is still moving.
+    pub fn is_animating(&self) -> bool {
+        self.animations.is_running() || self.confetti.is_running()
+    }
+
+    /// Stops every animation, for when a new board is dealt.
+    pub fn clear_animations(&mut self) {
+        self.animations.clear();
+        self.confetti.clear();
+    }
+}
+
+/// Fills a cell of the given shape inside `rect`. Square cells get their corners rounded by
+/// `radius`.
+fn fill_cell<C: Canvas>(canvas: &mut C,
+                        shape: CellShape,
+                        color: Color,
+                        rect: [f64; 4],
+                        radius: f64) {
+    match shape.outline(rect) {
+        Some(corners) => canvas.polygon(color, &corners),
+        None => canvas.rectangle(color, rect, radius),
+    }
+}
+
+/// Draws the outline of a cell of the given shape inside `rect`.
+fn outline_cell<C: Canvas>(canvas: &mut C,
+                           shape: CellShape,
+                           color: Color,
+                           rect: [f64; 4],
+                           radius: f64) {
+    match shape.outline(rect) {
+        Some(corners) => {
+            for (i, from) in corners.iter().enumerate() {
+                let to = corners[(i + 1) % corners.len()];
+                canvas.line(color, [from[0], from[1], to[0], to[1]], radius);
+            }
+        }
+        None => canvas.border(color, rect, radius),
+    }
+}
+
+/// Draws one tile of the given shape filling `rect`, squeezed sideways by `squeeze` for flip
+/// animations. The tile atlas only has square tiles up to the number 8, so other shapes and
+/// higher numbers are always drawn as shapes.
+pub fn draw_tile<C: Canvas>(settings: &GameboardViewSettings,
+                            sprites: Option<&SpriteSheet<C::Texture>>,
+                            shape: CellShape,
+                            tile: Tile,
+                            rect: [f64; 4],
+                            squeeze: f64,
+                            hovered: bool,
+                            canvas: &mut C) {
+    let cell_size = rect[2];
+    let cell_height = rect[3];
+    let rect = [rect[0] + rect[2] * (1.0 - squeeze) / 2.0, rect[1], rect[2] * squeeze, rect[3]];
+
+    if let Some(sheet) = sprites.filter(|_| shape == CellShape::Square && tile.in_atlas()) {
+        canvas.image(&sheet.texture, sheet.src_rect(tile), rect);
+        if hovered && (tile == Tile::Hidden || tile == Tile::Question) {
+            let mut tint = settings.hover_color;
+            tint[3] = 0.35;
+            canvas.rectangle(tint, rect, 0.0);
+        }
+        return;
+    }
+
+    let padding = settings.cell_padding * cell_size;
+    let raise = settings.cell_raise * cell_size;
+    let inner_width = (rect[2] - padding * 2.0 * squeeze).max(0.0);
+    let inner_height = cell_height - padding * 2.0;
+    let rounding = (settings.cell_corner_rounding * cell_size).min(inner_width / 2.0);
+    let xpos = rect[0] + padding * squeeze;
+    let ypos = rect[1] + padding;
+    let cell_rect = [xpos, ypos, inner_width, inner_height];
+    let cell_rect_2 = [xpos, ypos - raise, inner_width, inner_height];
+    // The middle half of the cell, for drawing mine marks in. It stays as tall as it is wide on
+    // cells taller than that.
+    let mark_height = (cell_size - padding * 2.0) / 2.0;
+    let mark_rect = [xpos + inner_width / 4.0,
+                     ypos + (inner_height - mark_height) / 2.0,
+                     inner_width / 2.0,
+                     mark_height];
+
+    match tile {
+        Tile::Hidden | Tile::Question => {
+            let top_color = if hovered {
+                settings.hover_color
+            } else {
+                settings.hidden_top_color
+            };
+            fill_cell(canvas, shape, settings.hidden_color, cell_rect, rounding);
+            fill_cell(canvas, shape, top_color, cell_rect_2, rounding);
+        }
+        Tile::Revealed | Tile::Number(_) => {
+            fill_cell(canvas, shape, settings.revealed_color, cell_rect, rounding);
+        }
+        Tile::Flag => fill_cell(canvas, shape, settings.flag_color, cell_rect, rounding),
+        Tile::Mine | Tile::ExplodedMine => {
+            let color = if tile == Tile::Mine {
+                settings.revealed_color
+            } else {
+                settings.bomb_color
+            };
+            fill_cell(canvas, shape, color, cell_rect, rounding);
+            canvas.ellipse(settings.mine_color, mark_rect);
+        }
+        Tile::WrongFlag => {
+            fill_cell(canvas, shape, settings.wrong_flag_color, cell_rect, rounding);
+            let radius = mark_height / 8.0;
+            let [mx, my, mw, mh] = mark_rect;
+            canvas.line(settings.mine_color, [mx, my, mx + mw, my + mh], radius);
+            canvas.line(settings.mine_color, [mx + mw, my, mx, my + mh], radius);
+        }
+    }
+
+    let label = match tile {
+        Tile::Number(num) => Some((num.to_string(), (num.max(1).min(8) - 1) as usize)),
+        Tile::Question => Some(("?".to_string(), 0)),
+        _ => None,
+    };
+    if let Some((text, color)) = label {
+        let font_size = (settings.font_scale * cell_size) as u32;
+        let sizes: Vec<[f64; 2]> =
+            text.chars().map(|ch| canvas.character_size(font_size, ch)).collect();
+        let width: f64 = sizes.iter().map(|size| size[0]).sum();
+        let mut ch_x = rect[0] + (rect[2] - width * squeeze) / 2.0;
+        for (ch, size) in text.chars().zip(sizes) {
+            let ch_y = rect[1] + (cell_height - size[1]) / 2.0;
+            canvas.character(settings.number_colors[color], font_size, ch, [ch_x, ch_y], squeeze);
+            ch_x += size[0] * squeeze;
+        }
+    }
+}
+
+impl<T> GameboardView<T> {
+    /// Draws the board with the given tile atlas instead of the view's own, for drawing with a
+    /// backend that can't use the view's textures.
+    pub fn draw_with<C: Canvas>(&self, sprites: Option<&SpriteSheet<C::Texture>>, canvas: &mut C) {
+        let settings = &self.settings;
+        let view_size = canvas.size();
+        let window_size = (view_size[0], view_size[1]);
+        let board = &self.controller.gameboard;
+        let cells = (board.width, board.height);
+        let topology = board.topology;
+        let layout =
+            BoardLayout::with_camera(window_size, cells, topology, &self.controller.camera);
+        // Only the part of the board inside the window is drawn.
+        let left = layout.position.0.max(0.0);
+        let top = layout.position.1.max(0.0);
+        let visible_rect = [left,
+                            top,
+                            (layout.position.0 + layout.size.0).min(view_size[0]) - left,
+                            (layout.position.1 + layout.size.1).min(view_size[1]) - top];
+        let visible = layout.visible_cells(window_size);
+
+        // Draw background
+        canvas.rectangle(settings.background_color, visible_rect, 0.0);
+
+        // Draw cell borders. Other shapes are kept apart by their padding alone. Wrapping boards
+        // also get borders between the board and the ghost cells around it.
+        if topology.is_grid() {
+            let [x1, y1, x2, y2] = visible[0];
+            let right = visible_rect[0] + visible_rect[2];
+            let bottom = visible_rect[1] + visible_rect[3];
+            let (first, extra) = if topology.wraps() { (0, 1) } else { (1, 0) };
+            for i in x1.max(first)..x2 + extra {
+                let x = layout.cell_pos(i, 0).0;
+                let vline = [x, visible_rect[1], x, bottom];
+                canvas.line(settings.cell_edge_color, vline, settings.cell_edge_radius);
+            }
+            for i in y1.max(first)..y2 + extra {
+                let y = layout.cell_pos(0, i).1;
+                let hline = [visible_rect[0], y, right, y];
+                canvas.line(settings.cell_edge_color, hline, settings.cell_edge_radius);
+            }
+        }
+
+        // Draw each cell
+        let cell_size = layout.cell_size();
+        let padding = settings.cell_padding * cell_size;
+        let lost = board.is_lost();
+        for &[x1, y1, x2, y2] in &visible {
+            for y in y1..y2 {
+                for x in x1..x2 {
+                    let shape = topology.shape([x, y]);
+                    let draw = |tile, rect, squeeze, hovered, canvas: &mut C| {
+                        draw_tile(settings, sprites, shape, tile, rect, squeeze, hovered, canvas)
+                    };
+                    let rect = layout.cell_rect(x, y);
+                    let [cell_x, cell_y, _, cell_height] = rect;
+                    let hovered = self.controller.hovered_cell == Some([x, y]);
+                    let tile = if self.controller.is_pressed(x, y) {
+                        Tile::Revealed
+                    } else {
+                        Tile::for_cell(board.cells[y][x], lost)
+                    };
+
+                    match self.animations.progress([x, y]) {
+                        // Shrink the hidden face away, then grow the open face back.
+                        Some((AnimationKind::Flip, t)) if t < 0.5 => {
+                            let squeeze = 1.0 - 2.0 * t.max(0.0);
+                            draw(Tile::Hidden, rect, squeeze, false, canvas);
+                        }
+                        Some((AnimationKind::Flip, t)) => {
+                            draw(tile, rect, 2.0 * t - 1.0, hovered, canvas);
+                        }
+                        Some((AnimationKind::FlagDrop, t)) => {
+                            draw(Tile::Hidden, rect, 1.0, hovered, canvas);
+                            let fall = (1.0 - t) * (1.0 - t) * cell_size / 2.0;
+                            let flag_rect = [cell_x, cell_y - fall, cell_size, cell_height];
+                            draw(tile, flag_rect, 1.0, false, canvas);
+                        }
+                        Some((AnimationKind::Explosion, t)) if t < 0.0 => {
+                            let waiting = match tile {
+                                Tile::Mine => Tile::Hidden,
+                                _ => tile,
+                            };
+                            draw(waiting, rect, 1.0, false, canvas);
+                        }
+                        Some((AnimationKind::Explosion, t)) => {
+                            draw(tile, rect, 1.0, false, canvas);
+                            let mut color = settings.bomb_color;
+                            color[3] *= (1.0 - t) as f32;
+                            let radius = cell_size * (0.3 + t);
+                            let center = [cell_x + cell_size / 2.0, cell_y + cell_height / 2.0];
+                            canvas.ellipse(color,
+                                           [center[0] - radius,
+                                            center[1] - radius,
+                                            radius * 2.0,
+                                            radius * 2.0]);
+                        }
+                        None => draw(tile, rect, 1.0, hovered, canvas),
+                    }
+
+                    // Layers other than the one being played fade more the further away they are.
+                    let layer = topology.layer_of([x, y], cells) as i32;
+                    let distance = (layer - self.controller.layer as i32).abs();
+                    if distance > 0 {
+                        let mut fade = settings.background_color;
+                        fade[3] = 1.0 - (1.0 - GHOST_FADE).powi(distance);
+                        canvas.rectangle(fade, rect, 0.0);
+                    }
+                }
+            }
+        }
+
+        if topology.wraps() {
+            self.draw_ghost_cells(sprites, &layout, window_size, canvas);
+        }
+
+        // Draw keyboard cursor
+        if let Some(ind) = self.controller.selected_cell {
+            let [cell_x, cell_y, width, height] = layout.cell_rect(ind[0], ind[1]);
+            let cell_rect = [cell_x + padding / 2.0,
+                             cell_y + padding / 2.0,
+                             width - padding,
+                             height - padding];
+            outline_cell(canvas,
+                         topology.shape(ind),
+                         settings.selected_cell_border_color,
+                         cell_rect,
+                         settings.selected_cell_border_radius);
+        }
+
+        if self.controller.camera.is_zoomed() {
+            self.draw_minimap(&layout, visible_rect, window_size, canvas);
+        }
+
+        // Draw confetti over everything else
+        for p in &self.confetti.particles {
+            let center = [visible_rect[0] + p.pos[0] * visible_rect[2],
+                          visible_rect[1] + p.pos[1] * visible_rect[3]];
+            let size = [p.size[0] * visible_rect[2], p.size[1] * visible_rect[2]];
+            canvas.rotated_rectangle(p.color, center, size, p.angle);
+        }
+    }
+
+    /// Draws the ring of cells around a wrapping board, each a faded copy of the cell on the
+    /// opposite edge.
+    fn draw_ghost_cells<C: Canvas>(&self,
+                                   sprites: Option<&SpriteSheet<C::Texture>>,
+                                   layout: &BoardLayout,
+                                   window_size: (f64, f64),
+                                   canvas: &mut C) {
+        let settings = &self.settings;
+        let board = &self.controller.gameboard;
+        let (width, height) = (board.width as i64, board.height as i64);
+        let size = layout.cell_size();
+        let lost = board.is_lost();
+        let mut fade = settings.background_color;
+        fade[3] = GHOST_FADE;
+        for y in -1..height + 1 {
+            let edge_row = y < 0 || y == height;
+            for x in -1..width + 1 {
+                if !edge_row && x >= 0 && x < width {
+                    continue;
+                }
+                let cell = [((x + width) % width) as usize, ((y + height) % height) as usize];
+                let [cell_x, cell_y, cell_width, cell_height] = layout.cell_rect(cell[0], cell[1]);
+                let rect = [cell_x + (x - cell[0] as i64) as f64 * size,
+                            cell_y + (y - cell[1] as i64) as f64 * size,
+                            cell_width,
+                            cell_height];
+                if rect[0] + rect[2] < 0.0 || rect[0] > window_size.0 ||
+                   rect[1] + rect[3] < 0.0 || rect[1] > window_size.1 {
+                    continue;
+                }
+                let tile = Tile::for_cell(board.cells[cell[1]][cell[0]], lost);
+                draw_tile(settings,
+                          sprites,
+                          board.topology.shape(cell),
+                          tile,
+                          rect,
+                          1.0,
+                          false,
+                          canvas);
+                canvas.rectangle(fade, rect, 0.0);
+            }
+        }
+    }
+
+    /// Draws the whole board small in a corner, with the part shown in the window outlined.
+    ///
+    /// Big boards are drawn in blocks of cells, colored by the most telling cell in each.
+    fn draw_minimap<C: Canvas>(&self,
+                               layout: &BoardLayout,
+                               visible_rect: [f64; 4],
+                               window_size: (f64, f64),
+                               canvas: &mut C) {
+        let settings = &self.settings;
+        let board = &self.controller.gameboard;
+        let cells = (board.width, board.height);
+        let minimap = Minimap::new(window_size, cells, board.topology);
+        let column_step = board.topology.column_step();
+        let row_step = board.topology.row_step();
+        canvas.rectangle(settings.hidden_top_color, minimap.rect, 0.0);
+
+        let step = (board.width.max(board.height) + MINIMAP_BLOCKS - 1) / MINIMAP_BLOCKS;
+        for by in (0..board.height).step_by(step) {
+            for bx in (0..board.width).step_by(step) {
+                let mut opened = true;
+                let mut color = None;
+                for row in &board.cells[by..(by + step).min(board.height)] {
+                    for &cell in &row[bx..(bx + step).min(board.width)] {
+                        match cell {
+                            CellState::Bomb => color = Some(settings.bomb_color),
+                            CellState::FlaggedBlank | CellState::FlaggedBomb => {
+                                color = color.or(Some(settings.flag_color))
+                            }
+                            CellState::EmptyBlank | CellState::EmptyNumber(_) => {}
+                            _ => opened = false,
+                        }
+                    }
+                }
+                let color = match color {
+                    Some(color) => color,
+                    None if opened => settings.revealed_color,
+                    None => continue,
+                };
+                let origin = board.topology.cell_origin([bx, by], cells);
+                let block = [origin[0],
+                             origin[1],
+                             step as f64 * column_step,
+                             step as f64 * row_step];
+                canvas.rectangle(color, minimap.rect_of(block), 0.0);
+            }
+        }
+
+        let cell_size = layout.cell_size();
+        let shown = [(visible_rect[0] - layout.position.0) / cell_size,
+                     (visible_rect[1] - layout.position.1) / cell_size,
+                     visible_rect[2] / cell_size,
+                     visible_rect[3] / cell_size];
+        canvas.border(settings.selected_cell_border_color, minimap.rect_of(shown), 1.0);
+        canvas.border(settings.cell_edge_color, minimap.rect, 1.0);
+    }
+}
+
+impl<T: ImageSize> Renderer<T> for GameboardView<T> {
+    /// Draw gameboard.
+    fn draw<C: Canvas<Texture = T>>(&self, canvas: &mut C) {
+        self.draw_with(self.sprites.as_ref(), canvas);
+    }
+}
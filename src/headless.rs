@@ -0,0 +1,142 @@
+//! Playing without a window.
+//!
+//! The board is printed as text after every move, and moves are read from stdin one per line,
+//! written the same way as in replay files: `reveal x y`, `flag x y` or `chord x y`.
+
+use std::char;
+use std::io::{self, BufRead, Write};
+
+use gameboard::Gameboard;
+use gameboard_controller::{GameEvent, GameboardController, GUESS_WARNING};
+use replay::Move;
+use sprites::Tile;
+
+/// Character used for a tile when the board is printed. Numbers from 10 up are letters, starting
+/// with `a`.
+pub fn tile_char(tile: Tile) -> char {
+    match tile {
+        Tile::Hidden => '#',
+        Tile::Revealed => '.',
+        Tile::Flag => 'F',
+        Tile::Mine => '*',
+        Tile::ExplodedMine => 'X',
+        Tile::WrongFlag => '!',
+        Tile::Number(num) => char::from_digit(num as u32, 36).unwrap_or('+'),
+        Tile::Question => '?',
+    }
+}
+
+/// The board as text, one line per row.
+pub fn board_text(board: &Gameboard) -> String {
+    let lost = board.is_lost();
+    let mut text = String::new();
+    for row in &board.cells {
+        text.extend(row.iter().map(|&cell| tile_char(Tile::for_cell(cell, lost))));
+        text.push('\n');
+    }
+    text
+}
+
+/// Plays the given moves, then reads more from stdin until the game is over or stdin closes.
+pub fn run(controller: &mut GameboardController, moves: Vec<Move>) -> io::Result<()> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let stderr = io::stderr();
+    play(controller, moves, stdin.lock(), stdout.lock(), stderr.lock())
+}
+
+/// Plays the given moves, then reads more from `input` until the game is over or `input` runs
+/// out. Boards go to `out`, mistakes and warnings to `err`.
+pub fn play<R, W, E>(controller: &mut GameboardController,
+                     moves: Vec<Move>,
+                     input: R,
+                     mut out: W,
+                     mut err: E)
+                     -> io::Result<()>
+    where R: BufRead,
+          W: Write,
+          E: Write
+{
+    for mv in moves {
+        controller.apply(mv);
+    }
+    warn(controller, &mut err)?;
+
+    write!(out, "{}", board_text(&controller.gameboard))?;
+    let mut lines = input.lines();
+    while !controller.gameboard.is_over() {
+        out.flush()?;
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => break,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        match Move::parse(&line) {
+            Ok(mv) => {
+                controller.apply(mv);
+                warn(controller, &mut err)?;
+            }
+            Err(e) => {
+                writeln!(err, "{}", e)?;
+                continue;
+            }
+        }
+        write!(out, "\n{}", board_text(&controller.gameboard))?;
+    }
+
+    if controller.gameboard.is_won() {
+        writeln!(out, "You won!")?;
+    } else if controller.gameboard.is_lost() {
+        writeln!(out, "You lost.")?;
+    }
+    Ok(())
+}
+
+/// Tells the player about anything the board can't show, like a no-guess board that may need a
+/// guess after all.
+fn warn<E: Write>(controller: &mut GameboardController, err: &mut E) -> io::Result<()> {
+    for event in controller.take_events() {
+        if event == GameEvent::GuessNeeded {
+            writeln!(err, "{}", GUESS_WARNING)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gameboard::{Difficulty, FirstClick, BEGINNER};
+
+    fn play_text(controller: &mut GameboardController, input: &str) -> (String, String) {
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        play(controller, Vec::new(), input.as_bytes(), &mut out, &mut err).unwrap();
+        (String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+    }
+
+    #[test]
+    fn crowded_no_guess_boards_warn_once() {
+        let crowded = Difficulty {
+            width: 8,
+            height: 8,
+            mines: 50,
+        };
+        let mut controller = GameboardController::new(Gameboard::new(crowded, FirstClick::Safe, 1));
+        controller.gameboard.no_guess = true;
+        let (_, err) = play_text(&mut controller, "reveal 0 0\nflag 7 7\n");
+        assert_eq!(err, format!("{}\n", GUESS_WARNING));
+    }
+
+    #[test]
+    fn solvable_boards_play_quietly() {
+        let board = Gameboard::new(BEGINNER, FirstClick::Safe, 1);
+        let mut controller = GameboardController::new(board);
+        controller.gameboard.no_guess = true;
+        let (out, err) = play_text(&mut controller, "reveal 4 4\nnonsense\n");
+        assert!(out.starts_with(&"#########\n".repeat(9)));
+        assert!(!err.contains(GUESS_WARNING));
+        assert!(!err.is_empty());
+    }
+}
@@ -0,0 +1,151 @@
+//! Keyboard bindings.
+
+use piston::input::keyboard::Key;
+
+/// Something the player can do from the keyboard on the board.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Action {
+    /// Move the cursor up.
+    Up,
+    /// Move the cursor down.
+    Down,
+    /// Move the cursor left.
+    Left,
+    /// Move the cursor right.
+    Right,
+    /// Open the cell under the cursor.
+    Reveal,
+    /// Flag the cell under the cursor.
+    Flag,
+    /// Open around the number under the cursor.
+    Chord,
+    /// Go back to the menu.
+    Menu,
+    /// Save a picture of the board.
+    Screenshot,
+    /// Make the cells bigger.
+    ZoomIn,
+    /// Make the cells smaller, down to fitting the whole board.
+    ZoomOut,
+    /// Go to the layer above on stacked boards.
+    LayerUp,
+    /// Go to the layer below on stacked boards.
+    LayerDown,
+}
+
+impl Action {
+    /// Every action, in the order they are written to the config file.
+    pub const ALL: [Action; 13] = [Action::Up,
+                                   Action::Down,
+                                   Action::Left,
+                                   Action::Right,
+                                   Action::Reveal,
+                                   Action::Flag,
+                                   Action::Chord,
+                                   Action::Menu,
+                                   Action::Screenshot,
+                                   Action::ZoomIn,
+                                   Action::ZoomOut,
+                                   Action::LayerUp,
+                                   Action::LayerDown];
+
+    /// Name used in the config file.
+    pub fn id(&self) -> &'static str {
+        match *self {
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Reveal => "reveal",
+            Action::Flag => "flag",
+            Action::Chord => "chord",
+            Action::Menu => "menu",
+            Action::Screenshot => "screenshot",
+            Action::ZoomIn => "zoom_in",
+            Action::ZoomOut => "zoom_out",
+            Action::LayerUp => "layer_up",
+            Action::LayerDown => "layer_down",
+        }
+    }
+}
+
+/// Keys that can be bound, with the names they go by in the config file.
+const KEY_NAMES: [(Key, &'static str); 78] =
+    [(Key::A, "A"), (Key::B, "B"), (Key::C, "C"), (Key::D, "D"), (Key::E, "E"), (Key::F, "F"),
+     (Key::G, "G"), (Key::H, "H"), (Key::I, "I"), (Key::J, "J"), (Key::K, "K"), (Key::L, "L"),
+     (Key::M, "M"), (Key::N, "N"), (Key::O, "O"), (Key::P, "P"), (Key::Q, "Q"), (Key::R, "R"),
+     (Key::S, "S"), (Key::T, "T"), (Key::U, "U"), (Key::V, "V"), (Key::W, "W"), (Key::X, "X"),
+     (Key::Y, "Y"), (Key::Z, "Z"), (Key::D0, "0"), (Key::D1, "1"), (Key::D2, "2"),
+     (Key::D3, "3"), (Key::D4, "4"), (Key::D5, "5"), (Key::D6, "6"), (Key::D7, "7"),
+     (Key::D8, "8"), (Key::D9, "9"), (Key::Up, "Up"), (Key::Down, "Down"),
+     (Key::Left, "Left"), (Key::Right, "Right"), (Key::Space, "Space"),
+     (Key::Return, "Return"), (Key::Escape, "Escape"), (Key::Tab, "Tab"),
+     (Key::Backspace, "Backspace"), (Key::Delete, "Delete"), (Key::Insert, "Insert"),
+     (Key::Home, "Home"), (Key::End, "End"), (Key::PageUp, "PageUp"),
+     (Key::PageDown, "PageDown"), (Key::NumPad0, "NumPad0"), (Key::NumPad1, "NumPad1"),
+     (Key::NumPad2, "NumPad2"), (Key::NumPad3, "NumPad3"), (Key::NumPad4, "NumPad4"),
+     (Key::NumPad5, "NumPad5"), (Key::NumPad6, "NumPad6"), (Key::NumPad7, "NumPad7"),
+     (Key::NumPad8, "NumPad8"), (Key::NumPad9, "NumPad9"), (Key::NumPadEnter, "NumPadEnter"),
+     (Key::F1, "F1"), (Key::F2, "F2"), (Key::F3, "F3"), (Key::F4, "F4"), (Key::F5, "F5"),
+     (Key::F6, "F6"), (Key::F7, "F7"), (Key::F8, "F8"), (Key::F9, "F9"), (Key::F10, "F10"),
+     (Key::F11, "F11"), (Key::F12, "F12"), (Key::Equals, "Equals"), (Key::Minus, "Minus"),
+     (Key::NumPadPlus, "NumPadPlus"), (Key::NumPadMinus, "NumPadMinus")];
+
+/// Looks up a key by its config file name, ignoring case.
+pub fn key_from_name(name: &str) -> Option<Key> {
+    KEY_NAMES.iter()
+        .find(|&&(_, key_name)| key_name.eq_ignore_ascii_case(name))
+        .map(|&(key, _)| key)
+}
+
+/// Config file name of a key, if it can be bound.
+pub fn key_name(key: Key) -> Option<&'static str> {
+    KEY_NAMES.iter().find(|&&(k, _)| k == key).map(|&(_, name)| name)
+}
+
+/// Which keys do what on the board. A key does at most one thing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyBindings {
+    bindings: Vec<(Key, Action)>,
+}
+
+impl KeyBindings {
+    /// Creates the default bindings: arrows, WASD and HJKL move, Space and Return reveal, F flags,
+    /// C chords, Escape goes back to the menu, F12 saves a screenshot, plus and minus zoom and
+    /// Page Up and Page Down change layers.
+    pub fn new() -> KeyBindings {
+        let mut keys = KeyBindings { bindings: Vec::new() };
+        keys.set(Action::Up, &[Key::Up, Key::W, Key::K]);
+        keys.set(Action::Down, &[Key::Down, Key::S, Key::J]);
+        keys.set(Action::Left, &[Key::Left, Key::A, Key::H]);
+        keys.set(Action::Right, &[Key::Right, Key::D, Key::L]);
+        keys.set(Action::Reveal, &[Key::Space, Key::Return]);
+        keys.set(Action::Flag, &[Key::F]);
+        keys.set(Action::Chord, &[Key::C]);
+        keys.set(Action::Menu, &[Key::Escape]);
+        keys.set(Action::Screenshot, &[Key::F12]);
+        keys.set(Action::ZoomIn, &[Key::Equals, Key::NumPadPlus]);
+        keys.set(Action::ZoomOut, &[Key::Minus, Key::NumPadMinus]);
+        keys.set(Action::LayerUp, &[Key::PageUp]);
+        keys.set(Action::LayerDown, &[Key::PageDown]);
+        keys
+    }
+
+    /// What a key does, if anything.
+    pub fn action(&self, key: Key) -> Option<Action> {
+        self.bindings.iter().find(|&&(k, _)| k == key).map(|&(_, action)| action)
+    }
+
+    /// Keys bound to an action.
+    pub fn keys(&self, action: Action) -> Vec<Key> {
+        self.bindings.iter().filter(|&&(_, a)| a == action).map(|&(key, _)| key).collect()
+    }
+
+    /// Replaces the keys bound to an action. Keys bound to something else are taken over.
+    pub fn set(&mut self, action: Action, keys: &[Key]) {
+        self.bindings.retain(|&(key, a)| a != action && !keys.contains(&key));
+        for &key in keys {
+            self.bindings.push((key, action));
+        }
+    }
+}
@@ -0,0 +1,333 @@
+//! Board layout.
+
+use topology::Topology;
+
+/// Smallest cell size the camera zooms out to, in pixels.
+pub const MIN_CELL_SIZE: f64 = 6.0;
+/// Largest cell size the camera zooms in to, in pixels.
+pub const MAX_CELL_SIZE: f64 = 80.0;
+/// Cell size boards too big to fit the window start at, in pixels.
+pub const START_CELL_SIZE: f64 = 24.0;
+/// Boards with more columns or rows than this start zoomed in.
+const FIT_LIMIT: usize = 100;
+/// Longer edge of the minimap, in pixels. Small windows get a smaller one.
+const MINIMAP_SIZE: f64 = 150.0;
+/// Space between the minimap and the window edges, in pixels.
+const MINIMAP_MARGIN: f64 = 10.0;
+
+/// Which part of the board is shown.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Camera {
+    /// Size of a cell in pixels, or `None` to fit the whole board in the window.
+    pub cell_size: Option<f64>,
+    /// Board position shown in the middle of the window, counted in cell widths.
+    pub center: [f64; 2],
+}
+
+impl Camera {
+    /// A camera showing the whole board.
+    pub fn fit() -> Camera {
+        Camera {
+            cell_size: None,
+            center: [0.0, 0.0],
+        }
+    }
+
+    /// Shows the whole board, or the middle of boards too big for that at a readable size.
+    pub fn for_board(cells: (usize, usize), topology: Topology) -> Camera {
+        if cells.0 <= FIT_LIMIT && cells.1 <= FIT_LIMIT {
+            return Camera::fit();
+        }
+        let extent = topology.extent(cells);
+        Camera {
+            cell_size: Some(START_CELL_SIZE),
+            center: [extent.0 / 2.0, extent.1 / 2.0],
+        }
+    }
+
+    /// Whether only part of the board may be shown.
+    pub fn is_zoomed(&self) -> bool {
+        self.cell_size.is_some()
+    }
+
+    /// Scales cells by `factor`, keeping the board under the window position `anchor` in place.
+    /// Zooming out far enough goes back to showing the whole board.
+    pub fn zoom(&mut self,
+                factor: f64,
+                anchor: [f64; 2],
+                window_size: (f64, f64),
+                cells: (usize, usize),
+                topology: Topology) {
+        let layout = BoardLayout::with_camera(window_size, cells, topology, self);
+        let fit = BoardLayout::new(window_size, cells, topology).cell_size();
+        let size = layout.cell_size() * factor;
+        if size <= fit && fit >= MIN_CELL_SIZE {
+            *self = Camera::fit();
+            return;
+        }
+        let size = size.max(fit).max(MIN_CELL_SIZE).min(MAX_CELL_SIZE);
+        let board_x = (anchor[0] - layout.position.0) / layout.cell_size();
+        let board_y = (anchor[1] - layout.position.1) / layout.cell_size();
+        self.cell_size = Some(size);
+        self.center = [board_x - (anchor[0] - window_size.0 / 2.0) / size,
+                       board_y - (anchor[1] - window_size.1 / 2.0) / size];
+        self.settle(window_size, cells, topology);
+    }
+
+    /// Moves the board by a distance in pixels, like dragging it.
+    pub fn pan(&mut self,
+               delta: [f64; 2],
+               window_size: (f64, f64),
+               cells: (usize, usize),
+               topology: Topology) {
+        if let Some(size) = self.cell_size {
+            self.center[0] -= delta[0] / size;
+            self.center[1] -= delta[1] / size;
+            self.settle(window_size, cells, topology);
+        }
+    }
+
+    /// Centers the window on a board position, counted in cell widths.
+    pub fn look_at(&mut self,
+                   pos: [f64; 2],
+                   window_size: (f64, f64),
+                   cells: (usize, usize),
+                   topology: Topology) {
+        self.center = pos;
+        self.settle(window_size, cells, topology);
+    }
+
+    /// Moves as little as needed to show a whole cell.
+    pub fn show_cell(&mut self,
+                     cell: [usize; 2],
+                     window_size: (f64, f64),
+                     cells: (usize, usize),
+                     topology: Topology) {
+        let size = match self.cell_size {
+            Some(size) => size,
+            None => return,
+        };
+        let half = [window_size.0 / size / 2.0, window_size.1 / size / 2.0];
+        let origin = topology.cell_origin(cell, cells);
+        let extent = [1.0, topology.cell_height()];
+        for axis in 0..2 {
+            let low = origin[axis] + extent[axis] - half[axis];
+            let high = origin[axis] + half[axis];
+            self.center[axis] = self.center[axis].max(low).min(high);
+        }
+        self.settle(window_size, cells, topology);
+    }
+
+    /// Moves the center to what is really shown, so the board can't be dragged past its edges.
+    fn settle(&mut self, window_size: (f64, f64), cells: (usize, usize), topology: Topology) {
+        let layout = BoardLayout::with_camera(window_size, cells, topology, self);
+        let size = layout.cell_size();
+        self.center = [(window_size.0 / 2.0 - layout.position.0) / size,
+                       (window_size.1 / 2.0 - layout.position.1) / size];
+    }
+}
+
+/// Where the board sits inside the window.
+///
+/// The view and the controller both build this from the window size, so a click always lands on
+/// the cell that was drawn under it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BoardLayout {
+    /// Position of the board's left-top corner.
+    pub position: (f64, f64),
+    /// Width and height of the board.
+    pub size: (f64, f64),
+    /// Number of columns and rows.
+    pub cells: (usize, usize),
+    /// Shape of the cells.
+    pub topology: Topology,
+}
+
+impl BoardLayout {
+    /// Fits a board in the window, centering it and leaving the rest as letterboxing.
+    pub fn new(window_size: (f64, f64), cells: (usize, usize), topology: Topology) -> BoardLayout {
+        let extent = topology.extent((cells.0.max(1), cells.1.max(1)));
+        let cell_size = (window_size.0 / extent.0).min(window_size.1 / extent.1).max(0.0);
+        let size = (cell_size * extent.0, cell_size * extent.1);
+        BoardLayout {
+            position: ((window_size.0 - size.0) / 2.0, (window_size.1 - size.1) / 2.0),
+            size: size,
+            cells: cells,
+            topology: topology,
+        }
+    }
+
+    /// Places the board as the camera shows it. Edges that fit the window are centered, and the
+    /// others are kept from leaving gaps at the window edges.
+    pub fn with_camera(window_size: (f64, f64),
+                       cells: (usize, usize),
+                       topology: Topology,
+                       camera: &Camera)
+                       -> BoardLayout {
+        let fit = BoardLayout::new(window_size, cells, topology);
+        let cell_size = match camera.cell_size {
+            Some(size) if size > fit.cell_size() => size,
+            _ => return fit,
+        };
+        let extent = topology.extent(cells);
+        let size = (cell_size * extent.0, cell_size * extent.1);
+        let place = |window: f64, board: f64, center: f64| if board <= window {
+            (window - board) / 2.0
+        } else {
+            (window / 2.0 - center * cell_size).min(0.0).max(window - board)
+        };
+        BoardLayout {
+            position: (place(window_size.0, size.0, camera.center[0]),
+                       place(window_size.1, size.1, camera.center[1])),
+            size: size,
+            cells: cells,
+            topology: topology,
+        }
+    }
+
+    /// Width of a single cell. Square cells are as tall as they are wide.
+    pub fn cell_size(&self) -> f64 {
+        self.size.0 / self.topology.extent((self.cells.0.max(1), self.cells.1.max(1))).0
+    }
+
+    /// Position of the left-top corner of the box around a cell.
+    pub fn cell_pos(&self, x: usize, y: usize) -> (f64, f64) {
+        let origin = self.topology.cell_origin([x, y], self.cells);
+        (self.position.0 + origin[0] * self.cell_size(),
+         self.position.1 + origin[1] * self.cell_size())
+    }
+
+    /// The box around a cell, as `[x, y, width, height]`.
+    pub fn cell_rect(&self, x: usize, y: usize) -> [f64; 4] {
+        let (cell_x, cell_y) = self.cell_pos(x, y);
+        let size = self.cell_size();
+        [cell_x, cell_y, size, size * self.topology.cell_height()]
+    }
+
+    /// Finds the cell under a window coordinate.
+    pub fn cell_at(&self, pos: [f64; 2]) -> Option<[usize; 2]> {
+        let size = self.cell_size();
+        if size <= 0.0 || self.cells.0 == 0 || self.cells.1 == 0 {
+            return None;
+        }
+        let board_pos = [(pos[0] - self.position.0) / size, (pos[1] - self.position.1) / size];
+        self.topology.cell_at(board_pos, self.cells)
+    }
+
+    /// Columns and rows at least partly inside the window, as `[x1, y1, x2, y2]` with the ends
+    /// left out. Stacked boards have their layers side by side, so they get one range for each
+    /// layer, with the rows counted down the whole board.
+    pub fn visible_cells(&self, window_size: (f64, f64)) -> Vec<[usize; 4]> {
+        let topology = self.topology;
+        let size = self.cell_size();
+        if size <= 0.0 {
+            return vec![[0, 0, 0, 0]; topology.layers()];
+        }
+        let first = |position: f64, reach: f64, step: f64| {
+            (((-position / size - reach) / step).floor() + 1.0).max(0.0) as usize
+        };
+        let end = |position: f64, window: f64, step: f64, count: usize| {
+            ((((window - position) / size) / step).ceil().max(0.0) as usize).min(count)
+        };
+        if topology.layers() > 1 {
+            let rows = topology.layer_rows(self.cells);
+            let top = self.position.1;
+            return (0..topology.layers())
+                .map(|layer| {
+                    let origin = topology.cell_origin([0, layer * rows], self.cells);
+                    let left = self.position.0 + origin[0] * size;
+                    [first(left, 1.0, 1.0),
+                     layer * rows + first(top, 1.0, 1.0).min(rows),
+                     end(left, window_size.0, 1.0, self.cells.0),
+                     layer * rows + end(top, window_size.1, 1.0, rows)]
+                })
+                .collect();
+        }
+        let margin = topology.margin();
+        // How far some rows are pushed to the right.
+        let shift = topology.row_shift(1);
+        let column_step = topology.column_step();
+        let step = topology.row_step();
+        // Where the first cell starts, past the ghost cells.
+        let left = self.position.0 + margin * size;
+        let top = self.position.1 + margin * size;
+        vec![[first(left, 1.0 + shift, column_step),
+              first(top, topology.cell_height(), step),
+              end(left, window_size.0, column_step, self.cells.0),
+              end(top, window_size.1, step, self.cells.1)]]
+    }
+}
+
+/// A small picture of the whole board in the window's bottom right corner, shown while zoomed
+/// in.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Minimap {
+    /// Where the minimap is drawn, as `[x, y, width, height]`.
+    pub rect: [f64; 4],
+    /// Width and height of the board, counted in cell widths.
+    pub extent: (f64, f64),
+}
+
+impl Minimap {
+    /// Fits the minimap in the corner, keeping the board's shape.
+    pub fn new(window_size: (f64, f64), cells: (usize, usize), topology: Topology) -> Minimap {
+        let extent = topology.extent(cells);
+        let size = MINIMAP_SIZE.min(window_size.0.min(window_size.1) / 4.0);
+        let scale = size / extent.0.max(extent.1).max(1.0);
+        let width = extent.0 * scale;
+        let height = extent.1 * scale;
+        Minimap {
+            rect: [window_size.0 - width - MINIMAP_MARGIN,
+                   window_size.1 - height - MINIMAP_MARGIN,
+                   width,
+                   height],
+            extent: extent,
+        }
+    }
+
+    /// Board position under a window position, counted in cell widths, if it is on the minimap.
+    pub fn board_pos(&self, pos: [f64; 2]) -> Option<[f64; 2]> {
+        let [x, y, width, height] = self.rect;
+        if pos[0] < x || pos[0] >= x + width || pos[1] < y || pos[1] >= y + height {
+            return None;
+        }
+        Some([(pos[0] - x) / width * self.extent.0, (pos[1] - y) / height * self.extent.1])
+    }
+
+    /// Window rectangle covering a board rectangle given in cell widths.
+    pub fn rect_of(&self, board_rect: [f64; 4]) -> [f64; 4] {
+        let scale_x = self.rect[2] / self.extent.0.max(1.0);
+        let scale_y = self.rect[3] / self.extent.1.max(1.0);
+        [self.rect[0] + board_rect[0] * scale_x,
+         self.rect[1] + board_rect[1] * scale_y,
+         board_rect[2] * scale_x,
+         board_rect[3] * scale_y]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flat_boards_only_show_the_cells_in_the_window() {
+        let cells = (500, 500);
+        let camera = Camera::for_board(cells, Topology::Square);
+        let layout = BoardLayout::with_camera((240.0, 240.0), cells, Topology::Square, &camera);
+        assert_eq!(layout.visible_cells((240.0, 240.0)), vec![[245, 245, 255, 255]]);
+    }
+
+    #[test]
+    fn stacked_boards_cull_each_layer() {
+        let (topology, cells) = (Topology::Layers(2), (500, 1000));
+        let mut camera = Camera::for_board(cells, topology);
+        // The middle of the second layer.
+        camera.look_at([751.0, 250.0], (240.0, 240.0), cells, topology);
+        let layout = BoardLayout::with_camera((240.0, 240.0), cells, topology, &camera);
+        let visible = layout.visible_cells((240.0, 240.0));
+        assert_eq!(visible.len(), 2);
+        let [x1, y1, x2, y2] = visible[0];
+        assert!(x1 >= x2 || y1 >= y2, "{:?}", visible);
+        assert_eq!(visible[1], [245, 745, 255, 755]);
+    }
+}
@@ -1,88 +1,449 @@
-#![deny(missing_docs)]
-
-//! A minesweeper game.
-
-extern crate piston;
-extern crate glutin_window;
-extern crate graphics;
-extern crate opengl_graphics;
-extern crate rand;
-
-use piston::window::{Window, WindowSettings};
-use piston::event_loop::{Events, EventLoop, EventSettings};
-use piston::input::{RenderEvent, CloseEvent};
-use glutin_window::GlutinWindow;
-use opengl_graphics::{OpenGL, Filter, GlGraphics, TextureSettings};
-use opengl_graphics::glyph_cache::GlyphCache;
-
-use gameboard::Gameboard;
-use gameboard_controller::GameboardController;
-use gameboard_view::*;
-use traits::*;
-use mainmenu::*;
-use state::State;
-
-mod gameboard;
-mod gameboard_controller;
-mod gameboard_view;
-mod mainmenu;
-mod traits;
-mod state;
-
-fn main() {
-    let opengl = OpenGL::V4_4;
-    let settings = WindowSettings::new("Sudoku", [600; 2])
-        .opengl(opengl)
-        .samples(2);
-
-    let clear_color = [0.82, 0.9, 0.87, 0.0];
-
-    let mut window: GlutinWindow = settings.build()
-        .expect("Could not create window");
-
-    let mut events = Events::new(EventSettings::new().lazy(true));
-    let mut gl = GlGraphics::new(opengl);
-
-    let texture_settings = TextureSettings::new().filter(Filter::Nearest);
-    let glyphs = &mut GlyphCache::new("assets/Roboto-Bold.ttf", texture_settings)
-        .expect("Could not load font");
-
-    let mut mainmenu = MainMenu::new(MainMenuSettings::new());
-    let gameboard = Gameboard::new();
-    let gameboard_controller = GameboardController::new(gameboard);
-    let gameboard_view_settings = GameboardViewSettings::new();
-    let mut gameboard_view = GameboardView::new(gameboard_view_settings, gameboard_controller);
-
-    let mut state = State::MainMenu;
-
-    while let Some(e) = events.next(&mut window) {
-        if e.close_args().is_some() {
-            break;
-        }
-        match state {
-            State::MainMenu => {
-                state = mainmenu.event((window.size().width as f64, window.size().height as f64),
-                                       &e);
-                if let Some(args) = e.render_args() {
-                    gl.draw(args.viewport(), |c, g| {
-                        graphics::clear(clear_color, g);
-                        mainmenu.draw(glyphs, &c, g);
-                    });
-                }
-            }
-            State::GameBoard => {
-                state = gameboard_view.controller
-                    .event((window.size().width as f64, window.size().height as f64),
-                           &e);
-
-                if let Some(args) = e.render_args() {
-                    gl.draw(args.viewport(), |c, g| {
-                        graphics::clear(clear_color, g);
-                        gameboard_view.draw(glyphs, &c, g);
-                    });
-                }
-            }
-            State::Exiting => break,
-        };
-    }
-}
+#![deny(missing_docs)]
+
+//! A minesweeper game.
+
+extern crate piston;
+extern crate glutin_window;
+extern crate graphics;
+extern crate image;
+extern crate opengl_graphics;
+extern crate rand;
+#[cfg(feature = "sound")]
+extern crate rodio;
+
+use std::env;
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use piston::window::Window;
+use piston::event_loop::{Events, EventLoop, EventSettings};
+use piston::input::{Button, PressEvent, RenderEvent, UpdateEvent, CloseEvent};
+use opengl_graphics::{OpenGL, Filter, GlGraphics, Texture, TextureSettings};
+use opengl_graphics::GlyphCache;
+
+use rand::Rng;
+
+use audio::Sounds;
+use cli::{Options, USAGE};
+use config::{Config, CONFIG_FILE};
+use endless::{EndlessController, EndlessView};
+use gameboard::{seeded_rng, Difficulty, Gameboard};
+use gameboard_controller::{GameEvent, GameboardController};
+use gameboard_view::*;
+use keybindings::Action;
+use traits::*;
+use mainmenu::*;
+use piston_canvas::PistonCanvas;
+use replay::{Playback, Replay};
+use software::Image;
+use sprites::SpriteSheet;
+use state::State;
+use theme::{ThemeSet, THEME_DIR};
+use topology::Topology;
+
+mod animation;
+mod audio;
+mod cli;
+mod config;
+mod endless;
+mod field;
+mod gameboard;
+mod gameboard_controller;
+mod gameboard_view;
+mod headless;
+mod keybindings;
+mod layout;
+mod mainmenu;
+mod particles;
+mod piston_canvas;
+mod pointer;
+mod replay;
+mod software;
+mod solver;
+mod sprites;
+#[cfg(test)]
+mod testing;
+mod traits;
+mod tui;
+mod window;
+mod state;
+mod theme;
+mod toml;
+mod topology;
+
+/// Size the window opens at, and the size of pictures from `--render-board`.
+const WINDOW_SIZE: [u32; 2] = [600; 2];
+
+fn main() {
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("Run with --help to see the options.");
+            process::exit(2);
+        }
+    };
+    if options.help {
+        print!("{}", USAGE);
+        return;
+    }
+
+    let mut config = Config::load(CONFIG_FILE);
+    if let Some(topology) = options.topology {
+        // Stacks picked on the command line are as deep as the settings file says.
+        config.topology = match (topology, config.topology) {
+            (Topology::Layers(_), Topology::Layers(depth)) => Topology::Layers(depth),
+            _ => topology,
+        };
+    }
+    // The difficulty for this session. It starts as asked for on the command line and follows the
+    // menu once the player picks another one there.
+    let mut difficulty = match options.difficulty(config.difficulty) {
+        Ok(difficulty) => difficulty.unwrap_or(config.difficulty),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(2);
+        }
+    };
+    let mut seeds = seeded_rng(options.seed.unwrap_or_else(rand::random));
+    let (gameboard_controller, replay_moves) = match options.load_board() {
+        Ok(Some((board, moves))) => {
+            difficulty = board.difficulty();
+            (setup_controller(&config, board), moves)
+        }
+        Ok(None) => {
+            (new_game(&config, difficulty, options.no_guess, seeds.next_u64()), Vec::new())
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+
+    if options.headless || options.render_board.is_some() {
+        let mut controller = gameboard_controller;
+        if options.headless {
+            if let Err(e) = headless::run(&mut controller, replay_moves) {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+            save_replay(&options, &controller);
+        } else {
+            for mv in replay_moves {
+                controller.apply(mv);
+            }
+        }
+        if let Some(ref path) = options.render_board {
+            let mainmenu = MainMenu::new(ThemeSet::find(THEME_DIR), config);
+            let view = GameboardView::<Image>::new(mainmenu.board_settings(), controller);
+            if let Err(e) = save_screenshot(&view, &mainmenu, WINDOW_SIZE, path) {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+    if options.tui {
+        let mut controller = gameboard_controller;
+        for mv in replay_moves {
+            controller.apply(mv);
+        }
+        let result = tui::run(controller,
+                              &mut || {
+                                  new_game(&config, difficulty, options.no_guess, seeds.next_u64())
+                              },
+                              &mut |controller| save_replay(&options, controller));
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    let first_opengl = options.opengl.unwrap_or(OpenGL::V4_4);
+    let (mut window, opengl) = match window::open("Rustsweeper", WINDOW_SIZE, first_opengl) {
+        Ok(opened) => opened,
+        Err(e) => {
+            eprintln!("Could not create window:\n{}", e);
+            process::exit(1);
+        }
+    };
+    if opengl != first_opengl {
+        eprintln!("OpenGL {} is not available, using {}",
+                  window::opengl_name(first_opengl),
+                  window::opengl_name(opengl));
+    }
+
+    let mut events = Events::new(EventSettings::new().lazy(true));
+    let mut gl = GlGraphics::new(opengl);
+
+    let mut mainmenu = MainMenu::new(ThemeSet::find(THEME_DIR), config);
+
+    let texture_settings = || TextureSettings::new().filter(Filter::Nearest);
+    let mut font = mainmenu.theme.font.clone();
+    let glyphs = &mut GlyphCache::new(&font, (), texture_settings()).expect("Could not load font");
+
+    let mut dealt =
+        (mainmenu.config.difficulty, mainmenu.config.first_click, mainmenu.config.topology);
+    let mut playback = if replay_moves.is_empty() {
+        None
+    } else {
+        Some(Playback::new(replay_moves))
+    };
+    let mut gameboard_view = GameboardView::new(mainmenu.board_settings(), gameboard_controller);
+    let endless_controller = new_endless(&mainmenu.config, seeds.next_u64());
+    let mut endless_view = EndlessView::new(mainmenu.board_settings(), endless_controller);
+    let mut tiles = None;
+    let mut sounds = Sounds::new(audio::default_backend());
+
+    let mut state = if options.endless {
+        State::Endless
+    } else if options.starts_game() {
+        State::GameBoard
+    } else {
+        State::MainMenu
+    };
+    gameboard_view.controller.locked = playback.is_some();
+
+    while let Some(e) = events.next(&mut window) {
+        if e.close_args().is_some() {
+            break;
+        }
+
+        // Pick up theme switches and edits to the theme file.
+        if mainmenu.theme_changed() {
+            gameboard_view.settings = mainmenu.board_settings();
+            endless_view.settings = mainmenu.board_settings();
+            endless_view.text_color = mainmenu.settings.text_color;
+            endless_view.font_size = mainmenu.settings.font_size_button;
+            if mainmenu.theme.font != font {
+                match GlyphCache::new(&mainmenu.theme.font, (), texture_settings()) {
+                    Ok(new_glyphs) => *glyphs = new_glyphs,
+                    Err(e) => eprintln!("Could not load font {}: {}", mainmenu.theme.font, e),
+                }
+                font = mainmenu.theme.font.clone();
+            }
+            if mainmenu.theme.tiles != tiles {
+                let load = |path: &String| {
+                    Texture::from_path(path, &texture_settings())
+                        .and_then(SpriteSheet::new)
+                        .map_err(|e| eprintln!("Could not load tiles {}: {}", path, e))
+                        .ok()
+                };
+                gameboard_view.sprites = mainmenu.theme.tiles.as_ref().and_then(&load);
+                endless_view.sprites = mainmenu.theme.tiles.as_ref().and_then(&load);
+                tiles = mainmenu.theme.tiles.clone();
+            }
+        }
+        // Pick up settings changed in the menu.
+        if mainmenu.config_changed() {
+            let config = &mainmenu.config;
+            gameboard_view.animations.settings = config.animations;
+            gameboard_view.controller.keys = config.keys.clone();
+            gameboard_view.controller.question_marks = config.question_marks;
+            endless_view.controller.keys = config.keys.clone();
+            endless_view.controller.question_marks = config.question_marks;
+            sounds.settings = config.sound;
+        }
+        let clear_color = mainmenu.settings.background_color;
+        match state {
+            State::MainMenu => {
+                state = mainmenu.event((window.size().width as f64, window.size().height as f64),
+                                       &e);
+                // Starting after a game ended, or after picking another kind of board, deals a
+                // new board instead of going back to the old one.
+                if let State::GameBoard = state {
+                    let config = &mainmenu.config;
+                    let settings = (config.difficulty, config.first_click, config.topology);
+                    if gameboard_view.controller.gameboard.is_over() || settings != dealt {
+                        if config.difficulty != dealt.0 {
+                            difficulty = config.difficulty;
+                        }
+                        dealt = settings;
+                        playback = None;
+                        gameboard_view.controller =
+                            new_game(config, difficulty, options.no_guess, seeds.next_u64());
+                        gameboard_view.clear_animations();
+                    }
+                }
+                // A field that went off is swapped for a new one too.
+                if state == State::Endless && endless_view.controller.board.lost {
+                    endless_view.controller = new_endless(&mainmenu.config, seeds.next_u64());
+                }
+                if let Some(args) = e.render_args() {
+                    gl.draw(args.viewport(), |c, g| {
+                        let mut canvas = PistonCanvas::new(c, g, glyphs);
+                        canvas.clear(clear_color);
+                        mainmenu.draw(&mut canvas);
+                    });
+                }
+            }
+            State::GameBoard => {
+                let was_over = gameboard_view.controller.gameboard.is_over();
+                state = gameboard_view.controller
+                    .event((window.size().width as f64, window.size().height as f64),
+                           &e);
+
+                // Replays make their moves as time passes, and hand the board back at the end.
+                if let Some(args) = e.update_args() {
+                    let mut done = false;
+                    if let Some(ref mut playback) = playback {
+                        if let Some(mv) = playback.update(args.dt) {
+                            gameboard_view.controller.apply(mv);
+                        }
+                        done = playback.is_done();
+                    }
+                    if done {
+                        playback = None;
+                        gameboard_view.controller.locked = false;
+                    }
+                }
+
+                for event in gameboard_view.take_events() {
+                    if event == GameEvent::GuessNeeded {
+                        eprintln!("{}", gameboard_controller::GUESS_WARNING);
+                    }
+                    sounds.handle(&event);
+                }
+                if !was_over && gameboard_view.controller.gameboard.is_over() {
+                    save_replay(&options, &gameboard_view.controller);
+                }
+
+                if let Some(Button::Keyboard(key)) = e.press_args() {
+                    if gameboard_view.controller.keys.action(key) == Some(Action::Screenshot) {
+                        let size = window.size();
+                        let secs = SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .map(|time| time.as_secs())
+                            .unwrap_or(0);
+                        let path = format!("screenshot-{}.png", secs);
+                        match save_screenshot(&gameboard_view,
+                                              &mainmenu,
+                                              [size.width, size.height],
+                                              &path) {
+                            Ok(()) => println!("Saved {}", path),
+                            Err(e) => eprintln!("{}", e),
+                        }
+                    }
+                }
+
+                if let Some(args) = e.update_args() {
+                    gameboard_view.update(args.dt);
+                }
+
+                if let Some(args) = e.render_args() {
+                    gl.draw(args.viewport(), |c, g| {
+                        let mut canvas = PistonCanvas::new(c, g, glyphs);
+                        canvas.clear(clear_color);
+                        gameboard_view.draw(&mut canvas);
+                    });
+                }
+            }
+            State::Endless => {
+                state = endless_view.controller
+                    .event((window.size().width as f64, window.size().height as f64),
+                           &e);
+                if let Some(args) = e.render_args() {
+                    gl.draw(args.viewport(), |c, g| {
+                        let mut canvas = PistonCanvas::new(c, g, glyphs);
+                        canvas.clear(clear_color);
+                        endless_view.draw(&mut canvas);
+                    });
+                }
+            }
+            State::Exiting => break,
+        };
+
+        // Only keep the loop ticking while something is moving.
+        let animating = match state {
+            State::GameBoard => gameboard_view.is_animating() || playback.is_some(),
+            _ => false,
+        };
+        events.set_lazy(!animating);
+    }
+}
+
+/// Deals a new board using the player's settings.
+fn new_game(config: &Config, difficulty: Difficulty, no_guess: bool, seed: u64)
+            -> GameboardController {
+    // Every layer of a stack gets the whole board. Stacks too tall for the size limits lose
+    // layers until they fit.
+    let mut topology = config.topology;
+    let mut stacked = difficulty.stacked(topology.layers());
+    while stacked.validate().is_err() && topology.layers() > 1 {
+        topology = Topology::Layers(topology.layers() - 1);
+        stacked = difficulty.stacked(topology.layers());
+    }
+    if topology != config.topology {
+        eprintln!("{} layers of {}x{} don't fit, playing {}",
+                  config.topology.layers(),
+                  difficulty.width,
+                  difficulty.height,
+                  topology.layers());
+    }
+    let mut board = Gameboard::new(stacked, config.first_click, seed);
+    board.no_guess = no_guess;
+    board.topology = topology;
+    setup_controller(config, board)
+}
+
+/// Sets up a controller for a board using the player's settings.
+fn setup_controller(config: &Config, board: Gameboard) -> GameboardController {
+    let mut controller = GameboardController::new(board);
+    controller.keys = config.keys.clone();
+    controller.question_marks = config.question_marks;
+    controller
+}
+
+/// Starts an endless game using the player's settings.
+fn new_endless(config: &Config, seed: u64) -> EndlessController {
+    let mut controller = EndlessController::new(seed);
+    controller.keys = config.keys.clone();
+    controller.question_marks = config.question_marks;
+    controller
+}
+
+/// Saves the game to the `--record` file, if one was given.
+fn save_replay(options: &Options, controller: &GameboardController) {
+    if let Some(ref path) = options.record {
+        if let Some(replay) = Replay::record(&controller.gameboard, &controller.moves) {
+            if let Err(e) = replay.save(path) {
+                eprintln!("{}", e);
+            }
+        }
+    }
+}
+
+/// Draws the board on the CPU and saves it as a PNG.
+fn save_screenshot<T>(view: &GameboardView<T>,
+                      mainmenu: &MainMenu,
+                      size: [u32; 2],
+                      path: &str)
+                      -> Result<(), String> {
+    // The view's tiles are GPU textures, so the atlas is loaded again for the CPU.
+    let sprites = match mainmenu.theme.tiles {
+        Some(ref tiles) => Some(SpriteSheet::new(Image::load(tiles)?)?),
+        None => None,
+    };
+    software::render_board(view, sprites.as_ref(), size, mainmenu.settings.background_color)
+        .save_png(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gameboard::{BEGINNER, MARATHON};
+
+    #[test]
+    fn stacks_too_tall_lose_layers() {
+        let mut config = Config::new();
+        config.topology = Topology::Layers(4);
+        let board = new_game(&config, MARATHON, false, 1).gameboard;
+        assert_eq!(board.topology, Topology::Layers(2));
+        assert_eq!((board.width, board.height, board.mines), (500, 1000, 80000));
+
+        let board = new_game(&config, BEGINNER, false, 1).gameboard;
+        assert_eq!(board.topology, Topology::Layers(4));
+        assert_eq!((board.width, board.height, board.mines), (9, 36, 40));
+    }
+}
@@ -1,155 +1,412 @@
-use graphics::types::Color;
-use graphics::{Context, Graphics, text, Rectangle};
-use graphics::character::CharacterCache;
-use piston::input::GenericEvent;
-use piston::input::keyboard::Key;
-use graphics::Transformed;
-
-use traits::*;
-use state::State;
-
-/// Stores main menu settings.
-pub struct MainMenuSettings {
-    /// Position from left-top corner.
-    pub position: (f64, f64),
-    /// Size of the main menu.
-    pub size: f64,
-    /// Background color.
-    pub background_color: Color,
-    /// Text color.
-    pub text_color: Color,
-    /// Button box color.
-    pub box_color: Color,
-    /// Button font size.
-    pub font_size_button: u32,
-    /// Title font size.
-    pub font_size_title: u32,
-    /// Button text margin.
-    pub button_text_margin: f64,
-}
-
-impl MainMenuSettings {
-    /// Creates a new main menu settings object.
-    pub fn new() -> MainMenuSettings {
-        MainMenuSettings {
-            position: (0.0, 0.0),
-            size: 600.0,
-            background_color: [0.82, 0.9, 0.87, 1.0],
-            text_color: [0.0, 0.0, 0.1, 1.0],
-            box_color: [0.01, 0.52, 0.59, 1.0],
-            font_size_button: 32,
-            font_size_title: 38,
-            button_text_margin: 7.0,
-        }
-    }
-}
-
-pub struct MainMenu {
-    pub settings: MainMenuSettings,
-    cursor_pos: [f64; 2],
-}
-
-impl MainMenu {
-    /// Creates a new main menu object view.
-    pub fn new(settings: MainMenuSettings) -> MainMenu {
-        MainMenu {
-            settings: settings,
-            cursor_pos: [0.0, 0.0],
-        }
-    }
-
-    fn get_button_rect(&self, index: u8, windowwidth: f64, windowheight: f64) -> [f64; 4] {
-        [windowwidth / 3.0,
-         windowheight / 10.0 * (index) as f64 + 5.0,
-         windowwidth / 3.0,
-         windowheight / 10.0 as f64 - 10.0]
-    }
-
-    fn drawcenteredtext<G: Graphics, C>(&self,
-                                        text: &str,
-                                        index: u8,
-                                        fontsize: u32,
-                                        glyphs: &mut C,
-                                        c: &Context,
-                                        g: &mut G)
-        where C: CharacterCache<Texture = G::Texture>
-    {
-        let rect = self.get_button_rect(index, c.get_view_size()[0], c.get_view_size()[1]);
-        let width = glyphs.width(fontsize, text);
-        let x = c.get_view_size()[0] / 2.0 - width / 2.0;
-        let transform = c.trans(x, rect[1] + rect[3] / 2.0 + 10.0).transform;
-        text::Text::new_color([0.0, 0.0, 0.0, 1.0], fontsize)
-            .draw(text, glyphs, &c.draw_state, transform, g);
-    }
-
-    fn drawcenteredtextwithbox<G: Graphics, C>(&self,
-                                               text: &str,
-                                               index: u8,
-                                               fontsize: u32,
-                                               glyphs: &mut C,
-                                               c: &Context,
-                                               g: &mut G)
-        where C: CharacterCache<Texture = G::Texture>
-    {
-        let rect = self.get_button_rect(index, c.get_view_size()[0], c.get_view_size()[1]);
-        Rectangle::new_round(self.settings.box_color, 5.0)
-            .draw(rect, &c.draw_state, c.transform, g);
-        self.drawcenteredtext(text, index, fontsize, glyphs, c, g);
-    }
-
-    /// Returns the index of the clicked button. Probably.
-    fn clicked_button(&self, mousepos: [f64; 2], windowsize: (f64, f64)) -> Option<u8> {
-        let rect = self.get_button_rect(0, windowsize.0, windowsize.1);
-        if mousepos[0] < rect[0] || mousepos[0] > rect[0] + rect[2] || mousepos[1] < 0.0 ||
-           mousepos[1] > windowsize.1 {
-            return None;
-        }
-        Some((mousepos[1] / (windowsize.1 / 10.0)) as u8)
-    }
-}
-
-impl EventHandler for MainMenu {
-    fn event<E: GenericEvent>(&mut self, size: (f64, f64), e: &E) -> State {
-        use piston::input::{Button, MouseButton};
-
-        let mut nextstate = State::MainMenu;
-
-        // Save mouse position.
-        if let Some(pos) = e.mouse_cursor_args() {
-            self.cursor_pos = pos;
-        }
-
-        // Handle button clicks.
-        if let Some(Button::Mouse(MouseButton::Left)) = e.press_args() {
-            if let Some(index) = self.clicked_button(self.cursor_pos, size) {
-                nextstate = match index {
-                    4 => State::GameBoard,
-                    5 => State::Exiting,
-                    _ => nextstate,
-                };
-            }
-        }
-
-        // Exit when you press ESC.
-        if let Some(Button::Keyboard(Key::Escape)) = e.press_args() {
-            nextstate = State::Exiting;
-        }
-
-        nextstate
-    }
-}
-
-impl Renderer for MainMenu {
-    fn draw<G: Graphics, C>(&self, glyphs: &mut C, c: &Context, g: &mut G)
-        where C: CharacterCache<Texture = G::Texture>
-    {
-        self.drawcenteredtext("Rustsweeper!",
-                              1,
-                              self.settings.font_size_title,
-                              glyphs,
-                              c,
-                              g);
-        self.drawcenteredtextwithbox("Start", 4, self.settings.font_size_button, glyphs, c, g);
-        self.drawcenteredtextwithbox("Quit", 5, self.settings.font_size_button, glyphs, c, g);
-    }
-}
+use graphics::types::Color;
+use graphics::ImageSize;
+use piston::input::GenericEvent;
+use piston::input::keyboard::Key;
+
+use animation::AnimationSettings;
+use config::{Config, CONFIG_FILE};
+use gameboard_view::GameboardViewSettings;
+use theme::{Theme, ThemeSet, DEFAULT_THEME};
+use traits::*;
+use state::State;
+
+/// Stores main menu settings.
+pub struct MainMenuSettings {
+    /// Position from left-top corner.
+    pub position: (f64, f64),
+    /// Size of the main menu.
+    pub size: f64,
+    /// Background color.
+    pub background_color: Color,
+    /// Text color.
+    pub text_color: Color,
+    /// Button box color.
+    pub box_color: Color,
+    /// Button box corner rounding.
+    pub box_rounding: f64,
+    /// Button font size.
+    pub font_size_button: u32,
+    /// Title font size.
+    pub font_size_title: u32,
+    /// Button text margin.
+    pub button_text_margin: f64,
+}
+
+impl MainMenuSettings {
+    /// Creates main menu settings from a theme.
+    pub fn from_theme(theme: &Theme) -> MainMenuSettings {
+        MainMenuSettings {
+            position: (0.0, 0.0),
+            size: 600.0,
+            background_color: theme.background_color,
+            text_color: theme.menu_text_color,
+            box_color: theme.menu_button_color,
+            box_rounding: theme.menu_button_rounding,
+            font_size_button: theme.font_size_button,
+            font_size_title: theme.font_size_title,
+            button_text_margin: 7.0,
+        }
+    }
+}
+
+/// Which screen of the menu is shown.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Page {
+    /// Start, settings and quit.
+    Main,
+    /// One button per setting.
+    Settings,
+}
+
+pub struct MainMenu {
+    pub settings: MainMenuSettings,
+    /// Player settings, saved whenever they change.
+    pub config: Config,
+    /// Theme in use.
+    pub theme: Theme,
+    /// Theme files to pick from.
+    themes: ThemeSet,
+    /// Screen being shown.
+    page: Page,
+    /// Set when the theme changed and hasn't been picked up yet.
+    theme_changed: bool,
+    /// Set when any other setting changed and hasn't been picked up yet.
+    config_changed: bool,
+    cursor_pos: [f64; 2],
+}
+
+impl MainMenu {
+    /// Creates a new main menu object view, using the theme picked in `config` if there is one.
+    pub fn new(mut themes: ThemeSet, config: Config) -> MainMenu {
+        if !themes.select(&config.theme) {
+            themes.select(DEFAULT_THEME);
+        }
+        let theme = themes.load();
+        MainMenu {
+            settings: MainMenuSettings::from_theme(&theme),
+            config: config,
+            theme: theme,
+            themes: themes,
+            page: Page::Main,
+            theme_changed: true,
+            config_changed: true,
+            cursor_pos: [0.0, 0.0],
+        }
+    }
+
+    /// Returns true once after the theme was switched or its file changed on disk.
+    pub fn theme_changed(&mut self) -> bool {
+        if let Some(theme) = self.themes.reload_if_changed() {
+            self.set_theme(theme);
+        }
+        let changed = self.theme_changed;
+        self.theme_changed = false;
+        changed
+    }
+
+    /// Returns true once after a setting was changed.
+    pub fn config_changed(&mut self) -> bool {
+        let changed = self.config_changed;
+        self.config_changed = false;
+        changed
+    }
+
+    /// Gameboard view settings for the chosen theme and number colors.
+    pub fn board_settings(&self) -> GameboardViewSettings {
+        let mut settings = GameboardViewSettings::from_theme(&self.theme);
+        settings.number_colors = self.config.number_palette.colors(settings.number_colors);
+        settings
+    }
+
+    fn set_theme(&mut self, theme: Theme) {
+        self.settings = MainMenuSettings::from_theme(&theme);
+        self.theme = theme;
+        self.theme_changed = true;
+    }
+
+    /// Marks the settings as changed and writes them to the config file.
+    fn save_config(&mut self) {
+        self.config_changed = true;
+        if let Err(e) = self.config.save(CONFIG_FILE) {
+            eprintln!("{}", e);
+        }
+    }
+
+    /// Changes the setting behind a button on the settings page.
+    fn change_setting(&mut self, index: u8) {
+        match index {
+            1 => self.config.difficulty = self.config.difficulty.next(),
+            2 => self.config.first_click = self.config.first_click.next(),
+            3 => self.config.topology = self.config.topology.next(self.config.neighbours),
+            4 => self.config.question_marks = !self.config.question_marks,
+            5 => {
+                let theme = self.themes.next();
+                self.set_theme(theme);
+                if let Some(name) = self.themes.current_name() {
+                    self.config.theme = name;
+                }
+            }
+            6 => {
+                self.config.number_palette = self.config.number_palette.next();
+                self.theme_changed = true;
+            }
+            7 => self.config.sound.next_volume(),
+            8 => self.config.sound.muted = !self.config.sound.muted,
+            9 => {
+                let animations = &mut self.config.animations;
+                let on = !any_animations(animations);
+                animations.flip = on;
+                animations.flood_wave = on;
+                animations.flag_drop = on;
+                animations.explosion = on;
+                animations.confetti = on;
+            }
+            _ => return,
+        }
+        self.save_config();
+    }
+
+    /// Number of rows the window is split into, counting the title.
+    fn rows(&self) -> f64 {
+        match self.page {
+            Page::Main => 10.0,
+            Page::Settings => 11.0,
+        }
+    }
+
+    fn get_button_rect(&self, index: u8, windowwidth: f64, windowheight: f64) -> [f64; 4] {
+        // Setting names are longer than the main buttons, so their page gets wider buttons.
+        let width = match self.page {
+            Page::Main => windowwidth / 3.0,
+            Page::Settings => windowwidth * 0.6,
+        };
+        let row = windowheight / self.rows();
+        [(windowwidth - width) / 2.0, row * (index) as f64 + 5.0, width, row - 10.0]
+    }
+
+    fn drawcenteredtext<C: Canvas>(&self, text: &str, index: u8, fontsize: u32, canvas: &mut C) {
+        let size = canvas.size();
+        let rect = self.get_button_rect(index, size[0], size[1]);
+        let width = canvas.text_width(fontsize, text);
+        let x = size[0] / 2.0 - width / 2.0;
+        canvas.text(self.settings.text_color,
+                    fontsize,
+                    text,
+                    [x, rect[1] + rect[3] / 2.0 + 10.0]);
+    }
+
+    fn drawcenteredtextwithbox<C: Canvas>(&self,
+                                          text: &str,
+                                          index: u8,
+                                          fontsize: u32,
+                                          canvas: &mut C) {
+        let size = canvas.size();
+        let rect = self.get_button_rect(index, size[0], size[1]);
+        canvas.rectangle(self.settings.box_color, rect, self.settings.box_rounding);
+        self.drawcenteredtext(text, index, fontsize, canvas);
+    }
+
+    /// Returns the index of the clicked button. Probably.
+    fn clicked_button(&self, mousepos: [f64; 2], windowsize: (f64, f64)) -> Option<u8> {
+        let rect = self.get_button_rect(0, windowsize.0, windowsize.1);
+        if mousepos[0] < rect[0] || mousepos[0] > rect[0] + rect[2] || mousepos[1] < 0.0 ||
+           mousepos[1] > windowsize.1 {
+            return None;
+        }
+        Some((mousepos[1] / (windowsize.1 / self.rows())) as u8)
+    }
+}
+
+impl EventHandler for MainMenu {
+    fn event<E: GenericEvent>(&mut self, size: (f64, f64), e: &E) -> State {
+        use piston::input::{Button, MouseButton};
+
+        let mut nextstate = State::MainMenu;
+
+        // Save mouse position.
+        if let Some(pos) = e.mouse_cursor_args() {
+            self.cursor_pos = pos;
+        }
+
+        // Handle button clicks.
+        if let Some(Button::Mouse(MouseButton::Left)) = e.press_args() {
+            if let Some(index) = self.clicked_button(self.cursor_pos, size) {
+                match self.page {
+                    Page::Main => {
+                        match index {
+                            4 => nextstate = State::GameBoard,
+                            5 => nextstate = State::Endless,
+                            6 => self.page = Page::Settings,
+                            7 => nextstate = State::Exiting,
+                            _ => {}
+                        }
+                    }
+                    Page::Settings if index == 10 => self.page = Page::Main,
+                    Page::Settings => self.change_setting(index),
+                }
+            }
+        }
+
+        // ESC leaves the settings, or exits from the main page.
+        if let Some(Button::Keyboard(Key::Escape)) = e.press_args() {
+            match self.page {
+                Page::Main => nextstate = State::Exiting,
+                Page::Settings => self.page = Page::Main,
+            }
+        }
+
+        nextstate
+    }
+}
+
+impl<T: ImageSize> Renderer<T> for MainMenu {
+    fn draw<C: Canvas<Texture = T>>(&self, canvas: &mut C) {
+        let font_size = self.settings.font_size_button;
+        match self.page {
+            Page::Main => {
+                self.drawcenteredtext("Rustsweeper!", 1, self.settings.font_size_title, canvas);
+                self.drawcenteredtextwithbox("Start", 4, font_size, canvas);
+                self.drawcenteredtextwithbox("Endless", 5, font_size, canvas);
+                self.drawcenteredtextwithbox("Settings", 6, font_size, canvas);
+                self.drawcenteredtextwithbox("Quit", 7, font_size, canvas);
+            }
+            Page::Settings => {
+                let config = &self.config;
+                let difficulty = match config.difficulty.name() {
+                    "Custom" => {
+                        format!("Custom {}x{}", config.difficulty.width, config.difficulty.height)
+                    }
+                    name => name.to_string(),
+                };
+                let volume = format!("Volume {}%", (config.sound.volume * 100.0).round());
+                let labels = [difficulty.as_str(),
+                              config.first_click.name(),
+                              config.topology.name(),
+                              if config.question_marks {
+                                  "Question marks"
+                              } else {
+                                  "No question marks"
+                              },
+                              &self.theme.name,
+                              config.number_palette.name(),
+                              &volume,
+                              if config.sound.muted { "Sound off" } else { "Sound on" },
+                              if any_animations(&config.animations) {
+                                  "Animations on"
+                              } else {
+                                  "Animations off"
+                              },
+                              "Back"];
+                self.drawcenteredtext("Settings", 0, self.settings.font_size_title, canvas);
+                for (i, label) in labels.iter().enumerate() {
+                    self.drawcenteredtextwithbox(label, i as u8 + 1, font_size, canvas);
+                }
+            }
+        }
+    }
+}
+
+fn any_animations(animations: &AnimationSettings) -> bool {
+    animations.flip || animations.flood_wave || animations.flag_drop || animations.explosion ||
+    animations.confetti
+}
+
+#[cfg(test)]
+mod tests {
+    use piston::input::MouseButton;
+
+    use software::Image;
+    use testing::{check_golden, render, Script};
+    use super::*;
+
+    const SIZE: (f64, f64) = (600.0, 600.0);
+
+    fn menu() -> MainMenu {
+        // No theme files, so the menu always uses the built-in theme.
+        MainMenu::new(ThemeSet::find(""), Config::new())
+    }
+
+    /// Clicks the middle of the button in a row of the menu.
+    fn click_button(index: u8) -> Script {
+        let y = SIZE.1 / 10.0 * (index as f64 + 0.5);
+        Script::new().click_at(SIZE.0 / 2.0, y, MouseButton::Left)
+    }
+
+    /// Clicks the middle of a button on the settings page, which has one more row.
+    fn click_setting(index: u8) -> Script {
+        let y = SIZE.1 / 11.0 * (index as f64 + 0.5);
+        Script::new().click_at(SIZE.0 / 2.0, y, MouseButton::Left)
+    }
+
+    fn draw(menu: &MainMenu) -> Image {
+        render(menu, [SIZE.0 as u32, SIZE.1 as u32], menu.settings.background_color)
+    }
+
+    #[test]
+    fn start_button_starts_game() {
+        let states = click_button(4).run(&mut menu(), SIZE);
+        assert_eq!(states, vec![State::MainMenu, State::GameBoard, State::MainMenu]);
+    }
+
+    #[test]
+    fn endless_button_starts_endless_mode() {
+        assert_eq!(click_button(5).run(&mut menu(), SIZE)[1], State::Endless);
+    }
+
+    #[test]
+    fn quit_button_and_escape_exit() {
+        assert_eq!(click_button(7).run(&mut menu(), SIZE)[1], State::Exiting);
+        assert_eq!(Script::new().key(Key::Escape).run(&mut menu(), SIZE)[0], State::Exiting);
+    }
+
+    #[test]
+    fn clicks_beside_buttons_do_nothing() {
+        let mut menu = menu();
+        let states = Script::new()
+            .click_at(20.0, 270.0, MouseButton::Left)
+            .click_at(300.0, 270.0, MouseButton::Right)
+            .click_at(300.0, 90.0, MouseButton::Left)
+            .run(&mut menu, SIZE);
+        assert!(states.iter().all(|&state| state == State::MainMenu));
+        assert_eq!(menu.page, Page::Main);
+    }
+
+    #[test]
+    fn settings_page_opens_and_closes() {
+        let mut menu = menu();
+        click_button(6).run(&mut menu, SIZE);
+        assert_eq!(menu.page, Page::Settings);
+
+        // Escape goes back to the main page instead of quitting.
+        assert_eq!(Script::new().key(Key::Escape).run(&mut menu, SIZE)[0], State::MainMenu);
+        assert_eq!(menu.page, Page::Main);
+
+        click_button(6).run(&mut menu, SIZE);
+        click_setting(10).run(&mut menu, SIZE);
+        assert_eq!(menu.page, Page::Main);
+    }
+
+    #[test]
+    fn any_single_animation_counts_as_on() {
+        let mut animations = AnimationSettings::new();
+        animations.flip = false;
+        animations.flag_drop = false;
+        animations.explosion = false;
+        animations.confetti = false;
+        assert!(any_animations(&animations));
+        animations.flood_wave = false;
+        assert!(!any_animations(&animations));
+    }
+
+    #[test]
+    fn main_page_matches_golden() {
+        check_golden("main_menu", &draw(&menu()));
+    }
+
+    #[test]
+    fn settings_page_matches_golden() {
+        let mut menu = menu();
+        click_button(6).run(&mut menu, SIZE);
+        check_golden("settings_menu", &draw(&menu));
+    }
+}
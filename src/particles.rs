@@ -0,0 +1,135 @@
+//! Confetti for winning.
+//!
+//! Positions are in board units, where the board goes from 0 to 1 on both axes, so the confetti
+//! keeps its place when the window is resized. The random generator is seeded, so the same seed
+//! always throws the same confetti.
+
+use std::f64::consts::PI;
+
+use graphics::types::Color;
+use rand::{Rng, XorShiftRng};
+
+use gameboard::seeded_rng;
+
+/// Board lengths per second squared pulling confetti down.
+const GRAVITY: f64 = 0.6;
+/// How much speed confetti loses to the air each second.
+const DRAG: f64 = 0.8;
+
+/// A single piece of confetti.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Particle {
+    /// Center, in board units.
+    pub pos: [f64; 2],
+    /// Speed, in board units per second.
+    pub vel: [f64; 2],
+    /// Rotation in radians.
+    pub angle: f64,
+    /// Rotation speed in radians per second.
+    pub spin: f64,
+    /// Width and height, in board units.
+    pub size: [f64; 2],
+    /// Fill color.
+    pub color: Color,
+    /// Seconds left before it disappears.
+    pub life: f64,
+}
+
+/// Confetti flying over the board.
+pub struct Confetti {
+    /// Pieces still in the air.
+    pub particles: Vec<Particle>,
+    rng: XorShiftRng,
+}
+
+impl Confetti {
+    /// Creates an empty confetti system. The same seed always gives the same confetti.
+    pub fn new(seed: u64) -> Confetti {
+        Confetti {
+            particles: Vec::new(),
+            rng: seeded_rng(seed),
+        }
+    }
+
+    /// Throws `count` pieces up from the bottom edge of the board, in the given colors.
+    pub fn burst(&mut self, count: usize, colors: &[Color]) {
+        if colors.is_empty() {
+            return;
+        }
+        for _ in 0..count {
+            let rng = &mut self.rng;
+            let x = rng.gen_range(0.0, 1.0);
+            self.particles.push(Particle {
+                pos: [x, 1.05],
+                vel: [(0.5 - x) * rng.gen_range(0.2, 0.8), -rng.gen_range(0.9, 1.6)],
+                angle: rng.gen_range(0.0, 2.0 * PI),
+                spin: rng.gen_range(-8.0, 8.0),
+                size: [rng.gen_range(0.01, 0.02), rng.gen_range(0.005, 0.012)],
+                color: colors[rng.gen_range(0, colors.len())],
+                life: rng.gen_range(2.5, 4.0),
+            });
+        }
+    }
+
+    /// Moves every piece along by `dt` seconds and drops the ones that are done.
+    pub fn update(&mut self, dt: f64) {
+        let drag = (1.0 - DRAG * dt).max(0.0);
+        for p in &mut self.particles {
+            p.vel[1] += GRAVITY * dt;
+            p.vel[0] *= drag;
+            p.vel[1] *= drag;
+            p.pos[0] += p.vel[0] * dt;
+            p.pos[1] += p.vel[1] * dt;
+            p.angle += p.spin * dt;
+            p.life -= dt;
+        }
+        self.particles.retain(|p| p.life > 0.0 && p.pos[1] < 1.2);
+    }
+
+    /// Whether any confetti is still in the air.
+    pub fn is_running(&self) -> bool {
+        !self.particles.is_empty()
+    }
+
+    /// Removes all confetti.
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLORS: [Color; 3] = [[1.0, 0.0, 0.0, 1.0], [0.0, 1.0, 0.0, 1.0], [0.0, 0.0, 1.0, 1.0]];
+
+    #[test]
+    fn same_seed_throws_the_same_confetti() {
+        let mut a = Confetti::new(7);
+        let mut b = Confetti::new(7);
+        a.burst(50, &COLORS);
+        b.burst(50, &COLORS);
+        assert_eq!(a.particles, b.particles);
+
+        // A second burst carries on from the same place in the random sequence.
+        a.burst(20, &COLORS);
+        b.burst(20, &COLORS);
+        assert_eq!(a.particles, b.particles);
+
+        for _ in 0..150 {
+            a.update(1.0 / 30.0);
+            b.update(1.0 / 30.0);
+            assert_eq!(a.particles, b.particles);
+        }
+        assert!(!a.is_running());
+    }
+
+    #[test]
+    fn other_seeds_throw_other_confetti() {
+        let mut a = Confetti::new(7);
+        let mut b = Confetti::new(8);
+        a.burst(50, &COLORS);
+        b.burst(50, &COLORS);
+        assert!(a.particles != b.particles);
+    }
+}
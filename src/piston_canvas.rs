@@ -0,0 +1,124 @@
+//! Drawing through piston's `Graphics`, which is what the window uses.
+
+use graphics;
+use graphics::character::CharacterCache;
+use graphics::types::Color;
+use graphics::{Context, Ellipse, Graphics, Image, ImageSize, Line, Polygon, Rectangle,
+               Transformed};
+use graphics::text::Text;
+
+use traits::Canvas;
+
+/// A canvas drawing with any piston graphics backend and glyph cache.
+pub struct PistonCanvas<'a, G: 'a, C: 'a> {
+    context: Context,
+    graphics: &'a mut G,
+    glyphs: &'a mut C,
+}
+
+impl<'a, G, C> PistonCanvas<'a, G, C> {
+    /// Creates a canvas for one frame.
+    pub fn new(context: Context, graphics: &'a mut G, glyphs: &'a mut C) -> PistonCanvas<'a, G, C> {
+        PistonCanvas {
+            context: context,
+            graphics: graphics,
+            glyphs: glyphs,
+        }
+    }
+}
+
+impl<'a, T, G, C> Canvas for PistonCanvas<'a, G, C>
+    where T: ImageSize,
+          G: Graphics<Texture = T>,
+          C: CharacterCache<Texture = T>
+{
+    type Texture = T;
+
+    fn size(&self) -> [f64; 2] {
+        self.context.get_view_size()
+    }
+
+    fn clear(&mut self, color: Color) {
+        graphics::clear(color, self.graphics);
+    }
+
+    fn rectangle(&mut self, color: Color, rect: [f64; 4], radius: f64) {
+        let c = &self.context;
+        let rectangle = if radius > 0.0 {
+            Rectangle::new_round(color, radius)
+        } else {
+            Rectangle::new(color)
+        };
+        rectangle.draw(rect, &c.draw_state, c.transform, self.graphics);
+    }
+
+    fn border(&mut self, color: Color, rect: [f64; 4], radius: f64) {
+        let c = &self.context;
+        Rectangle::new_border(color, radius).draw(rect, &c.draw_state, c.transform, self.graphics);
+    }
+
+    fn rotated_rectangle(&mut self, color: Color, center: [f64; 2], size: [f64; 2], angle: f64) {
+        let c = &self.context;
+        let transform = c.transform.trans(center[0], center[1]).rot_rad(angle);
+        Rectangle::new(color).draw([-size[0] / 2.0, -size[1] / 2.0, size[0], size[1]],
+                                   &c.draw_state,
+                                   transform,
+                                   self.graphics);
+    }
+
+    fn polygon(&mut self, color: Color, corners: &[[f64; 2]]) {
+        let c = &self.context;
+        Polygon::new(color).draw(corners, &c.draw_state, c.transform, self.graphics);
+    }
+
+    fn ellipse(&mut self, color: Color, rect: [f64; 4]) {
+        let c = &self.context;
+        Ellipse::new(color).draw(rect, &c.draw_state, c.transform, self.graphics);
+    }
+
+    fn line(&mut self, color: Color, line: [f64; 4], radius: f64) {
+        let c = &self.context;
+        Line::new(color, radius).draw(line, &c.draw_state, c.transform, self.graphics);
+    }
+
+    fn image(&mut self, texture: &T, src: [f64; 4], rect: [f64; 4]) {
+        let c = &self.context;
+        Image::new()
+            .rect(rect)
+            .src_rect(src)
+            .draw(texture, &c.draw_state, c.transform, self.graphics);
+    }
+
+    fn text_width(&mut self, font_size: u32, text: &str) -> f64 {
+        self.glyphs.width(font_size, text).unwrap_or(0.0)
+    }
+
+    fn text(&mut self, color: Color, font_size: u32, text: &str, pos: [f64; 2]) {
+        let c = &self.context;
+        // Characters the font can't draw are left out.
+        let _ = Text::new_color(color, font_size).draw(text,
+                                                       self.glyphs,
+                                                       &c.draw_state,
+                                                       c.transform.trans(pos[0], pos[1]),
+                                                       self.graphics);
+    }
+
+    fn character_size(&mut self, font_size: u32, ch: char) -> [f64; 2] {
+        match self.glyphs.character(font_size, ch) {
+            Ok(character) => [character.width(), character.height()],
+            Err(_) => [0.0, 0.0],
+        }
+    }
+
+    fn character(&mut self, color: Color, font_size: u32, ch: char, pos: [f64; 2], squeeze: f64) {
+        let c = &self.context;
+        let character = match self.glyphs.character(font_size, ch) {
+            Ok(character) => character,
+            Err(_) => return,
+        };
+        Image::new_color(color).draw(character.texture,
+                                     &c.draw_state,
+                                     c.transform.trans(pos[0], pos[1]).scale(squeeze, 1.0),
+                                     self.graphics);
+    }
+}
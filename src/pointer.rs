@@ -0,0 +1,203 @@
+//! Mouse play, shared by every kind of board.
+//!
+//! A left press only pushes a cell down and opens it on release, so dragging off the cell cancels
+//! it. Moving far enough with the left button held drags the board instead. Right click flags
+//! straight away, and the middle button or both buttons together chord.
+
+use piston::input::{Button, GenericEvent, MouseButton};
+
+/// How far the mouse has to move with the left button held before it drags the board instead of
+/// pressing a cell, in pixels.
+pub const DRAG_DISTANCE: f64 = 4.0;
+
+/// A move the mouse makes on a cell, given by the board's `Field::Pos`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Click<P> {
+    /// Open the cell.
+    Reveal(P),
+    /// Flag the cell, or take the flag off.
+    Flag(P),
+    /// Open the cells around a number.
+    Chord(P),
+}
+
+/// Where the mouse is and which of its buttons are held.
+pub struct Pointer {
+    /// Last mouse cursor position.
+    pub cursor_pos: [f64; 2],
+    /// Where the left button went down, while it is held on the board.
+    drag_from: Option<[f64; 2]>,
+    /// Whether the held left button drags the board around.
+    panning: bool,
+    /// Whether the left mouse button is held down.
+    left_held: bool,
+    /// Whether the right mouse button is held down.
+    right_held: bool,
+    /// Whether a chord is being pressed with the middle button or both buttons.
+    chording: bool,
+}
+
+impl Pointer {
+    /// Creates a pointer with no buttons held.
+    pub fn new() -> Pointer {
+        Pointer {
+            cursor_pos: [0.0; 2],
+            drag_from: None,
+            panning: false,
+            left_held: false,
+            right_held: false,
+            chording: false,
+        }
+    }
+
+    /// Moves the cursor, and returns how far it went.
+    pub fn move_to(&mut self, pos: [f64; 2]) -> [f64; 2] {
+        let delta = [pos[0] - self.cursor_pos[0], pos[1] - self.cursor_pos[1]];
+        self.cursor_pos = pos;
+        delta
+    }
+
+    /// Starts following a drag from the cursor, when the left button goes down on the board.
+    pub fn start_drag(&mut self) {
+        self.drag_from = Some(self.cursor_pos);
+    }
+
+    /// Stops following a drag, when the left button goes up.
+    pub fn end_drag(&mut self) {
+        self.drag_from = None;
+        self.panning = false;
+    }
+
+    /// How far to move the board after the cursor moved by `delta`. A drag only starts once the
+    /// cursor is far enough from where the left button went down, and only if `can_pan` is set.
+    /// Starting one cancels whatever the press was going to do.
+    pub fn drag(&mut self, delta: [f64; 2], can_pan: bool) -> Option<[f64; 2]> {
+        let from = match self.drag_from {
+            Some(from) => from,
+            None => return None,
+        };
+        if self.panning {
+            return Some(delta);
+        }
+        let moved = [self.cursor_pos[0] - from[0], self.cursor_pos[1] - from[1]];
+        if can_pan && moved[0].hypot(moved[1]) > DRAG_DISTANCE {
+            self.panning = true;
+            self.left_held = false;
+            self.chording = false;
+            return Some(moved);
+        }
+        None
+    }
+
+    /// Forgets the held buttons without acting on them, for boards that stopped taking moves.
+    pub fn let_go(&mut self) {
+        self.left_held = false;
+        self.right_held = false;
+        self.chording = false;
+    }
+
+    /// Whether the held buttons push a hidden cell down. `hovered` tells whether the cursor is
+    /// on the cell, and `touching` whether the cell touches the one the cursor is on. It is only
+    /// asked while chording.
+    pub fn is_pressing<F: FnOnce() -> bool>(&self, hovered: bool, touching: F) -> bool {
+        if self.chording {
+            hovered || touching()
+        } else {
+            self.left_held && hovered
+        }
+    }
+
+    /// Follows the mouse buttons, and returns the move they make on `hovered`, the cell under the
+    /// cursor. Presses only push cells down, except right click which flags straight away.
+    /// Releasing acts on whatever cell the cursor ended up on.
+    pub fn click<E, P>(&mut self, e: &E, hovered: Option<P>) -> Option<Click<P>>
+        where E: GenericEvent,
+              P: Copy
+    {
+        match e.press_args() {
+            Some(Button::Mouse(MouseButton::Left)) => {
+                self.left_held = true;
+                self.chording = self.right_held;
+            }
+            Some(Button::Mouse(MouseButton::Right)) => {
+                self.right_held = true;
+                if self.left_held {
+                    self.chording = true;
+                } else {
+                    return hovered.map(Click::Flag);
+                }
+            }
+            Some(Button::Mouse(MouseButton::Middle)) => self.chording = true,
+            _ => {}
+        }
+
+        let button = match e.release_args() {
+            Some(Button::Mouse(button)) => button,
+            _ => return None,
+        };
+        let click = if self.chording {
+            self.let_go();
+            hovered.map(Click::Chord)
+        } else if button == MouseButton::Left && self.left_held {
+            hovered.map(Click::Reveal)
+        } else {
+            None
+        };
+        match button {
+            MouseButton::Left => self.left_held = false,
+            MouseButton::Right => self.right_held = false,
+            _ => {}
+        }
+        click
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use piston::input::MouseButton;
+
+    use testing::Script;
+    use super::*;
+
+    /// The moves a script makes with the cursor over `[1, 1]`.
+    fn clicks(script: Script, pointer: &mut Pointer) -> Vec<Click<[usize; 2]>> {
+        script.events.iter().filter_map(|e| pointer.click(e, Some([1, 1]))).collect()
+    }
+
+    #[test]
+    fn left_button_reveals_on_release() {
+        let mut pointer = Pointer::new();
+        assert_eq!(clicks(Script::new().press(MouseButton::Left), &mut pointer), vec![]);
+        assert!(pointer.is_pressing(true, || false));
+        assert_eq!(clicks(Script::new().release(MouseButton::Left), &mut pointer),
+                   vec![Click::Reveal([1, 1])]);
+        assert!(!pointer.is_pressing(true, || false));
+    }
+
+    #[test]
+    fn right_button_flags_on_press_and_both_chord() {
+        let mut pointer = Pointer::new();
+        assert_eq!(clicks(Script::new().press(MouseButton::Right), &mut pointer),
+                   vec![Click::Flag([1, 1])]);
+        let script = Script::new()
+            .press(MouseButton::Left)
+            .release(MouseButton::Right)
+            .release(MouseButton::Left);
+        assert_eq!(clicks(script, &mut pointer), vec![Click::Chord([1, 1])]);
+    }
+
+    #[test]
+    fn dragging_far_enough_cancels_the_press() {
+        let mut pointer = Pointer::new();
+        clicks(Script::new().press(MouseButton::Left), &mut pointer);
+        pointer.start_drag();
+        let delta = pointer.move_to([DRAG_DISTANCE / 2.0, 0.0]);
+        assert_eq!(pointer.drag(delta, true), None);
+        let delta = pointer.move_to([DRAG_DISTANCE * 2.0, 0.0]);
+        assert_eq!(pointer.drag(delta, false), None);
+        assert_eq!(pointer.drag(delta, true), Some([DRAG_DISTANCE * 2.0, 0.0]));
+        let delta = pointer.move_to([DRAG_DISTANCE * 3.0, 0.0]);
+        assert_eq!(pointer.drag(delta, true), Some([DRAG_DISTANCE, 0.0]));
+        assert_eq!(clicks(Script::new().release(MouseButton::Left), &mut pointer), vec![]);
+    }
+}
@@ -0,0 +1,272 @@
+//! Recorded games.
+//!
+//! A replay is a TOML file holding the shape of the cells, the board, as rows of `*` for bombs and
+//! `.` for empty cells, and every move made on it in order:
+//!
+//! ```toml
+//! cells = "square"
+//! board = ["..*", "...", "*.."]
+//! moves = ["reveal 0 0", "flag 2 0", "reveal 2 2"]
+//! ```
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use gameboard::Gameboard;
+use toml::{expect, quote, Document};
+use topology::{Offsets, Topology, MAX_LAYERS};
+
+/// Seconds between moves when a replay is played back.
+pub const MOVE_TIME: f64 = 0.4;
+
+/// Something the player did to a cell.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Move {
+    /// Opened a cell.
+    Reveal([usize; 2]),
+    /// Flagged a cell, or took a flag or question mark off.
+    Flag([usize; 2]),
+    /// Opened around a number.
+    Chord([usize; 2]),
+}
+
+impl Move {
+    /// Reads a move written as `reveal x y`, `flag x y` or `chord x y`.
+    pub fn parse(text: &str) -> Result<Move, String> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        if words.len() != 3 {
+            return Err(format!("`{}` should look like `reveal x y`", text));
+        }
+        let x = words[1].parse().map_err(|_| format!("`{}` is not a column", words[1]))?;
+        let y = words[2].parse().map_err(|_| format!("`{}` is not a row", words[2]))?;
+        match words[0] {
+            "reveal" => Ok(Move::Reveal([x, y])),
+            "flag" => Ok(Move::Flag([x, y])),
+            "chord" => Ok(Move::Chord([x, y])),
+            other => Err(format!("`{}` is not reveal, flag or chord", other)),
+        }
+    }
+
+    /// The cell the move was made on.
+    pub fn pos(&self) -> [usize; 2] {
+        match *self {
+            Move::Reveal(pos) | Move::Flag(pos) | Move::Chord(pos) => pos,
+        }
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Move::Reveal(_) => "reveal",
+            Move::Flag(_) => "flag",
+            Move::Chord(_) => "chord",
+        };
+        let [x, y] = self.pos();
+        write!(f, "{} {} {}", name, x, y)
+    }
+}
+
+/// A board and the moves made on it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    /// Shape of the cells.
+    pub topology: Topology,
+    /// Rows of `*` for bombs and `.` for empty cells.
+    pub board: Vec<String>,
+    /// Moves in the order they were made.
+    pub moves: Vec<Move>,
+}
+
+impl Replay {
+    /// Records a game. Boards whose bombs haven't been placed yet have nothing to record.
+    pub fn record(board: &Gameboard, moves: &[Move]) -> Option<Replay> {
+        if !board.mines_placed {
+            return None;
+        }
+        Some(Replay {
+            topology: board.topology,
+            board: board.mine_rows(),
+            moves: moves.to_vec(),
+        })
+    }
+
+    /// Reads a replay from TOML text.
+    pub fn parse(text: &str) -> Result<Replay, String> {
+        let doc = Document::parse(text)?;
+        // Replays from before boards had other shapes have square cells.
+        let neighbours = match doc.get("", "neighbours") {
+            Some(value) => {
+                let steps = expect(value.as_steps(), "", "neighbours", "a list of [x, y] steps")?;
+                Offsets::new(&steps).map_err(|e| format!("neighbours: {}", e))?
+            }
+            None => Offsets::default(),
+        };
+        let mut topology = match doc.get("", "cells") {
+            Some(value) => {
+                let kind = "a cell shape such as \"square\", \"hex\" or \"layers\"";
+                let name = expect(value.as_str(), "", "cells", kind)?;
+                expect(Topology::from_id(name, neighbours), "", "cells", kind)?
+            }
+            None => Topology::Square,
+        };
+        if let (Topology::Layers(ref mut depth), Some(value)) = (&mut topology,
+                                                                doc.get("", "layers")) {
+            let count = expect(value.as_f64(), "", "layers", "a whole number")?;
+            if count < 1.0 || count > MAX_LAYERS as f64 || count.fract() != 0.0 {
+                return Err(format!("layers should be a whole number from 1 to {}", MAX_LAYERS));
+            }
+            *depth = count as usize;
+        }
+        let rows = expect(doc.get("", "board").and_then(|value| value.as_array()),
+                          "",
+                          "board",
+                          "a list of rows")?;
+        let mut board = Vec::new();
+        for row in rows {
+            board.push(expect(row.as_str(), "", "board", "a list of rows")?.to_string());
+        }
+        let mut moves = Vec::new();
+        if let Some(value) = doc.get("", "moves") {
+            for text in expect(value.as_array(), "", "moves", "a list of moves")? {
+                let text = expect(text.as_str(), "", "moves", "a list of moves")?;
+                moves.push(Move::parse(text)?);
+            }
+        }
+        Ok(Replay {
+            topology: topology,
+            board: board,
+            moves: moves,
+        })
+    }
+
+    /// Loads a replay file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Replay, String> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+        Replay::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Writes the replay as TOML text.
+    pub fn to_toml(&self) -> String {
+        let mut text = format!("cells = {}\n", quote(self.topology.id()));
+        match self.topology {
+            Topology::Custom(ref offsets) => {
+                text.push_str(&format!("neighbours = {}\n", offsets.to_toml()))
+            }
+            Topology::Layers(depth) => text.push_str(&format!("layers = {}\n", depth)),
+            _ => {}
+        }
+        text.push_str("board = [\n");
+        for row in &self.board {
+            text.push_str(&format!("    {},\n", quote(row)));
+        }
+        text.push_str("]\nmoves = [\n");
+        for mv in &self.moves {
+            text.push_str(&format!("    {},\n", quote(&mv.to_string())));
+        }
+        text.push_str("]\n");
+        text
+    }
+
+    /// Writes the replay to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        fs::write(path, self.to_toml())
+            .map_err(|e| format!("could not write {}: {}", path.display(), e))
+    }
+
+    /// A fresh board to play the moves on.
+    pub fn gameboard(&self) -> Result<Gameboard, String> {
+        let mut board = Gameboard::from_rows(&self.board)?;
+        self.topology.check_rows(board.height)?;
+        board.set_topology(self.topology);
+        Ok(board)
+    }
+}
+
+/// Hands out the moves of a replay one at a time as time passes.
+pub struct Playback {
+    moves: Vec<Move>,
+    next: usize,
+    timer: f64,
+}
+
+impl Playback {
+    /// Starts playing moves back, the first one after `MOVE_TIME`.
+    pub fn new(moves: Vec<Move>) -> Playback {
+        Playback {
+            moves: moves,
+            next: 0,
+            timer: 0.0,
+        }
+    }
+
+    /// Moves time forward, returning the move that is due, if any.
+    pub fn update(&mut self, dt: f64) -> Option<Move> {
+        if self.is_done() {
+            return None;
+        }
+        self.timer += dt;
+        if self.timer < MOVE_TIME {
+            return None;
+        }
+        self.timer -= MOVE_TIME;
+        self.next += 1;
+        Some(self.moves[self.next - 1])
+    }
+
+    /// Whether every move has been handed out.
+    pub fn is_done(&self) -> bool {
+        self.next >= self.moves.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replays_survive_a_round_trip() {
+        let replays = [Replay {
+                           topology: Topology::Square,
+                           board: vec!["..*".to_string(), "...".to_string(), "*..".to_string()],
+                           moves: vec![Move::Reveal([0, 0]), Move::Flag([2, 0]),
+                                       Move::Chord([1, 1])],
+                       },
+                       Replay {
+                           topology: Topology::Custom(Offsets::new(&[[2, 0], [1, -2]]).unwrap()),
+                           board: vec!["*.".to_string(), "..".to_string()],
+                           moves: Vec::new(),
+                       },
+                       Replay {
+                           topology: Topology::Layers(2),
+                           board: vec!["*.".to_string(); 4],
+                           moves: vec![Move::Reveal([1, 3])],
+                       }];
+        for replay in replays.iter() {
+            assert_eq!(Replay::parse(&replay.to_toml()).as_ref(), Ok(replay));
+        }
+    }
+
+    #[test]
+    fn old_replays_have_square_cells() {
+        let replay = Replay::parse("board = [\"*.\", \"..\"]\nmoves = [\"reveal 1 1\"]").unwrap();
+        assert_eq!(replay.topology, Topology::Square);
+        assert_eq!(replay.moves, vec![Move::Reveal([1, 1])]);
+    }
+
+    #[test]
+    fn bad_moves_are_reported() {
+        assert_eq!(Move::parse("reveal 1").unwrap_err(),
+                   "`reveal 1` should look like `reveal x y`");
+        assert_eq!(Move::parse("dig 1 2").unwrap_err(), "`dig` is not reveal, flag or chord");
+        assert_eq!(Move::parse("flag a 2").unwrap_err(), "`a` is not a column");
+        assert_eq!(Replay::parse("board = [\"*.\"]\nmoves = [\"open 0 0\"]").unwrap_err(),
+                   "`open` is not reveal, flag or chord");
+        assert_eq!(Replay::parse("cells = \"layers\"\nlayers = 11\nboard = [\"*.\"]").unwrap_err(),
+                   "layers should be a whole number from 1 to 10");
+    }
+}
@@ -0,0 +1,470 @@
+//! Drawing on the CPU, for screenshots that don't need a GPU or a window.
+//!
+//! Shapes are antialiased by their distance to each pixel center. Text uses a small built-in
+//! pixel font instead of the theme font, so the same board always gives the same picture.
+
+use graphics::types::Color;
+use graphics::ImageSize;
+use image;
+
+use gameboard_view::GameboardView;
+use sprites::SpriteSheet;
+use traits::Canvas;
+
+/// Rows of each character in the pixel font, 5 pixels wide with the leftmost in bit 4.
+const FONT: [(char, [u8; 7]); 42] =
+    [('0', [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110]),
+     ('1', [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+     ('2', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111]),
+     ('3', [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110]),
+     ('4', [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010]),
+     ('5', [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110]),
+     ('6', [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110]),
+     ('7', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000]),
+     ('8', [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110]),
+     ('9', [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100]),
+     ('A', [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+     ('B', [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110]),
+     ('C', [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110]),
+     ('D', [0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100]),
+     ('E', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111]),
+     ('F', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000]),
+     ('G', [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111]),
+     ('H', [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+     ('I', [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+     ('J', [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100]),
+     ('K', [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001]),
+     ('L', [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111]),
+     ('M', [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001]),
+     ('N', [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001]),
+     ('O', [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+     ('P', [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000]),
+     ('Q', [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101]),
+     ('R', [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001]),
+     ('S', [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110]),
+     ('T', [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100]),
+     ('U', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+     ('V', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100]),
+     ('W', [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010]),
+     ('X', [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001]),
+     ('Y', [0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100]),
+     ('Z', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111]),
+     ('?', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100]),
+     ('!', [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100]),
+     ('%', [0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011]),
+     ('-', [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000]),
+     ('.', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100]),
+     (':', [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000])];
+
+/// Samples per pixel side when filling characters, which have hard edges between font pixels.
+const SUBSAMPLES: usize = 4;
+
+/// Rows of a character in the pixel font. Lower case letters look like upper case ones, and
+/// anything else is blank.
+fn glyph(ch: char) -> [u8; 7] {
+    let ch = ch.to_ascii_uppercase();
+    FONT.iter().find(|&&(c, _)| c == ch).map_or([0; 7], |&(_, rows)| rows)
+}
+
+/// Size of a font pixel for a font size.
+fn font_pixel(font_size: u32) -> f64 {
+    font_size as f64 / 10.0
+}
+
+/// An RGBA picture with 8 bits per channel, stored row by row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+    /// Red, green, blue and alpha of every pixel.
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    /// Creates a transparent image.
+    pub fn new(width: u32, height: u32) -> Image {
+        Image {
+            width: width,
+            height: height,
+            pixels: vec![0; width as usize * height as usize * 4],
+        }
+    }
+
+    /// Loads an image file.
+    pub fn load(path: &str) -> Result<Image, String> {
+        let rgba = image::open(path)
+            .map_err(|e| format!("could not load {}: {}", path, e))?
+            .to_rgba();
+        let (width, height) = rgba.dimensions();
+        Ok(Image {
+            width: width,
+            height: height,
+            pixels: rgba.into_raw(),
+        })
+    }
+
+    /// Saves the image as a PNG file.
+    pub fn save_png(&self, path: &str) -> Result<(), String> {
+        image::save_buffer(path, &self.pixels, self.width, self.height, image::RGBA(8))
+            .map_err(|e| format!("could not save {}: {}", path, e))
+    }
+
+    /// Color of a pixel.
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2], self.pixels[i + 3]]
+    }
+
+    /// Paints a color over a pixel, with `coverage` saying how much of the pixel it covers.
+    fn blend(&mut self, x: u32, y: u32, color: Color, coverage: f64) {
+        let alpha = color[3] as f64 * coverage;
+        if alpha <= 0.0 {
+            return;
+        }
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        for channel in 0..3 {
+            let old = self.pixels[i + channel] as f64 / 255.0;
+            let new = color[channel] as f64 * alpha + old * (1.0 - alpha);
+            self.pixels[i + channel] = (new * 255.0).round() as u8;
+        }
+        let old = self.pixels[i + 3] as f64 / 255.0;
+        self.pixels[i + 3] = ((alpha + old * (1.0 - alpha)) * 255.0).round() as u8;
+    }
+}
+
+impl ImageSize for Image {
+    fn get_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
+
+/// Distance from a point to the edge of a box around the origin with corners rounded by
+/// `radius`, negative inside.
+fn box_distance(p: [f64; 2], half_size: [f64; 2], radius: f64) -> f64 {
+    let radius = radius.min(half_size[0]).min(half_size[1]).max(0.0);
+    let qx = p[0].abs() - half_size[0] + radius;
+    let qy = p[1].abs() - half_size[1] + radius;
+    let outside = (qx.max(0.0).powi(2) + qy.max(0.0).powi(2)).sqrt();
+    outside + qx.max(qy).min(0.0) - radius
+}
+
+/// A canvas that draws into an `Image`.
+pub struct SoftwareCanvas {
+    /// The picture drawn so far.
+    pub image: Image,
+}
+
+impl SoftwareCanvas {
+    /// Creates a transparent canvas.
+    pub fn new(width: u32, height: u32) -> SoftwareCanvas {
+        SoftwareCanvas { image: Image::new(width, height) }
+    }
+
+    /// Paints every pixel in `bounds` by how far its center is inside the shape, given as a
+    /// distance to the edge that is negative inside.
+    fn fill<F>(&mut self, color: Color, bounds: [f64; 4], distance: F)
+        where F: Fn([f64; 2]) -> f64
+    {
+        self.fill_coverage(color, bounds, |p| (0.5 - distance(p)).max(0.0).min(1.0));
+    }
+
+    /// Paints every pixel in `bounds` by how much of it is covered.
+    fn fill_coverage<F>(&mut self, color: Color, bounds: [f64; 4], coverage: F)
+        where F: Fn([f64; 2]) -> f64
+    {
+        let x1 = (bounds[0].floor().max(0.0) as u32).min(self.image.width);
+        let y1 = (bounds[1].floor().max(0.0) as u32).min(self.image.height);
+        let x2 = ((bounds[0] + bounds[2]).ceil().max(0.0) as u32).min(self.image.width);
+        let y2 = ((bounds[1] + bounds[3]).ceil().max(0.0) as u32).min(self.image.height);
+        for y in y1..y2 {
+            for x in x1..x2 {
+                let amount = coverage([x as f64 + 0.5, y as f64 + 0.5]);
+                self.image.blend(x, y, color, amount);
+            }
+        }
+    }
+}
+
+/// Grows a rectangle by `amount` on every side.
+fn grow(rect: [f64; 4], amount: f64) -> [f64; 4] {
+    [rect[0] - amount, rect[1] - amount, rect[2] + amount * 2.0, rect[3] + amount * 2.0]
+}
+
+impl Canvas for SoftwareCanvas {
+    type Texture = Image;
+
+    fn size(&self) -> [f64; 2] {
+        [self.image.width as f64, self.image.height as f64]
+    }
+
+    fn clear(&mut self, color: Color) {
+        let rgba = [(color[0] * 255.0).round() as u8,
+                    (color[1] * 255.0).round() as u8,
+                    (color[2] * 255.0).round() as u8,
+                    (color[3] * 255.0).round() as u8];
+        for pixel in self.image.pixels.chunks_mut(4) {
+            pixel.copy_from_slice(&rgba);
+        }
+    }
+
+    fn rectangle(&mut self, color: Color, rect: [f64; 4], radius: f64) {
+        let center = [rect[0] + rect[2] / 2.0, rect[1] + rect[3] / 2.0];
+        let half_size = [rect[2] / 2.0, rect[3] / 2.0];
+        self.fill(color, grow(rect, 1.0), |p| {
+            box_distance([p[0] - center[0], p[1] - center[1]], half_size, radius)
+        });
+    }
+
+    fn border(&mut self, color: Color, rect: [f64; 4], radius: f64) {
+        let center = [rect[0] + rect[2] / 2.0, rect[1] + rect[3] / 2.0];
+        let half_size = [rect[2] / 2.0, rect[3] / 2.0];
+        self.fill(color, grow(rect, radius + 1.0), |p| {
+            box_distance([p[0] - center[0], p[1] - center[1]], half_size, 0.0).abs() - radius
+        });
+    }
+
+    fn rotated_rectangle(&mut self, color: Color, center: [f64; 2], size: [f64; 2], angle: f64) {
+        let (sin, cos) = angle.sin_cos();
+        let reach = (size[0] * size[0] + size[1] * size[1]).sqrt() / 2.0 + 1.0;
+        self.fill(color,
+                  [center[0] - reach, center[1] - reach, reach * 2.0, reach * 2.0],
+                  |p| {
+            let (dx, dy) = (p[0] - center[0], p[1] - center[1]);
+            let local = [dx * cos + dy * sin, dy * cos - dx * sin];
+            box_distance(local, [size[0] / 2.0, size[1] / 2.0], 0.0)
+        });
+    }
+
+    fn polygon(&mut self, color: Color, corners: &[[f64; 2]]) {
+        if corners.len() < 3 {
+            return;
+        }
+        let x1 = corners.iter().map(|p| p[0]).fold(f64::INFINITY, f64::min);
+        let y1 = corners.iter().map(|p| p[1]).fold(f64::INFINITY, f64::min);
+        let x2 = corners.iter().map(|p| p[0]).fold(f64::NEG_INFINITY, f64::max);
+        let y2 = corners.iter().map(|p| p[1]).fold(f64::NEG_INFINITY, f64::max);
+        // Corners can go either way around, so the edges are turned to face out.
+        let area: f64 = (0..corners.len())
+            .map(|i| {
+                let (a, b) = (corners[i], corners[(i + 1) % corners.len()]);
+                a[0] * b[1] - b[0] * a[1]
+            })
+            .sum();
+        let turn = if area < 0.0 { -1.0 } else { 1.0 };
+        self.fill(color, grow([x1, y1, x2 - x1, y2 - y1], 1.0), |p| {
+            // Inside a convex polygon the distance to the edge is the largest distance past any
+            // of its edge lines.
+            (0..corners.len())
+                .map(|i| {
+                    let (a, b) = (corners[i], corners[(i + 1) % corners.len()]);
+                    let length = (b[0] - a[0]).hypot(b[1] - a[1]).max(1e-9);
+                    turn * ((p[0] - a[0]) * (b[1] - a[1]) - (p[1] - a[1]) * (b[0] - a[0])) / length
+                })
+                .fold(f64::NEG_INFINITY, f64::max)
+        });
+    }
+
+    fn ellipse(&mut self, color: Color, rect: [f64; 4]) {
+        let radius = [rect[2] / 2.0, rect[3] / 2.0];
+        if radius[0] <= 0.0 || radius[1] <= 0.0 {
+            return;
+        }
+        let center = [rect[0] + radius[0], rect[1] + radius[1]];
+        self.fill(color, grow(rect, 1.0), |p| {
+            let dx = (p[0] - center[0]) / radius[0];
+            let dy = (p[1] - center[1]) / radius[1];
+            ((dx * dx + dy * dy).sqrt() - 1.0) * radius[0].min(radius[1])
+        });
+    }
+
+    fn line(&mut self, color: Color, line: [f64; 4], radius: f64) {
+        let [x1, y1, x2, y2] = line;
+        let length = ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt();
+        if length == 0.0 {
+            return;
+        }
+        let dir = [(x2 - x1) / length, (y2 - y1) / length];
+        let bounds = grow([x1.min(x2), y1.min(y2), (x2 - x1).abs(), (y2 - y1).abs()],
+                          radius + 1.0);
+        // Square ends, like piston's lines: inside when both along and across the line.
+        self.fill(color, bounds, |p| {
+            let (dx, dy) = (p[0] - x1, p[1] - y1);
+            let along = dx * dir[0] + dy * dir[1];
+            let across = (dy * dir[0] - dx * dir[1]).abs();
+            (across - radius).max(-along).max(along - length)
+        });
+    }
+
+    fn image(&mut self, texture: &Image, src: [f64; 4], rect: [f64; 4]) {
+        if rect[2] <= 0.0 || rect[3] <= 0.0 {
+            return;
+        }
+        let x1 = (rect[0].round().max(0.0) as u32).min(self.image.width);
+        let y1 = (rect[1].round().max(0.0) as u32).min(self.image.height);
+        let x2 = ((rect[0] + rect[2]).round().max(0.0) as u32).min(self.image.width);
+        let y2 = ((rect[1] + rect[3]).round().max(0.0) as u32).min(self.image.height);
+        for y in y1..y2 {
+            for x in x1..x2 {
+                // Nearest pixel, like the window's textures.
+                let u = src[0] + (x as f64 + 0.5 - rect[0]) / rect[2] * src[2];
+                let v = src[1] + (y as f64 + 0.5 - rect[1]) / rect[3] * src[3];
+                let tx = (u.floor().max(0.0) as u32).min(texture.width - 1);
+                let ty = (v.floor().max(0.0) as u32).min(texture.height - 1);
+                let [r, g, b, a] = texture.pixel(tx, ty);
+                let color =
+                    [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, a as f32 / 255.0];
+                self.image.blend(x, y, color, 1.0);
+            }
+        }
+    }
+
+    fn text_width(&mut self, font_size: u32, text: &str) -> f64 {
+        let count = text.chars().count() as f64;
+        (count * 6.0 - 1.0).max(0.0) * font_pixel(font_size)
+    }
+
+    fn text(&mut self, color: Color, font_size: u32, text: &str, pos: [f64; 2]) {
+        let pixel = font_pixel(font_size);
+        for (i, ch) in text.chars().enumerate() {
+            let top_left = [pos[0] + i as f64 * 6.0 * pixel, pos[1] - 7.0 * pixel];
+            self.character(color, font_size, ch, top_left, 1.0);
+        }
+    }
+
+    fn character_size(&mut self, font_size: u32, _ch: char) -> [f64; 2] {
+        let pixel = font_pixel(font_size);
+        [5.0 * pixel, 7.0 * pixel]
+    }
+
+    fn character(&mut self, color: Color, font_size: u32, ch: char, pos: [f64; 2], squeeze: f64) {
+        let rows = glyph(ch);
+        let pixel = [font_pixel(font_size) * squeeze, font_pixel(font_size)];
+        if pixel[0] <= 0.0 || pixel[1] <= 0.0 {
+            return;
+        }
+        let bounds = [pos[0], pos[1], 5.0 * pixel[0], 7.0 * pixel[1]];
+        self.fill_coverage(color, bounds, |p| {
+            let mut hits = 0;
+            for sy in 0..SUBSAMPLES {
+                for sx in 0..SUBSAMPLES {
+                    let offset = |s: usize| (s as f64 + 0.5) / SUBSAMPLES as f64 - 0.5;
+                    let col = ((p[0] + offset(sx) - pos[0]) / pixel[0]).floor();
+                    let row = ((p[1] + offset(sy) - pos[1]) / pixel[1]).floor();
+                    if col >= 0.0 && col < 5.0 && row >= 0.0 && row < 7.0 &&
+                       rows[row as usize] & (0b10000 >> col as usize) != 0 {
+                        hits += 1;
+                    }
+                }
+            }
+            hits as f64 / (SUBSAMPLES * SUBSAMPLES) as f64
+        });
+    }
+}
+
+/// Draws the board as it is now into an image of the given size. The view's own tiles live on
+/// the GPU, so `sprites` gives the atlas to use instead; cells are drawn as shapes without one.
+pub fn render_board<T>(view: &GameboardView<T>,
+                       sprites: Option<&SpriteSheet<Image>>,
+                       size: [u32; 2],
+                       background: Color)
+                       -> Image {
+    let mut canvas = SoftwareCanvas::new(size[0], size[1]);
+    canvas.clear(background);
+    view.draw_with(sprites, &mut canvas);
+    canvas.image
+}
+
+#[cfg(test)]
+mod tests {
+    use gameboard::{FirstClick, Gameboard, BEGINNER};
+    use gameboard_controller::GameboardController;
+    use gameboard_view::{GameboardView, GameboardViewSettings};
+    use replay::Move;
+    use sprites::SpriteSheet;
+    use testing::check_golden;
+    use theme::Theme;
+    use topology::Topology;
+    use super::*;
+
+    fn view(controller: GameboardController) -> GameboardView<Image> {
+        GameboardView::new(GameboardViewSettings::from_theme(&Theme::new()), controller)
+    }
+
+    /// A small board played into a loss, with every kind of tile on it.
+    fn lost_board(topology: Topology) -> GameboardView<Image> {
+        let mut board = Gameboard::from_rows(&["...*", "....", "*.*.", "...."]).unwrap();
+        board.set_topology(topology);
+        let mut controller = GameboardController::new(board);
+        controller.question_marks = true;
+        for &mv in &[Move::Reveal([0, 0]),
+                     Move::Flag([3, 0]),
+                     Move::Flag([3, 3]),
+                     Move::Flag([2, 3]),
+                     Move::Flag([2, 3]),
+                     Move::Reveal([0, 2])] {
+            controller.apply(mv);
+        }
+        controller.selected_cell = Some([1, 1]);
+        view(controller)
+    }
+
+    #[test]
+    fn new_board_matches_golden() {
+        let board = Gameboard::new(BEGINNER, FirstClick::Safe, 1);
+        let image = render_board(&view(GameboardController::new(board)),
+                                 None,
+                                 [90, 90],
+                                 [1.0; 4]);
+        check_golden("new_board", &image);
+    }
+
+    #[test]
+    fn lost_board_matches_golden() {
+        let image = render_board(&lost_board(Topology::Square), None, [120, 120], [1.0; 4]);
+        check_golden("lost_board", &image);
+    }
+
+    #[test]
+    fn sprite_board_matches_golden() {
+        let tiles = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/tiles.png");
+        let sprites = SpriteSheet::new(Image::load(tiles).unwrap()).unwrap();
+        let image =
+            render_board(&lost_board(Topology::Square), Some(&sprites), [120, 120], [1.0; 4]);
+        check_golden("sprite_board", &image);
+    }
+
+    #[test]
+    fn hex_board_matches_golden() {
+        let image = render_board(&lost_board(Topology::Hex), None, [120, 120], [1.0; 4]);
+        check_golden("hex_board", &image);
+    }
+
+    #[test]
+    fn torus_board_matches_golden() {
+        let image = render_board(&lost_board(Topology::Torus), None, [120, 120], [1.0; 4]);
+        check_golden("torus_board", &image);
+    }
+
+    #[test]
+    fn triangle_board_matches_golden() {
+        let image = render_board(&lost_board(Topology::Triangle), None, [120, 120], [1.0; 4]);
+        check_golden("triangle_board", &image);
+    }
+
+    #[test]
+    fn layered_board_matches_golden() {
+        let image = render_board(&lost_board(Topology::Layers(2)), None, [160, 80], [1.0; 4]);
+        check_golden("layered_board", &image);
+    }
+
+    #[test]
+    fn half_covered_pixels_blend() {
+        let mut canvas = SoftwareCanvas::new(2, 1);
+        canvas.clear([0.0, 0.0, 0.0, 1.0]);
+        canvas.rectangle([1.0; 4], [0.0, 0.0, 1.5, 1.0], 0.0);
+        assert_eq!(canvas.image.pixel(0, 0), [255, 255, 255, 255]);
+        assert_eq!(canvas.image.pixel(1, 0), [128, 128, 128, 255]);
+    }
+}
@@ -0,0 +1,296 @@
+//! A solver that plays without guessing, for dealing no-guess boards.
+//!
+//! It only uses what a careful player can see: a number whose bombs are all found frees its other
+//! neighbours, a number with as many hidden neighbours as missing bombs has bombs on all of them,
+//! and when one number's hidden neighbours are a subset of another's, the difference between the
+//! two has to hold the difference in missing bombs.
+
+use std::collections::{HashSet, VecDeque};
+
+use gameboard::adjacent_counts;
+use topology::Topology;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Knowledge {
+    Unknown,
+    Opened,
+    Mine,
+}
+
+/// Whether every empty cell can be opened starting from `start` without ever guessing.
+pub fn is_solvable(topology: Topology,
+                   cells: (usize, usize),
+                   mines: &[[usize; 2]],
+                   start: [usize; 2])
+                   -> bool {
+    solve(topology, cells, mines, start, true)
+}
+
+/// Plays from `start`, comparing pairs of numbers only if `subsets` is set.
+fn solve(topology: Topology,
+         cells: (usize, usize),
+         mines: &[[usize; 2]],
+         start: [usize; 2],
+         subsets: bool)
+         -> bool {
+    if mines.contains(&start) {
+        return false;
+    }
+    let (width, height) = cells;
+    let mut solver = Solver {
+        topology: topology,
+        width: width,
+        height: height,
+        counts: adjacent_counts(topology, cells, mines),
+        known: vec![vec![Knowledge::Unknown; width]; height],
+        opened: 0,
+        queue: VecDeque::new(),
+        queued: vec![vec![false; width]; height],
+        changed: Vec::new(),
+        to_compare: vec![vec![false; width]; height],
+    };
+    solver.open(start);
+
+    loop {
+        while let Some([x, y]) = solver.queue.pop_front() {
+            solver.queued[y][x] = false;
+            solver.apply_single_rules([x, y]);
+        }
+        if !subsets || !solver.apply_subset_rule() {
+            break;
+        }
+    }
+
+    solver.opened == width * height - mines.len()
+}
+
+/// Works through the numbers that changed since they were last looked at, instead of going over
+/// the whole board every time something is learned.
+struct Solver {
+    topology: Topology,
+    width: usize,
+    height: usize,
+    /// Bombs around each cell.
+    counts: Vec<Vec<u8>>,
+    known: Vec<Vec<Knowledge>>,
+    /// Number of cells opened so far.
+    opened: usize,
+    /// Opened cells whose neighbours changed since the single rules last looked at them.
+    queue: VecDeque<[usize; 2]>,
+    /// Which cells are in `queue`.
+    queued: Vec<Vec<bool>>,
+    /// Opened cells the single rules couldn't settle, waiting to be compared with the numbers
+    /// they share unknown cells with.
+    changed: Vec<[usize; 2]>,
+    /// Which cells are in `changed`.
+    to_compare: Vec<Vec<bool>>,
+}
+
+impl Solver {
+    fn neighbours(&self, pos: [usize; 2]) -> Vec<[usize; 2]> {
+        self.topology.neighbours(pos, (self.width, self.height))
+    }
+
+    fn count(&self, pos: [usize; 2]) -> usize {
+        self.counts[pos[1]][pos[0]] as usize
+    }
+
+    /// Has the single rules look at a cell again if it is opened.
+    fn queue(&mut self, [x, y]: [usize; 2]) {
+        if self.known[y][x] == Knowledge::Opened && !self.queued[y][x] {
+            self.queued[y][x] = true;
+            self.queue.push_back([x, y]);
+        }
+    }
+
+    /// Has the subset rule compare a cell with its neighbours' numbers again.
+    fn compare_later(&mut self, [x, y]: [usize; 2]) {
+        if !self.to_compare[y][x] {
+            self.to_compare[y][x] = true;
+            self.changed.push([x, y]);
+        }
+    }
+
+    /// Opens a cell the way the game does, spreading out over cells with no bombs around them.
+    fn open(&mut self, start: [usize; 2]) {
+        let mut stack = vec![start];
+        while let Some([x, y]) = stack.pop() {
+            if self.known[y][x] != Knowledge::Unknown {
+                continue;
+            }
+            self.known[y][x] = Knowledge::Opened;
+            self.opened += 1;
+            self.queue([x, y]);
+            let spreads = self.count([x, y]) == 0;
+            for [nx, ny] in self.neighbours([x, y]) {
+                match self.known[ny][nx] {
+                    Knowledge::Opened => self.queue([nx, ny]),
+                    Knowledge::Unknown if spreads => stack.push([nx, ny]),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Marks a cell as a bomb.
+    fn mark_mine(&mut self, [x, y]: [usize; 2]) {
+        self.known[y][x] = Knowledge::Mine;
+        for neighbour in self.neighbours([x, y]) {
+            self.queue(neighbour);
+        }
+    }
+
+    /// Bombs still missing around an opened cell, and its unknown neighbours.
+    fn constraint(&self, pos: [usize; 2]) -> (usize, Vec<[usize; 2]>) {
+        let mut found = 0;
+        let mut unknown = Vec::new();
+        for [x, y] in self.neighbours(pos) {
+            match self.known[y][x] {
+                Knowledge::Mine => found += 1,
+                Knowledge::Unknown => unknown.push([x, y]),
+                Knowledge::Opened => {}
+            }
+        }
+        (self.count(pos) - found, unknown)
+    }
+
+    /// Marks what one number gives away, or saves it for the subset rule if it gives away
+    /// nothing.
+    fn apply_single_rules(&mut self, pos: [usize; 2]) {
+        let (missing, unknown) = self.constraint(pos);
+        if unknown.is_empty() {
+            return;
+        }
+        if missing == 0 {
+            for cell in unknown {
+                self.open(cell);
+            }
+        } else if missing == unknown.len() {
+            for cell in unknown {
+                self.mark_mine(cell);
+            }
+        } else {
+            self.compare_later(pos);
+        }
+    }
+
+    /// Compares numbers that changed with the numbers they share unknown cells with, until one
+    /// pair gives something away. Returns whether anything was learned.
+    fn apply_subset_rule(&mut self) -> bool {
+        while let Some([x, y]) = self.changed.pop() {
+            self.to_compare[y][x] = false;
+            let (missing_a, a) = self.constraint([x, y]);
+            // Numbers without an unknown cell in common say nothing about each other, however
+            // the board links them, so only the numbers around these unknown cells are compared.
+            let mut partners = HashSet::new();
+            for &cell in &a {
+                for other in self.neighbours(cell) {
+                    if other != [x, y] && self.known[other[1]][other[0]] == Knowledge::Opened {
+                        partners.insert(other);
+                    }
+                }
+            }
+            for other in partners {
+                let (missing_b, b) = self.constraint(other);
+                if self.apply_subset(missing_a, &a, missing_b, &b) ||
+                   self.apply_subset(missing_b, &b, missing_a, &a) {
+                    // The other pairs of this number haven't been looked at yet.
+                    self.compare_later([x, y]);
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// When the unknown cells `a` are a subset of `b`, settles the cells only in `b` if the
+    /// missing bombs allow it. Returns whether anything was learned.
+    fn apply_subset(&mut self,
+                    missing_a: usize,
+                    a: &[[usize; 2]],
+                    missing_b: usize,
+                    b: &[[usize; 2]])
+                    -> bool {
+        if a.len() >= b.len() || !a.iter().all(|cell| b.contains(cell)) {
+            return false;
+        }
+        let rest: Vec<[usize; 2]> = b.iter().cloned().filter(|cell| !a.contains(cell)).collect();
+        if missing_b == missing_a {
+            for cell in rest {
+                self.open(cell);
+            }
+            true
+        } else if missing_b >= missing_a && missing_b - missing_a == rest.len() {
+            for cell in rest {
+                self.mark_mine(cell);
+            }
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Bombs in rows of `*` and `.`, with the size of the board.
+    fn mines(rows: &[&str]) -> (Vec<[usize; 2]>, (usize, usize)) {
+        let mut mines = Vec::new();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, ch) in row.chars().enumerate() {
+                if ch == '*' {
+                    mines.push([x, y]);
+                }
+            }
+        }
+        (mines, (rows[0].len(), rows.len()))
+    }
+
+    #[test]
+    fn single_numbers_are_enough_for_some_boards() {
+        // The 1 right of the middle bomb is its only hidden neighbour, which then frees the cell
+        // between the two bombs and gives away the other one.
+        let (mines, cells) = mines(&["*.*..",
+                                     ".....",
+                                     "....."]);
+        assert!(solve(Topology::Square, cells, &mines, [4, 2], false));
+        assert!(is_solvable(Topology::Square, cells, &mines, [4, 2]));
+    }
+
+    #[test]
+    fn two_cells_behind_one_number_need_a_guess() {
+        // Both corner cells touch the same numbers, so nothing tells them apart.
+        let (mines, cells) = mines(&["*.....",
+                                     "......"]);
+        assert!(!is_solvable(Topology::Square, cells, &mines, [5, 1]));
+    }
+
+    #[test]
+    fn some_boards_need_pairs_of_numbers() {
+        let (mines, cells) = mines(&["..*..",
+                                     ".....",
+                                     "*....",
+                                     "....*"]);
+        assert!(!solve(Topology::Square, cells, &mines, [4, 1], false));
+        assert!(is_solvable(Topology::Square, cells, &mines, [4, 1]));
+    }
+
+    #[test]
+    fn numbers_far_apart_can_share_cells() {
+        // Knight's move numbers up to four cells apart count some of the same cells.
+        let (mines, cells) = mines(&["...**.",
+                                     "...*..",
+                                     "...*..",
+                                     "......",
+                                     "....*."]);
+        assert!(is_solvable(Topology::Knight, cells, &mines, [4, 1]));
+    }
+
+    #[test]
+    fn starting_on_a_bomb_is_never_solvable() {
+        let (mines, cells) = mines(&["*....", ".....", "....."]);
+        assert!(!is_solvable(Topology::Square, cells, &mines, [0, 0]));
+    }
+}
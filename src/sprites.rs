@@ -0,0 +1,98 @@
+//! Tile atlas.
+
+use graphics::ImageSize;
+
+use gameboard::CellState;
+
+/// Number of tiles an atlas has to hold.
+pub const TILE_COUNT: usize = 15;
+
+/// Pictures in the atlas, in the order they appear from left to right.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Tile {
+    /// A cell that hasn't been opened.
+    Hidden,
+    /// An opened cell with no bombs around it.
+    Revealed,
+    /// A flagged cell.
+    Flag,
+    /// A bomb shown after the game is over.
+    Mine,
+    /// The bomb that ended the game.
+    ExplodedMine,
+    /// A flag that was placed on an empty cell.
+    WrongFlag,
+    /// An opened cell with bombs around it. Only 1 to 8 are in the atlas.
+    Number(u8),
+    /// A hidden cell marked with a question mark.
+    Question,
+}
+
+impl Tile {
+    /// Tile for a cell. Once the game is lost, hidden bombs and wrong flags are shown too.
+    pub fn for_cell(cell: CellState, lost: bool) -> Tile {
+        use gameboard::CellState::*;
+        match cell {
+            HiddenBomb | QuestionBomb if lost => Tile::Mine,
+            FlaggedBlank if lost => Tile::WrongFlag,
+            HiddenBlank | HiddenBomb => Tile::Hidden,
+            EmptyBlank => Tile::Revealed,
+            EmptyNumber(num) => Tile::Number(num),
+            Bomb => Tile::ExplodedMine,
+            FlaggedBomb | FlaggedBlank => Tile::Flag,
+            QuestionBomb | QuestionBlank => Tile::Question,
+        }
+    }
+
+    /// Whether the atlas has a picture of the tile. Numbers above 8 have to be drawn.
+    pub fn in_atlas(&self) -> bool {
+        match *self {
+            Tile::Number(num) => num <= 8,
+            _ => true,
+        }
+    }
+
+    /// Column of the tile in the atlas.
+    pub fn index(&self) -> usize {
+        match *self {
+            Tile::Hidden => 0,
+            Tile::Revealed => 1,
+            Tile::Flag => 2,
+            Tile::Mine => 3,
+            Tile::ExplodedMine => 4,
+            Tile::WrongFlag => 5,
+            Tile::Number(num) => 5 + num.max(1).min(8) as usize,
+            Tile::Question => 14,
+        }
+    }
+}
+
+/// A texture holding every tile side by side in a single row of squares.
+pub struct SpriteSheet<T> {
+    /// The atlas texture.
+    pub texture: T,
+    /// Size of one tile in texture pixels.
+    tile_size: f64,
+}
+
+impl<T: ImageSize> SpriteSheet<T> {
+    /// Wraps a texture, checking that it is wide enough for every tile.
+    pub fn new(texture: T) -> Result<SpriteSheet<T>, String> {
+        let (width, height) = texture.get_size();
+        if height == 0 || (width / height) < TILE_COUNT as u32 {
+            return Err(format!("tile atlas is {}x{}, needs {} square tiles in a row",
+                               width,
+                               height,
+                               TILE_COUNT));
+        }
+        Ok(SpriteSheet {
+            texture: texture,
+            tile_size: height as f64,
+        })
+    }
+
+    /// Where a tile sits in the texture.
+    pub fn src_rect(&self, tile: Tile) -> [f64; 4] {
+        [tile.index() as f64 * self.tile_size, 0.0, self.tile_size, self.tile_size]
+    }
+}
@@ -1,6 +1,7 @@
-#[derive(Debug, Copy, Clone)]
-pub enum State {
-    MainMenu,
-    GameBoard,
-    Exiting,
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum State {
+    MainMenu,
+    GameBoard,
+    Endless,
+    Exiting,
 }
\ No newline at end of file
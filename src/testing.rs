@@ -0,0 +1,102 @@
+//! Helpers for testing the screens without a window.
+//!
+//! Tests script the input a window would send, feed it through `EventHandler` and compare what the
+//! screens draw on a `SoftwareCanvas` with pictures kept in `tests/golden`.
+
+use std::env;
+
+use graphics::types::Color;
+use piston::input::{Button, ButtonArgs, ButtonState, Event, Input, Key, Motion, MouseButton};
+
+use software::{Image, SoftwareCanvas};
+use state::State;
+use traits::{Canvas, EventHandler, Renderer};
+
+/// Where the expected pictures are kept.
+const GOLDEN_DIR: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+
+/// A list of input events, built up one action at a time.
+pub struct Script {
+    pub events: Vec<Event>,
+}
+
+impl Script {
+    /// Creates an empty script.
+    pub fn new() -> Script {
+        Script { events: Vec::new() }
+    }
+
+    /// Moves the mouse cursor to a window coordinate.
+    pub fn move_to(mut self, x: f64, y: f64) -> Script {
+        self.events.push(Event::from(Input::Move(Motion::MouseCursor(x, y))));
+        self
+    }
+
+    /// Presses a mouse button without letting go.
+    pub fn press(mut self, button: MouseButton) -> Script {
+        self.events.push(button_event(ButtonState::Press, Button::Mouse(button)));
+        self
+    }
+
+    /// Lets go of a mouse button.
+    pub fn release(mut self, button: MouseButton) -> Script {
+        self.events.push(button_event(ButtonState::Release, Button::Mouse(button)));
+        self
+    }
+
+    /// Turns the scroll wheel, up for positive steps.
+    pub fn scroll(mut self, steps: f64) -> Script {
+        self.events.push(Event::from(Input::Move(Motion::MouseScroll(0.0, steps))));
+        self
+    }
+
+    /// Moves to a window coordinate and clicks there.
+    pub fn click_at(self, x: f64, y: f64, button: MouseButton) -> Script {
+        self.move_to(x, y).press(button).release(button)
+    }
+
+    /// Presses and lets go of a key.
+    pub fn key(mut self, key: Key) -> Script {
+        self.events.push(button_event(ButtonState::Press, Button::Keyboard(key)));
+        self.events.push(button_event(ButtonState::Release, Button::Keyboard(key)));
+        self
+    }
+
+    /// Feeds every event to a screen in a window of the given size, returning the state it asked
+    /// for after each one.
+    pub fn run<H: EventHandler>(&self, handler: &mut H, size: (f64, f64)) -> Vec<State> {
+        self.events.iter().map(|e| handler.event(size, e)).collect()
+    }
+}
+
+/// A button being pressed or let go of.
+fn button_event(state: ButtonState, button: Button) -> Event {
+    Event::from(Input::Button(ButtonArgs {
+        state: state,
+        button: button,
+        scancode: None,
+    }))
+}
+
+/// Draws a screen on a cleared canvas of the given size.
+pub fn render<R: Renderer<Image>>(renderer: &R, size: [u32; 2], background: Color) -> Image {
+    let mut canvas = SoftwareCanvas::new(size[0], size[1]);
+    canvas.clear(background);
+    renderer.draw(&mut canvas);
+    canvas.image
+}
+
+/// Compares a picture with the expected one. Run with `UPDATE_GOLDEN=1` to save new ones.
+pub fn check_golden(name: &str, image: &Image) {
+    let path = format!("{}/{}.png", GOLDEN_DIR, name);
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        image.save_png(&path).unwrap();
+        return;
+    }
+    let expected = Image::load(&path).unwrap();
+    if *image != expected {
+        let actual = format!("{}/{}.actual.png", GOLDEN_DIR, name);
+        image.save_png(&actual).unwrap();
+        panic!("{} doesn't match {}, see {}", name, path, actual);
+    }
+}
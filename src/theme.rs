@@ -0,0 +1,300 @@
+//! Themes.
+//!
+//! A theme holds every color, size and font the game draws with. Themes live as TOML files in
+//! `assets/themes`; keys left out of a file fall back to the built-in teal look.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use graphics::types::Color;
+
+use toml::{expect, Document};
+
+/// Directory the bundled themes are read from.
+pub const THEME_DIR: &'static str = "assets/themes";
+/// File stem of the theme used until another one is picked.
+pub const DEFAULT_THEME: &'static str = "teal";
+
+/// Stores the look of the game.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    /// Name shown in the menu.
+    pub name: String,
+    /// Path to the font file.
+    pub font: String,
+    /// Path to a tile atlas image. Cells are drawn as shapes when this is `None`.
+    pub tiles: Option<String>,
+    /// Window background, also used behind the menu.
+    pub background_color: Color,
+    /// Background of the board itself.
+    pub board_background_color: Color,
+    /// Edge color between cells.
+    pub cell_edge_color: Color,
+    /// Edge radius around the cells.
+    pub cell_edge_radius: f64,
+    /// Hidden cell, and the shadow under its raised top.
+    pub hidden_color: Color,
+    /// Raised top of a hidden cell.
+    pub hidden_top_color: Color,
+    /// Raised top of a hidden cell under the mouse cursor.
+    pub hover_color: Color,
+    /// Revealed cell.
+    pub revealed_color: Color,
+    /// Exploded bomb.
+    pub bomb_color: Color,
+    /// Flagged cell.
+    pub flag_color: Color,
+    /// Flag found on an empty cell after losing.
+    pub wrong_flag_color: Color,
+    /// Mark drawn on mines and wrong flags after losing.
+    pub mine_color: Color,
+    /// Color of each number, starting at 1.
+    pub number_colors: [Color; 8],
+    /// Outline color of the keyboard cursor.
+    pub selected_cell_border_color: Color,
+    /// Outline radius of the keyboard cursor.
+    pub selected_cell_border_radius: f64,
+    /// Cell corner rounding, relative to the cell size.
+    pub cell_corner_rounding: f64,
+    /// Padding within each cell, relative to the cell size.
+    pub cell_padding: f64,
+    /// How far hidden cells stick out, relative to the cell size.
+    pub cell_raise: f64,
+    /// Font size of the numbers, relative to the cell size.
+    pub font_scale: f64,
+    /// Menu text color.
+    pub menu_text_color: Color,
+    /// Menu button color.
+    pub menu_button_color: Color,
+    /// Menu button corner rounding.
+    pub menu_button_rounding: f64,
+    /// Menu button font size.
+    pub font_size_button: u32,
+    /// Menu title font size.
+    pub font_size_title: u32,
+}
+
+impl Theme {
+    /// Creates the built-in teal theme.
+    pub fn new() -> Theme {
+        Theme {
+            name: "Teal".to_string(),
+            font: "assets/Roboto-Bold.ttf".to_string(),
+            tiles: None,
+            background_color: [0.82, 0.9, 0.87, 1.0],
+            board_background_color: [0.82, 0.9, 0.87, 1.0],
+            cell_edge_color: [0.69, 0.76, 0.73, 1.0],
+            cell_edge_radius: 1.0,
+            hidden_color: [0.01, 0.52, 0.59, 1.0],
+            hidden_top_color: [0.01, 0.71, 0.81, 1.0],
+            hover_color: [0.25, 0.8, 0.89, 1.0],
+            revealed_color: [0.01, 0.52, 0.59, 1.0],
+            bomb_color: [1.0, 0.0, 0.247, 1.0],
+            flag_color: [0.1, 1.0, 0.1, 1.0],
+            wrong_flag_color: [1.0, 0.6, 0.1, 1.0],
+            mine_color: [0.1, 0.1, 0.1, 1.0],
+            number_colors: [[0.0, 0.0, 1.0, 1.0],
+                            [0.0, 0.5, 0.0, 1.0],
+                            [1.0, 0.0, 0.0, 1.0],
+                            [0.0, 0.0, 0.5, 1.0],
+                            [0.5, 0.0, 0.0, 1.0],
+                            [0.0, 0.5, 0.5, 1.0],
+                            [0.0, 0.0, 0.0, 1.0],
+                            [0.5, 0.5, 0.5, 1.0]],
+            selected_cell_border_color: [1.0, 0.85, 0.2, 1.0],
+            selected_cell_border_radius: 2.0,
+            cell_corner_rounding: 0.17,
+            cell_padding: 0.12,
+            cell_raise: 0.08,
+            font_scale: 0.57,
+            menu_text_color: [0.0, 0.0, 0.0, 1.0],
+            menu_button_color: [0.01, 0.52, 0.59, 1.0],
+            menu_button_rounding: 5.0,
+            font_size_button: 32,
+            font_size_title: 38,
+        }
+    }
+
+    /// Reads a theme from TOML text.
+    pub fn parse(text: &str) -> Result<Theme, String> {
+        let doc = Document::parse(text)?;
+        let mut theme = Theme::new();
+
+        if let Some(value) = doc.get("", "name") {
+            theme.name = expect(value.as_str(), "", "name", "a string")?.to_string();
+        }
+        if let Some(value) = doc.get("", "font") {
+            theme.font = expect(value.as_str(), "", "font", "a string")?.to_string();
+        }
+        if let Some(value) = doc.get("", "tiles") {
+            theme.tiles = Some(expect(value.as_str(), "", "tiles", "a string")?.to_string());
+        }
+        read_color(&doc, "", "background", &mut theme.background_color)?;
+
+        read_color(&doc, "board", "background", &mut theme.board_background_color)?;
+        read_color(&doc, "board", "cell_edge", &mut theme.cell_edge_color)?;
+        read_f64(&doc, "board", "cell_edge_radius", &mut theme.cell_edge_radius)?;
+        read_color(&doc, "board", "hidden", &mut theme.hidden_color)?;
+        read_color(&doc, "board", "hidden_top", &mut theme.hidden_top_color)?;
+        read_color(&doc, "board", "hover", &mut theme.hover_color)?;
+        read_color(&doc, "board", "revealed", &mut theme.revealed_color)?;
+        read_color(&doc, "board", "bomb", &mut theme.bomb_color)?;
+        read_color(&doc, "board", "flag", &mut theme.flag_color)?;
+        read_color(&doc, "board", "wrong_flag", &mut theme.wrong_flag_color)?;
+        read_color(&doc, "board", "mine", &mut theme.mine_color)?;
+        read_color(&doc, "board", "cursor", &mut theme.selected_cell_border_color)?;
+        read_f64(&doc,
+                 "board",
+                 "cursor_radius",
+                 &mut theme.selected_cell_border_radius)?;
+        read_f64(&doc, "board", "corner_rounding", &mut theme.cell_corner_rounding)?;
+        read_f64(&doc, "board", "padding", &mut theme.cell_padding)?;
+        read_f64(&doc, "board", "raise", &mut theme.cell_raise)?;
+        read_f64(&doc, "board", "font_scale", &mut theme.font_scale)?;
+        if let Some(value) = doc.get("board", "numbers") {
+            let colors = expect(value.as_array(), "board", "numbers", "a list of colors")?;
+            if colors.len() != 8 {
+                return Err("board.numbers needs exactly 8 colors".to_string());
+            }
+            for (i, color) in colors.iter().enumerate() {
+                theme.number_colors[i] = expect(color.as_color(), "board", "numbers", "colors")?;
+            }
+        }
+
+        read_color(&doc, "menu", "text", &mut theme.menu_text_color)?;
+        read_color(&doc, "menu", "button", &mut theme.menu_button_color)?;
+        read_f64(&doc, "menu", "button_rounding", &mut theme.menu_button_rounding)?;
+        let mut size = theme.font_size_button as f64;
+        read_f64(&doc, "menu", "font_size_button", &mut size)?;
+        theme.font_size_button = size as u32;
+        let mut size = theme.font_size_title as f64;
+        read_f64(&doc, "menu", "font_size_title", &mut size)?;
+        theme.font_size_title = size as u32;
+
+        Ok(theme)
+    }
+
+    /// Loads a theme file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Theme, String> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+        Theme::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+fn read_color(doc: &Document, section: &str, key: &str, target: &mut Color) -> Result<(), String> {
+    if let Some(value) = doc.get(section, key) {
+        *target = expect(value.as_color(), section, key, "a color")?;
+    }
+    Ok(())
+}
+
+fn read_f64(doc: &Document, section: &str, key: &str, target: &mut f64) -> Result<(), String> {
+    if let Some(value) = doc.get(section, key) {
+        *target = expect(value.as_f64(), section, key, "a number")?;
+    }
+    Ok(())
+}
+
+/// The theme files to choose between, and which one is in use.
+pub struct ThemeSet {
+    /// Theme files, sorted by file name.
+    paths: Vec<PathBuf>,
+    /// Index of the theme in use.
+    current: usize,
+    /// When the theme in use was last changed on disk.
+    modified: Option<SystemTime>,
+}
+
+impl ThemeSet {
+    /// Finds every `.toml` file in a directory.
+    pub fn find<P: AsRef<Path>>(dir: P) -> ThemeSet {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+            .map(|entries| {
+                entries.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|path| path.extension().map_or(false, |ext| ext == "toml"))
+                    .collect()
+            })
+            .unwrap_or_default();
+        paths.sort();
+        ThemeSet {
+            paths: paths,
+            current: 0,
+            modified: None,
+        }
+    }
+
+    /// Selects the theme whose file stem matches `name`, if there is one.
+    pub fn select(&mut self, name: &str) -> bool {
+        match self.paths
+            .iter()
+            .position(|path| path.file_stem().map_or(false, |stem| stem == name)) {
+            Some(index) => {
+                self.current = index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// File stem of the theme in use.
+    pub fn current_name(&self) -> Option<String> {
+        self.paths
+            .get(self.current)
+            .and_then(|path| path.file_stem())
+            .map(|stem| stem.to_string_lossy().into_owned())
+    }
+
+    /// Loads the theme in use, falling back to the built-in one if it can't be read.
+    pub fn load(&mut self) -> Theme {
+        let path = match self.paths.get(self.current) {
+            Some(path) => path.clone(),
+            None => return Theme::new(),
+        };
+        self.modified = modified_time(&path);
+        match Theme::load(&path) {
+            Ok(theme) => theme,
+            Err(e) => {
+                eprintln!("{}", e);
+                Theme::new()
+            }
+        }
+    }
+
+    /// Switches to the next theme and loads it.
+    pub fn next(&mut self) -> Theme {
+        if !self.paths.is_empty() {
+            self.current = (self.current + 1) % self.paths.len();
+        }
+        self.load()
+    }
+
+    /// Reloads the theme in use if its file changed since it was loaded.
+    ///
+    /// A file that fails to parse is reported and skipped, so a half-saved edit doesn't
+    /// throw away the theme on screen.
+    pub fn reload_if_changed(&mut self) -> Option<Theme> {
+        let path = match self.paths.get(self.current) {
+            Some(path) => path.clone(),
+            None => return None,
+        };
+        let modified = modified_time(&path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        match Theme::load(&path) {
+            Ok(theme) => Some(theme),
+            Err(e) => {
+                eprintln!("{}", e);
+                None
+            }
+        }
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
@@ -0,0 +1,339 @@
+//! Minimal TOML reader.
+//!
+//! Only handles what the game's own files use: `[sections]`, `key = value` pairs, strings,
+//! numbers, booleans and arrays of those. Arrays may span several lines.
+
+use std::collections::HashMap;
+
+use graphics::types::Color;
+
+/// A single value on the right side of a `key = value` line.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// A quoted string.
+    Str(String),
+    /// Any number, integer or not.
+    Num(f64),
+    /// `true` or `false`.
+    Bool(bool),
+    /// A bracketed list of values.
+    Array(Vec<Value>),
+}
+
+impl Value {
+    /// Returns the string, if this is one.
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::Str(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the number, if this is one.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::Num(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// Returns the boolean, if this is one.
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// Returns the list, if this is one.
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match *self {
+            Value::Array(ref values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Reads a color written either as `"#rrggbb"`/`"#rrggbbaa"` or as `[r, g, b, a]`.
+    pub fn as_color(&self) -> Option<Color> {
+        match *self {
+            Value::Str(ref s) => parse_hex_color(s),
+            Value::Array(ref values) if values.len() == 3 || values.len() == 4 => {
+                let mut color = [0.0, 0.0, 0.0, 1.0];
+                for (i, value) in values.iter().enumerate() {
+                    color[i] = value.as_f64()? as f32;
+                }
+                Some(color)
+            }
+            _ => None,
+        }
+    }
+
+    /// Reads a list of whole-number pairs such as `[[1, 2], [-1, 0]]`.
+    pub fn as_steps(&self) -> Option<Vec<[i64; 2]>> {
+        let mut steps = Vec::new();
+        for pair in self.as_array()? {
+            match pair.as_array()? {
+                &[ref x, ref y] => {
+                    let (x, y) = (x.as_f64()?, y.as_f64()?);
+                    if x.fract() != 0.0 || y.fract() != 0.0 {
+                        return None;
+                    }
+                    steps.push([x as i64, y as i64]);
+                }
+                _ => return None,
+            }
+        }
+        Some(steps)
+    }
+}
+
+fn parse_hex_color(s: &str) -> Option<Color> {
+    let digits = s.trim_start_matches('#');
+    if (digits.len() != 6 && digits.len() != 8) || !digits.is_ascii() {
+        return None;
+    }
+    let mut color = [0.0, 0.0, 0.0, 1.0];
+    for i in 0..digits.len() / 2 {
+        let byte = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).ok()?;
+        color[i] = byte as f32 / 255.0;
+    }
+    Some(color)
+}
+
+/// A parsed file, keyed by section and then by key. Keys before any section live in `""`.
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+    sections: HashMap<String, HashMap<String, Value>>,
+}
+
+impl Document {
+    /// Parses a document, reporting the first bad line.
+    pub fn parse(text: &str) -> Result<Document, String> {
+        let mut doc = Document::default();
+        let mut section = String::new();
+        let mut lines = text.lines().enumerate();
+
+        while let Some((index, raw)) = lines.next() {
+            let mut line = strip_comment(raw).trim().to_string();
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') && !line.contains('=') {
+                section = line[1..line.len() - 1].trim().to_string();
+                doc.sections.entry(section.clone()).or_insert_with(HashMap::new);
+                continue;
+            }
+
+            // Keep pulling in lines until every bracket of an array is closed.
+            while bracket_depth(&line) > 0 {
+                match lines.next() {
+                    Some((_, next)) => {
+                        line.push(' ');
+                        line.push_str(strip_comment(next).trim());
+                    }
+                    None => return Err(format!("line {}: unclosed array", index + 1)),
+                }
+            }
+
+            let eq = line.find('=')
+                .ok_or_else(|| format!("line {}: expected `key = value`", index + 1))?;
+            let key = line[..eq].trim().trim_matches('"').to_string();
+            let (value, rest) = parse_value(line[eq + 1..].trim())
+                .map_err(|e| format!("line {}: {}", index + 1, e))?;
+            if key.is_empty() || !rest.trim().is_empty() {
+                return Err(format!("line {}: expected `key = value`", index + 1));
+            }
+            doc.sections
+                .entry(section.clone())
+                .or_insert_with(HashMap::new)
+                .insert(key, value);
+        }
+        Ok(doc)
+    }
+
+    /// Looks up a key in a section.
+    pub fn get(&self, section: &str, key: &str) -> Option<&Value> {
+        self.sections.get(section).and_then(|keys| keys.get(key))
+    }
+}
+
+/// Turns a missing value into an error naming the key and the kind of value it should hold.
+pub fn expect<T>(value: Option<T>, section: &str, key: &str, kind: &str) -> Result<T, String> {
+    value.ok_or_else(|| {
+        if section.is_empty() {
+            format!("{} should be {}", key, kind)
+        } else {
+            format!("{}.{} should be {}", section, key, kind)
+        }
+    })
+}
+
+/// Writes a string as a quoted TOML string.
+pub fn quote(s: &str) -> String {
+    format!("\"{}\"",
+            s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+/// Removes a trailing `# comment`, leaving `#` inside strings alone.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, ch) in line.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match ch {
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// How many more `[` than `]` there are outside strings.
+fn bracket_depth(line: &str) -> i32 {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for ch in line.chars() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match ch {
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '[' if !in_string => depth += 1,
+            ']' if !in_string => depth -= 1,
+            _ => {}
+        }
+    }
+    depth
+}
+
+/// Parses one value from the front of `s`, returning it and whatever follows.
+fn parse_value(s: &str) -> Result<(Value, &str), String> {
+    let s = s.trim_start();
+    if s.starts_with('"') {
+        let mut out = String::new();
+        let mut chars = s.char_indices().skip(1);
+        while let Some((i, ch)) = chars.next() {
+            match ch {
+                '"' => return Ok((Value::Str(out), &s[i + 1..])),
+                '\\' => {
+                    match chars.next() {
+                        Some((_, 'n')) => out.push('\n'),
+                        Some((_, 't')) => out.push('\t'),
+                        Some((_, other)) => out.push(other),
+                        None => break,
+                    }
+                }
+                _ => out.push(ch),
+            }
+        }
+        return Err("unterminated string".to_string());
+    }
+    if s.starts_with('[') {
+        let mut values = Vec::new();
+        let mut rest = s[1..].trim_start();
+        loop {
+            if rest.starts_with(']') {
+                return Ok((Value::Array(values), &rest[1..]));
+            }
+            let (value, after) = parse_value(rest)?;
+            values.push(value);
+            rest = after.trim_start();
+            if rest.starts_with(',') {
+                rest = rest[1..].trim_start();
+            } else if !rest.starts_with(']') {
+                return Err("expected `,` or `]` in array".to_string());
+            }
+        }
+    }
+
+    let end = s.find(|c: char| c == ',' || c == ']' || c.is_whitespace()).unwrap_or(s.len());
+    let word = &s[..end];
+    let value = match word {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => {
+            Value::Num(word.replace('_', "")
+                .parse::<f64>()
+                .map_err(|_| format!("can't read value `{}`", word))?)
+        }
+    };
+    Ok((value, &s[end..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comments_are_skipped_outside_strings() {
+        let doc = Document::parse("# a whole line\n\
+                                   name = \"#1 theme\" # trailing\n\
+                                   \n\
+                                   [board] # section comment\n\
+                                   size = 3 # three\n")
+            .unwrap();
+        assert_eq!(doc.get("", "name"), Some(&Value::Str("#1 theme".to_string())));
+        assert_eq!(doc.get("board", "size"), Some(&Value::Num(3.0)));
+    }
+
+    #[test]
+    fn arrays_span_several_lines() {
+        let doc = Document::parse("numbers = [1, 2, # first two\n\
+                                   3,\n\
+                                   [4, 5]]\n\
+                                   after = true\n")
+            .unwrap();
+        assert_eq!(doc.get("", "numbers"),
+                   Some(&Value::Array(vec![Value::Num(1.0),
+                                           Value::Num(2.0),
+                                           Value::Num(3.0),
+                                           Value::Array(vec![Value::Num(4.0),
+                                                             Value::Num(5.0)])])));
+        assert_eq!(doc.get("", "after"), Some(&Value::Bool(true)));
+    }
+
+    #[test]
+    fn escapes_in_strings_are_read_back() {
+        let doc = Document::parse(r#"text = "a \"quote\", a \\ and\na\ttab""#).unwrap();
+        let text = doc.get("", "text").and_then(Value::as_str).unwrap();
+        assert_eq!(text, "a \"quote\", a \\ and\na\ttab");
+
+        let quoted = format!("text = {}", quote(text));
+        let doc = Document::parse(&quoted).unwrap();
+        assert_eq!(doc.get("", "text").and_then(Value::as_str), Some(text));
+    }
+
+    #[test]
+    fn hex_colors_with_and_without_alpha() {
+        let color = |s: &str| Value::Str(s.to_string()).as_color();
+        assert_eq!(color("#ff0000"), Some([1.0, 0.0, 0.0, 1.0]));
+        assert_eq!(color("#00ff0080"), Some([0.0, 1.0, 0.0, 128.0 / 255.0]));
+        assert_eq!(color("#fff"), None);
+        assert_eq!(color("#gg0000"), None);
+        assert_eq!(Value::Array(vec![Value::Num(0.5), Value::Num(0.25), Value::Num(0.0)])
+                       .as_color(),
+                   Some([0.5, 0.25, 0.0, 1.0]));
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        assert_eq!(Document::parse("a = 1\n\nb 2\n").unwrap_err(),
+                   "line 3: expected `key = value`");
+        assert_eq!(Document::parse("[x]\nname = \"open\n").unwrap_err(),
+                   "line 2: unterminated string");
+        assert_eq!(Document::parse("a = 1\nb = nope\n").unwrap_err(),
+                   "line 2: can't read value `nope`");
+        assert_eq!(Document::parse("a = 1\nlist = [1,\n2,\n").unwrap_err(),
+                   "line 2: unclosed array");
+        assert_eq!(Document::parse("list = [1 2]\n").unwrap_err(),
+                   "line 1: expected `,` or `]` in array");
+    }
+}
@@ -0,0 +1,569 @@
+//! How the cells of a board sit next to each other.
+
+/// Height of a hexagon with pointy tops, relative to its width.
+const HEX_HEIGHT: f64 = 1.1547005383792515;
+/// Distance between rows of hexagons, relative to their width.
+const HEX_ROW_STEP: f64 = 0.8660254037844386;
+/// Height of a triangle with equal sides, relative to its width.
+const TRIANGLE_HEIGHT: f64 = 0.8660254037844386;
+
+/// Layers in a stack picked by name.
+pub const DEFAULT_LAYERS: usize = 4;
+/// Deepest stack that can be asked for.
+pub const MAX_LAYERS: usize = 10;
+/// Space between layers drawn side by side, counted in cells.
+const LAYER_GAP: f64 = 1.0;
+
+/// Most steps a custom neighbourhood can have: every cell up to two away in both directions.
+pub const MAX_OFFSETS: usize = 24;
+
+/// Neighbours of a square cell.
+const SQUARE_OFFSETS: [[i64; 2]; 8] =
+    [[-1, -1], [0, -1], [1, -1], [-1, 0], [1, 0], [-1, 1], [0, 1], [1, 1]];
+/// Neighbours of a hexagon in an even row.
+const HEX_EVEN_OFFSETS: [[i64; 2]; 6] = [[-1, -1], [0, -1], [-1, 0], [1, 0], [-1, 1], [0, 1]];
+/// Neighbours of a hexagon in an odd row, which sits half a cell further right.
+const HEX_ODD_OFFSETS: [[i64; 2]; 6] = [[0, -1], [1, -1], [-1, 0], [1, 0], [0, 1], [1, 1]];
+/// Neighbours of a triangle pointing up: three above its tip, five along its base and two to
+/// each side.
+const TRIANGLE_UP_OFFSETS: [[i64; 2]; 12] = [[-1, -1], [0, -1], [1, -1],
+                                             [-2, 0], [-1, 0], [1, 0], [2, 0],
+                                             [-2, 1], [-1, 1], [0, 1], [1, 1], [2, 1]];
+/// Neighbours of a triangle pointing down, the upside-down of one pointing up.
+const TRIANGLE_DOWN_OFFSETS: [[i64; 2]; 12] = [[-2, -1], [-1, -1], [0, -1], [1, -1], [2, -1],
+                                               [-2, 0], [-1, 0], [1, 0], [2, 0],
+                                               [-1, 1], [0, 1], [1, 1]];
+/// A cell and the eight around it, taken in its own layer and the ones above and below.
+const CUBE_OFFSETS: [[i64; 2]; 9] =
+    [[-1, -1], [0, -1], [1, -1], [-1, 0], [0, 0], [1, 0], [-1, 1], [0, 1], [1, 1]];
+/// The cells a chess knight can jump to.
+const KNIGHT_OFFSETS: [[i64; 2]; 8] =
+    [[-1, -2], [1, -2], [-2, -1], [2, -1], [-2, 1], [2, 1], [-1, 2], [1, 2]];
+
+/// A set of steps from a cell to the cells it counts, read from a settings or replay file.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Offsets {
+    steps: [[i64; 2]; MAX_OFFSETS],
+    len: usize,
+}
+
+impl Offsets {
+    /// Collects steps into a set. Every step is also taken the other way, so two cells always
+    /// count each other. Steps may reach at most two cells away.
+    pub fn new(steps: &[[i64; 2]]) -> Result<Offsets, String> {
+        let mut offsets = Offsets {
+            steps: [[0, 0]; MAX_OFFSETS],
+            len: 0,
+        };
+        for &[x, y] in steps {
+            if x.abs() > 2 || y.abs() > 2 {
+                return Err(format!("[{}, {}] reaches more than two cells away", x, y));
+            }
+            if x == 0 && y == 0 {
+                return Err("[0, 0] is the cell itself".to_string());
+            }
+            for &step in &[[x, y], [-x, -y]] {
+                if !offsets.as_slice().contains(&step) {
+                    offsets.steps[offsets.len] = step;
+                    offsets.len += 1;
+                }
+            }
+        }
+        Ok(offsets)
+    }
+
+    /// The steps, each one once.
+    pub fn as_slice(&self) -> &[[i64; 2]] {
+        &self.steps[..self.len]
+    }
+
+    /// The steps written as a TOML list, such as `[[1, 2], [-1, -2]]`.
+    pub fn to_toml(&self) -> String {
+        let steps: Vec<String> =
+            self.as_slice().iter().map(|step| format!("[{}, {}]", step[0], step[1])).collect();
+        format!("[{}]", steps.join(", "))
+    }
+}
+
+impl Default for Offsets {
+    /// The eight cells around a square.
+    fn default() -> Offsets {
+        Offsets::new(&SQUARE_OFFSETS).unwrap()
+    }
+}
+
+/// What a single cell looks like when drawn.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CellShape {
+    /// A square filling its box.
+    Square,
+    /// A hexagon with pointy tops.
+    Hexagon,
+    /// A triangle with its tip at the top.
+    TriangleUp,
+    /// A triangle with its tip at the bottom.
+    TriangleDown,
+}
+
+impl CellShape {
+    /// Corners of the cell filling a box, going clockwise, or `None` when the cell is the box
+    /// itself.
+    pub fn outline(&self, rect: [f64; 4]) -> Option<Vec<[f64; 2]>> {
+        let [x, y, w, h] = rect;
+        match *self {
+            CellShape::Square => None,
+            CellShape::Hexagon => {
+                Some(vec![[x + w / 2.0, y],
+                          [x + w, y + h / 4.0],
+                          [x + w, y + h * 0.75],
+                          [x + w / 2.0, y + h],
+                          [x, y + h * 0.75],
+                          [x, y + h / 4.0]])
+            }
+            CellShape::TriangleUp => Some(vec![[x + w / 2.0, y], [x + w, y + h], [x, y + h]]),
+            CellShape::TriangleDown => Some(vec![[x, y], [x + w, y], [x + w / 2.0, y + h]]),
+        }
+    }
+
+    /// Whether a point lies on the cell filling a box.
+    pub fn contains(&self, rect: [f64; 4], point: [f64; 2]) -> bool {
+        let corners = match self.outline(rect) {
+            Some(corners) => corners,
+            None => {
+                return point[0] >= rect[0] && point[0] < rect[0] + rect[2] &&
+                       point[1] >= rect[1] && point[1] < rect[1] + rect[3]
+            }
+        };
+        // Going clockwise, the point has to be on the inner side of every edge.
+        corners.iter().enumerate().all(|(i, from)| {
+            let to = corners[(i + 1) % corners.len()];
+            (to[0] - from[0]) * (point[1] - from[1]) - (to[1] - from[1]) * (point[0] - from[0]) >=
+            0.0
+        })
+    }
+}
+
+/// The shape of the cells and which cells count as touching. Everything that needs to know
+/// which cells touch asks `neighbours`, so adding a variant only takes a new offset table and,
+/// for new cell shapes, their geometry here.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Topology {
+    /// Square cells, each touching eight others.
+    Square,
+    /// Hexagons with pointy tops, each touching six others. Every odd row is pushed half a cell
+    /// to the right.
+    Hex,
+    /// Square cells on a board whose edges wrap around, so every cell touches eight others. The
+    /// board is drawn with a ring of ghost cells showing the opposite edges.
+    Torus,
+    /// Triangles pointing up and down in turn, each touching the twelve others that share an
+    /// edge or a corner with it.
+    Triangle,
+    /// Square cells whose numbers count the bombs a knight's move away.
+    Knight,
+    /// Square cells whose numbers count the bombs at the given steps away.
+    Custom(Offsets),
+    /// A stack of layers of square cells, the given number deep, where each cell touches the 26
+    /// around it in three dimensions. The board's rows hold one layer after another, and the
+    /// layers are drawn side by side.
+    Layers(usize),
+}
+
+impl Topology {
+    /// Every topology with its own name, for cycling and parsing. Custom neighbourhoods need
+    /// their steps, so they are left out.
+    pub const ALL: [Topology; 6] = [Topology::Square,
+                                    Topology::Hex,
+                                    Topology::Torus,
+                                    Topology::Triangle,
+                                    Topology::Knight,
+                                    Topology::Layers(DEFAULT_LAYERS)];
+
+    /// Name used in the config file and on the command line.
+    pub fn id(&self) -> &'static str {
+        match *self {
+            Topology::Square => "square",
+            Topology::Hex => "hex",
+            Topology::Torus => "torus",
+            Topology::Triangle => "triangle",
+            Topology::Knight => "knight",
+            Topology::Custom(_) => "custom",
+            Topology::Layers(_) => "layers",
+        }
+    }
+
+    /// Short name shown in the menu.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Topology::Square => "Square cells",
+            Topology::Hex => "Hex cells",
+            Topology::Torus => "Wrapped edges",
+            Topology::Triangle => "Triangle cells",
+            Topology::Knight => "Knight's moves",
+            Topology::Custom(_) => "Custom neighbours",
+            Topology::Layers(_) => "Stacked layers",
+        }
+    }
+
+    /// Looks up a topology by its config file name. `"custom"` gives the custom steps, and
+    /// `"layers"` a stack `DEFAULT_LAYERS` deep.
+    pub fn from_id(id: &str, custom: Offsets) -> Option<Topology> {
+        if id == "custom" {
+            return Some(Topology::Custom(custom));
+        }
+        Topology::ALL.iter().cloned().find(|topology| topology.id() == id)
+    }
+
+    /// The topology after this one, for cycling through them. The custom steps come last.
+    pub fn next(&self, custom: Offsets) -> Topology {
+        match *self {
+            Topology::Square => Topology::Hex,
+            Topology::Hex => Topology::Torus,
+            Topology::Torus => Topology::Triangle,
+            Topology::Triangle => Topology::Knight,
+            Topology::Knight => Topology::Layers(DEFAULT_LAYERS),
+            Topology::Layers(_) => Topology::Custom(custom),
+            Topology::Custom(_) => Topology::Square,
+        }
+    }
+
+    /// Whether the edges of the board wrap around to the opposite side.
+    pub fn wraps(&self) -> bool {
+        *self == Topology::Torus
+    }
+
+    /// Width of the ring of ghost cells drawn around the board, counted in cells.
+    pub fn margin(&self) -> f64 {
+        if self.wraps() { 1.0 } else { 0.0 }
+    }
+
+    /// Layers in the stack, 1 for flat boards.
+    pub fn layers(&self) -> usize {
+        match *self {
+            Topology::Layers(depth) => depth.max(1),
+            _ => 1,
+        }
+    }
+
+    /// Rows in each layer of a board with the given number of columns and rows.
+    pub fn layer_rows(&self, cells: (usize, usize)) -> usize {
+        (cells.1 / self.layers()).max(1)
+    }
+
+    /// Checks that a board with the given number of rows splits evenly into the layers.
+    pub fn check_rows(&self, rows: usize) -> Result<(), String> {
+        if rows % self.layers() != 0 {
+            return Err(format!("{} rows can't be split into {} layers", rows, self.layers()));
+        }
+        Ok(())
+    }
+
+    /// The layer a cell is in, counted from 0.
+    pub fn layer_of(&self, pos: [usize; 2], cells: (usize, usize)) -> usize {
+        pos[1] / self.layer_rows(cells)
+    }
+
+    /// Steps from a cell to each of its neighbours. Hexagons and triangles have different ones
+    /// depending on where they sit. On layered boards the steps are also taken in the layers
+    /// above and below.
+    pub fn offsets(&self, pos: [usize; 2]) -> &[[i64; 2]] {
+        match *self {
+            Topology::Square | Topology::Torus => &SQUARE_OFFSETS,
+            Topology::Hex if pos[1] % 2 == 0 => &HEX_EVEN_OFFSETS,
+            Topology::Hex => &HEX_ODD_OFFSETS,
+            Topology::Triangle if (pos[0] + pos[1]) % 2 == 0 => &TRIANGLE_UP_OFFSETS,
+            Topology::Triangle => &TRIANGLE_DOWN_OFFSETS,
+            Topology::Knight => &KNIGHT_OFFSETS,
+            Topology::Custom(ref offsets) => offsets.as_slice(),
+            Topology::Layers(_) => &CUBE_OFFSETS,
+        }
+    }
+
+    /// The cells touching a cell on a board with the given number of columns and rows. On
+    /// wrapping boards narrower than three cells the same cell can be reached two ways, but it is
+    /// only listed once.
+    pub fn neighbours(&self, pos: [usize; 2], cells: (usize, usize)) -> Vec<[usize; 2]> {
+        let offsets = self.offsets(pos);
+        let (depth, rows) = (self.layers() as i64, self.layer_rows(cells) as i64);
+        let layer = (pos[1] as i64) / rows;
+        let layer_steps: &[i64] = if depth > 1 { &[-1, 0, 1] } else { &[0] };
+        let mut result = Vec::with_capacity(offsets.len() * layer_steps.len());
+        for &dz in layer_steps {
+            let z = layer + dz;
+            if z < 0 || z >= depth {
+                continue;
+            }
+            for offset in offsets {
+                let mut x = pos[0] as i64 + offset[0];
+                let mut y = pos[1] as i64 % rows + offset[1];
+                if self.wraps() {
+                    x = (x + cells.0 as i64) % cells.0 as i64;
+                    y = (y + rows) % rows;
+                }
+                if x < 0 || x as usize >= cells.0 || y < 0 || y >= rows {
+                    continue;
+                }
+                let cell = [x as usize, (z * rows + y) as usize];
+                if cell != pos && !result.contains(&cell) {
+                    result.push(cell);
+                }
+            }
+        }
+        result
+    }
+
+    /// What a cell looks like.
+    pub fn shape(&self, pos: [usize; 2]) -> CellShape {
+        match *self {
+            Topology::Hex => CellShape::Hexagon,
+            Topology::Triangle if (pos[0] + pos[1]) % 2 == 0 => CellShape::TriangleUp,
+            Topology::Triangle => CellShape::TriangleDown,
+            _ => CellShape::Square,
+        }
+    }
+
+    /// Whether the board is a single grid of squares.
+    pub fn is_grid(&self) -> bool {
+        self.shape([0, 0]) == CellShape::Square && self.layers() == 1
+    }
+
+    /// Height of a cell, counted in cell widths.
+    pub fn cell_height(&self) -> f64 {
+        match *self {
+            Topology::Hex => HEX_HEIGHT,
+            Topology::Triangle => TRIANGLE_HEIGHT,
+            _ => 1.0,
+        }
+    }
+
+    /// Distance between the left edges of two columns, counted in cell widths. Triangles pointing
+    /// up and down fit into each other, half a cell apart.
+    pub fn column_step(&self) -> f64 {
+        if *self == Topology::Triangle { 0.5 } else { 1.0 }
+    }
+
+    /// Distance between the tops of two rows, counted in cell widths.
+    pub fn row_step(&self) -> f64 {
+        match *self {
+            Topology::Hex => HEX_ROW_STEP,
+            Topology::Triangle => TRIANGLE_HEIGHT,
+            _ => 1.0,
+        }
+    }
+
+    /// How far a row is pushed to the right, counted in cell widths.
+    pub fn row_shift(&self, row: usize) -> f64 {
+        if *self == Topology::Hex && row % 2 == 1 { 0.5 } else { 0.0 }
+    }
+
+    /// Width and height of a whole board, ghost cells included, counted in cell widths.
+    pub fn extent(&self, cells: (usize, usize)) -> (f64, f64) {
+        if self.layers() > 1 {
+            let depth = self.layers() as f64;
+            return (cells.0 as f64 * depth + LAYER_GAP * (depth - 1.0),
+                    self.layer_rows(cells) as f64);
+        }
+        let (columns, rows) = (cells.0.max(1), cells.1.max(1));
+        let shift = if rows > 1 { self.row_shift(1) } else { 0.0 };
+        ((columns - 1) as f64 * self.column_step() + 1.0 + shift + self.margin() * 2.0,
+         (rows - 1) as f64 * self.row_step() + self.cell_height() + self.margin() * 2.0)
+    }
+
+    /// Left-top corner of the box around a cell on a board with the given number of columns and
+    /// rows, counted in cell widths from the board's corner.
+    pub fn cell_origin(&self, pos: [usize; 2], cells: (usize, usize)) -> [f64; 2] {
+        if self.layers() > 1 {
+            let (layer, rows) = (self.layer_of(pos, cells), self.layer_rows(cells));
+            return [(layer as f64) * (cells.0 as f64 + LAYER_GAP) + pos[0] as f64,
+                    (pos[1] % rows) as f64];
+        }
+        [pos[0] as f64 * self.column_step() + self.row_shift(pos[1]) + self.margin(),
+         pos[1] as f64 * self.row_step() + self.margin()]
+    }
+
+    /// Finds the cell under a board position counted in cell widths.
+    pub fn cell_at(&self, pos: [f64; 2], cells: (usize, usize)) -> Option<[usize; 2]> {
+        let (width, height) = self.extent(cells);
+        if pos[0] < 0.0 || pos[0] >= width || pos[1] < 0.0 || pos[1] >= height {
+            return None;
+        }
+        if self.wraps() {
+            // Ghost cells stand for the cells on the opposite edge.
+            let wrap = |pos: f64, count: usize| {
+                let cell = (pos - self.margin()).floor() as i64;
+                ((cell + count as i64) % count as i64) as usize
+            };
+            return Some([wrap(pos[0], cells.0), wrap(pos[1], cells.1)]);
+        }
+        if self.layers() > 1 {
+            // Layers sit side by side with gaps between them.
+            let layer = (pos[0] / (cells.0 as f64 + LAYER_GAP)) as usize;
+            let x = (pos[0] - layer as f64 * (cells.0 as f64 + LAYER_GAP)) as usize;
+            if x >= cells.0 || layer >= self.layers() {
+                return None;
+            }
+            let rows = self.layer_rows(cells);
+            return Some([x, layer * rows + (pos[1] as usize).min(rows - 1)]);
+        }
+        if self.is_grid() {
+            return Some([(pos[0] as usize).min(cells.0 - 1), (pos[1] as usize).min(cells.1 - 1)]);
+        }
+        // The boxes around other shapes overlap, so the cell is one of a few around the rough
+        // guess.
+        let row = (pos[1] / self.row_step()) as i64;
+        let column = (pos[0] / self.column_step()) as i64;
+        for y in row - 1..row + 1 {
+            for x in column - 1..column + 1 {
+                if x < 0 || y < 0 || x as usize >= cells.0 || y as usize >= cells.1 {
+                    continue;
+                }
+                let cell = [x as usize, y as usize];
+                let origin = self.cell_origin(cell, cells);
+                let rect = [origin[0], origin[1], 1.0, self.cell_height()];
+                if self.shape(cell).contains(rect, pos) {
+                    return Some(cell);
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CELLS: (usize, usize) = (5, 4);
+
+    /// Every named topology and a lopsided custom one.
+    fn topologies() -> Vec<Topology> {
+        let mut topologies = Topology::ALL.to_vec();
+        topologies.push(Topology::Custom(Offsets::new(&[[2, 0], [1, -2]]).unwrap()));
+        topologies.push(Topology::Layers(2));
+        topologies
+    }
+
+    #[test]
+    fn neighbours_touch_each_other_both_ways() {
+        for topology in topologies() {
+            for y in 0..CELLS.1 {
+                for x in 0..CELLS.0 {
+                    for other in topology.neighbours([x, y], CELLS) {
+                        assert!(topology.neighbours(other, CELLS).contains(&[x, y]),
+                                "{:?} {:?} {:?}",
+                                topology,
+                                [x, y],
+                                other);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hexagons_have_six_neighbours_inside_the_board() {
+        assert_eq!(Topology::Hex.neighbours([2, 2], CELLS).len(), 6);
+        assert_eq!(Topology::Hex.neighbours([2, 1], CELLS).len(), 6);
+        assert_eq!(Topology::Square.neighbours([2, 1], CELLS).len(), 8);
+        assert_eq!(Topology::Hex.neighbours([0, 0], CELLS), vec![[1, 0], [0, 1]]);
+    }
+
+    #[test]
+    fn wrapped_cells_always_have_eight_neighbours() {
+        assert_eq!(Topology::Torus.neighbours([0, 0], CELLS).len(), 8);
+        assert!(Topology::Torus.neighbours([0, 0], CELLS).contains(&[4, 3]));
+        assert_eq!(Topology::Torus.neighbours([4, 3], CELLS).len(), 8);
+        // Two columns wrap onto each other, leaving five different cells around each one.
+        assert_eq!(Topology::Torus.neighbours([0, 1], (2, 4)).len(), 5);
+    }
+
+    #[test]
+    fn ghost_cells_stand_for_the_opposite_edge() {
+        let torus = Topology::Torus;
+        assert_eq!(torus.cell_at([0.5, 0.5], CELLS), Some([4, 3]));
+        assert_eq!(torus.cell_at([6.5, 2.5], CELLS), Some([0, 1]));
+        assert_eq!(torus.cell_at([7.0, 2.5], CELLS), None);
+    }
+
+    #[test]
+    fn every_cell_is_found_at_its_center() {
+        for topology in topologies() {
+            for y in 0..CELLS.1 {
+                for x in 0..CELLS.0 {
+                    let origin = topology.cell_origin([x, y], CELLS);
+                    let center = [origin[0] + 0.5, origin[1] + topology.cell_height() / 2.0];
+                    assert_eq!(topology.cell_at(center, CELLS), Some([x, y]));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn corners_beside_hexagons_are_empty() {
+        assert_eq!(Topology::Hex.cell_at([0.02, 0.02], CELLS), None);
+        assert_eq!(Topology::Hex.cell_at([0.2, 1.0], CELLS), None);
+        assert_eq!(Topology::Hex.cell_at([0.8, 1.0], CELLS), Some([0, 1]));
+    }
+
+    #[test]
+    fn triangles_touch_twelve_others_inside_the_board() {
+        assert_eq!(Topology::Triangle.neighbours([2, 2], CELLS).len(), 12);
+        assert_eq!(Topology::Triangle.neighbours([2, 1], CELLS).len(), 12);
+        assert_eq!(Topology::Triangle.neighbours([0, 0], CELLS),
+                   vec![[1, 0], [2, 0], [0, 1], [1, 1], [2, 1]]);
+    }
+
+    #[test]
+    fn triangles_point_up_and_down_in_turn() {
+        let triangle = Topology::Triangle;
+        assert_eq!(triangle.shape([0, 0]), CellShape::TriangleUp);
+        assert_eq!(triangle.shape([1, 0]), CellShape::TriangleDown);
+        assert_eq!(triangle.shape([0, 1]), CellShape::TriangleDown);
+        assert_eq!(triangle.extent(CELLS), (3.0, 4.0 * TRIANGLE_HEIGHT));
+        // Left of the first tip is empty, right of it is the triangle pointing down.
+        assert_eq!(triangle.cell_at([0.1, 0.1], CELLS), None);
+        assert_eq!(triangle.cell_at([0.9, 0.1], CELLS), Some([1, 0]));
+    }
+
+    #[test]
+    fn knights_count_jumps_away() {
+        let neighbours = Topology::Knight.neighbours([0, 0], CELLS);
+        assert_eq!(neighbours, vec![[2, 1], [1, 2]]);
+        assert_eq!(Topology::Knight.neighbours([2, 2], CELLS).len(), 6);
+    }
+
+    #[test]
+    fn custom_steps_are_taken_both_ways() {
+        let offsets = Offsets::new(&[[1, 2], [-1, -2], [0, 1]]).unwrap();
+        assert_eq!(offsets.as_slice(), &[[1, 2], [-1, -2], [0, 1], [0, -1]]);
+        assert_eq!(offsets.to_toml(), "[[1, 2], [-1, -2], [0, 1], [0, -1]]");
+        assert!(Offsets::new(&[[3, 0]]).is_err());
+        assert!(Offsets::new(&[[0, 0]]).is_err());
+        assert_eq!(Topology::from_id("custom", offsets), Some(Topology::Custom(offsets)));
+        assert_eq!(Topology::Layers(DEFAULT_LAYERS).next(offsets), Topology::Custom(offsets));
+    }
+
+    #[test]
+    fn layered_cells_touch_26_others_inside_the_stack() {
+        let layers = Topology::Layers(3);
+        let cells = (4, 12);
+        assert_eq!(layers.neighbours([1, 5], cells).len(), 26);
+        // A corner of the top layer touches three cells in its own layer and four below it.
+        let corner = layers.neighbours([0, 0], cells);
+        assert_eq!(corner, vec![[1, 0], [0, 1], [1, 1], [0, 4], [1, 4], [0, 5], [1, 5]]);
+        // The bottom row of a layer doesn't reach the top row of the next one.
+        assert!(!layers.neighbours([0, 3], cells).contains(&[0, 4]));
+        assert_eq!(layers.layer_of([3, 11], cells), 2);
+    }
+
+    #[test]
+    fn layers_are_drawn_side_by_side() {
+        let layers = Topology::Layers(3);
+        let cells = (4, 12);
+        assert_eq!(layers.extent(cells), (14.0, 4.0));
+        assert_eq!(layers.cell_origin([1, 5], cells), [6.0, 1.0]);
+        assert_eq!(layers.cell_at([6.5, 1.5], cells), Some([1, 5]));
+        // Nothing is in the gap between two layers.
+        assert_eq!(layers.cell_at([4.5, 1.5], cells), None);
+    }
+}
@@ -1,13 +1,59 @@
-use graphics::{Context, Graphics};
-use graphics::character::CharacterCache;
-use piston::input::GenericEvent;
-use state::State;
-
-pub trait Renderer {
-    fn draw<G: Graphics, C>(&self, glyphs: &mut C, c: &Context, g: &mut G)
-        where C: CharacterCache<Texture = G::Texture>;
-}
-
-pub trait EventHandler {
-    fn event<E: GenericEvent>(&mut self, size: (f64, f64), e: &E) -> State;
-}
+use graphics::types::Color;
+use graphics::ImageSize;
+use piston::input::GenericEvent;
+use state::State;
+
+/// Something to draw on. Each rendering backend implements this once, and the views only draw
+/// through it.
+pub trait Canvas {
+    /// Images the backend can draw sprites from.
+    type Texture: ImageSize;
+
+    /// Width and height of the area being drawn on.
+    fn size(&self) -> [f64; 2];
+
+    /// Fills everything with one color.
+    fn clear(&mut self, color: Color);
+
+    /// Fills a rectangle, with corners rounded by `radius`.
+    fn rectangle(&mut self, color: Color, rect: [f64; 4], radius: f64);
+
+    /// Draws the outline of a rectangle, reaching `radius` to both sides of its edge.
+    fn border(&mut self, color: Color, rect: [f64; 4], radius: f64);
+
+    /// Fills a rectangle turned `angle` radians around its center.
+    fn rotated_rectangle(&mut self, color: Color, center: [f64; 2], size: [f64; 2], angle: f64);
+
+    /// Fills a convex polygon through the given corners.
+    fn polygon(&mut self, color: Color, corners: &[[f64; 2]]);
+
+    /// Fills the ellipse inside a rectangle.
+    fn ellipse(&mut self, color: Color, rect: [f64; 4]);
+
+    /// Draws a line from `[x1, y1, x2, y2]`, reaching `radius` to both sides.
+    fn line(&mut self, color: Color, line: [f64; 4], radius: f64);
+
+    /// Draws the `src` part of a texture stretched over `rect`.
+    fn image(&mut self, texture: &Self::Texture, src: [f64; 4], rect: [f64; 4]);
+
+    /// Width of a line of text.
+    fn text_width(&mut self, font_size: u32, text: &str) -> f64;
+
+    /// Draws a line of text starting at the left end of its baseline.
+    fn text(&mut self, color: Color, font_size: u32, text: &str, pos: [f64; 2]);
+
+    /// Width and height of a single character.
+    fn character_size(&mut self, font_size: u32, ch: char) -> [f64; 2];
+
+    /// Draws a single character with its top left corner at `pos`, squeezed sideways by
+    /// `squeeze`.
+    fn character(&mut self, color: Color, font_size: u32, ch: char, pos: [f64; 2], squeeze: f64);
+}
+
+pub trait Renderer<T: ImageSize> {
+    fn draw<C: Canvas<Texture = T>>(&self, canvas: &mut C);
+}
+
+pub trait EventHandler {
+    fn event<E: GenericEvent>(&mut self, size: (f64, f64), e: &E) -> State;
+}
@@ -0,0 +1,387 @@
+//! Playing in a terminal.
+//!
+//! The board is drawn with ANSI colors and played with the same keys as in the window, or with the
+//! mouse on terminals with xterm mouse reporting. Raw mode is set up with `stty`, so it works over
+//! SSH without a window or any extra libraries.
+
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+
+use piston::input::keyboard::Key;
+use piston::input::MouseButton;
+
+use gameboard::CellState;
+use gameboard_controller::GameboardController;
+use headless::tile_char;
+use keybindings::{key_from_name, Action};
+use replay::Move;
+use sprites::Tile;
+use topology::Topology;
+
+/// Lines above the board, for the status line.
+const TOP: usize = 2;
+/// Columns taken by each cell. A gap between cells makes the board look about square.
+const CELL_WIDTH: usize = 2;
+/// Shown under the board.
+const HELP: &'static str = "Keys as in the window, N for a new game, Q to quit. Mouse: left opens, \
+                            right flags, middle chords.";
+
+/// Something typed or clicked in the terminal.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Input {
+    /// A key was pressed.
+    Key(Key),
+    /// A mouse button was pressed on a terminal cell, as column and line counted from 0.
+    Click(MouseButton, [usize; 2]),
+    /// Ctrl-C was pressed.
+    Interrupt,
+}
+
+/// Reads what the terminal sent, which can hold several keys or mouse reports at once.
+pub fn parse_input(bytes: &[u8]) -> Vec<Input> {
+    let mut inputs = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let byte = bytes[i];
+        i += 1;
+        let key = match byte {
+            3 => {
+                inputs.push(Input::Interrupt);
+                continue;
+            }
+            27 if i < bytes.len() && (bytes[i] == b'[' || bytes[i] == b'O') => {
+                i += 1;
+                i += parse_escape(&bytes[i..], &mut inputs);
+                continue;
+            }
+            27 => Some(Key::Escape),
+            b'\r' | b'\n' => Some(Key::Return),
+            b' ' => Some(Key::Space),
+            b'\t' => Some(Key::Tab),
+            8 | 127 => Some(Key::Backspace),
+            _ if byte.is_ascii_alphanumeric() => key_from_name(&(byte as char).to_string()),
+            _ => None,
+        };
+        if let Some(key) = key {
+            inputs.push(Input::Key(key));
+        }
+    }
+    inputs
+}
+
+/// Reads an escape sequence after its `ESC [`, returning how many bytes it took.
+fn parse_escape(bytes: &[u8], inputs: &mut Vec<Input>) -> usize {
+    // Old style mouse reports send the button and position as single bytes. One cut short by the
+    // read is dropped, so its bytes aren't taken for keys.
+    if bytes.first() == Some(&b'M') {
+        if bytes.len() < 4 {
+            return bytes.len();
+        }
+        let button = bytes[1].saturating_sub(32) as usize;
+        let col = bytes[2].saturating_sub(33) as usize;
+        let line = bytes[3].saturating_sub(33) as usize;
+        if let Some(button) = mouse_button(button) {
+            inputs.push(Input::Click(button, [col, line]));
+        }
+        return 4;
+    }
+
+    let sgr = bytes.first() == Some(&b'<');
+    let start = if sgr { 1 } else { 0 };
+    let end = match bytes[start..].iter().position(|&b| (0x40..0x7f).contains(&b)) {
+        Some(end) => start + end,
+        None => return bytes.len(),
+    };
+    let params: Vec<usize> = String::from_utf8_lossy(&bytes[start..end])
+        .split(';')
+        .map(|param| param.parse().unwrap_or(0))
+        .collect();
+    let param = |index: usize| params.get(index).cloned().unwrap_or(0);
+
+    if sgr {
+        // `ESC [ < button ; column ; line M`, with `m` for releases, counting from 1.
+        if bytes[end] == b'M' {
+            if let Some(button) = mouse_button(param(0)) {
+                inputs.push(Input::Click(button, [param(1).saturating_sub(1),
+                                                  param(2).saturating_sub(1)]));
+            }
+        }
+        return end + 1;
+    }
+
+    let key = match (bytes[end], param(0)) {
+        (b'A', _) => Some(Key::Up),
+        (b'B', _) => Some(Key::Down),
+        (b'C', _) => Some(Key::Right),
+        (b'D', _) => Some(Key::Left),
+        (b'H', _) | (b'~', 1) | (b'~', 7) => Some(Key::Home),
+        (b'F', _) | (b'~', 4) | (b'~', 8) => Some(Key::End),
+        (b'~', 2) => Some(Key::Insert),
+        (b'~', 3) => Some(Key::Delete),
+        (b'~', 5) => Some(Key::PageUp),
+        (b'~', 6) => Some(Key::PageDown),
+        _ => None,
+    };
+    if let Some(key) = key {
+        inputs.push(Input::Key(key));
+    }
+    end + 1
+}
+
+/// The button in a mouse report, leaving out releases, dragging and the wheel.
+fn mouse_button(code: usize) -> Option<MouseButton> {
+    if code & (32 | 64) != 0 {
+        return None;
+    }
+    match code & 3 {
+        0 => Some(MouseButton::Left),
+        1 => Some(MouseButton::Middle),
+        2 => Some(MouseButton::Right),
+        _ => None,
+    }
+}
+
+/// ANSI color for a tile.
+fn tile_color(tile: Tile) -> &'static str {
+    match tile {
+        Tile::Hidden => "\x1b[37m",
+        Tile::Revealed => "\x1b[90m",
+        Tile::Flag => "\x1b[1;31m",
+        Tile::Mine => "\x1b[1m",
+        Tile::ExplodedMine => "\x1b[1;37;41m",
+        Tile::WrongFlag => "\x1b[1;33m",
+        Tile::Question => "\x1b[33m",
+        Tile::Number(1) => "\x1b[94m",
+        Tile::Number(2) => "\x1b[32m",
+        Tile::Number(3) => "\x1b[91m",
+        Tile::Number(4) => "\x1b[34m",
+        Tile::Number(5) => "\x1b[31m",
+        Tile::Number(6) => "\x1b[36m",
+        Tile::Number(7) => "\x1b[35m",
+        Tile::Number(_) => "\x1b[90m",
+    }
+}
+
+/// The whole screen, drawn over the last one from the top left corner.
+pub fn draw(controller: &GameboardController) -> String {
+    let board = &controller.gameboard;
+    let flags = board.cells
+        .iter()
+        .map(|row| {
+            row.iter()
+                .filter(|&&cell| cell == CellState::FlaggedBlank || cell == CellState::FlaggedBomb)
+                .count()
+        })
+        .sum::<usize>();
+    let result = if board.is_won() {
+        "You won!"
+    } else if board.is_lost() {
+        "You lost."
+    } else if board.needs_guess {
+        "No board without guesses was found, this one may need a guess."
+    } else {
+        ""
+    };
+
+    let mut screen = "\x1b[H".to_string();
+    screen.push_str(&format!("{} {}x{}   Bombs left: {}   {}\x1b[K\r\n\x1b[K\r\n",
+                             board.difficulty().name(),
+                             board.width,
+                             board.height,
+                             board.mines as i64 - flags as i64,
+                             result));
+    let lost = board.is_lost();
+    for (y, row) in board.cells.iter().enumerate() {
+        screen.push_str(&" ".repeat(indent(controller, y)));
+        for (x, &cell) in row.iter().enumerate() {
+            let tile = Tile::for_cell(cell, lost);
+            if controller.selected_cell == Some([x, y]) {
+                screen.push_str("\x1b[7m");
+            }
+            screen.push_str(tile_color(tile));
+            screen.push(tile_char(tile));
+            screen.push_str("\x1b[0m ");
+        }
+        screen.push_str("\x1b[K\r\n");
+    }
+    screen.push_str(&format!("\x1b[K\r\n{}\x1b[J", HELP));
+    screen
+}
+
+/// Columns a row is pushed to the right. Odd rows of hexagons sit half a cell further right.
+fn indent(controller: &GameboardController, y: usize) -> usize {
+    match controller.gameboard.topology {
+        Topology::Hex if y % 2 == 1 => CELL_WIDTH / 2,
+        _ => 0,
+    }
+}
+
+/// The board cell at a terminal cell, if there is one.
+fn cell_at(controller: &GameboardController, [col, line]: [usize; 2]) -> Option<[usize; 2]> {
+    let board = &controller.gameboard;
+    if line < TOP {
+        return None;
+    }
+    let y = line - TOP;
+    if y >= board.height || col < indent(controller, y) {
+        return None;
+    }
+    let x = (col - indent(controller, y)) / CELL_WIDTH;
+    if x < board.width {
+        Some([x, y])
+    } else {
+        None
+    }
+}
+
+/// Puts the terminal in raw mode with mouse reporting on a screen of its own, and puts it back
+/// when dropped.
+struct Terminal {
+    saved: String,
+}
+
+impl Terminal {
+    fn new() -> io::Result<Terminal> {
+        let saved = stty(&["-g"])?;
+        stty(&["raw", "-echo"])?;
+        print!("\x1b[?1049h\x1b[?25l\x1b[?1000h\x1b[?1006h");
+        io::stdout().flush()?;
+        Ok(Terminal { saved: saved.trim().to_string() })
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        print!("\x1b[?1006l\x1b[?1000l\x1b[?25h\x1b[?1049l");
+        let _ = io::stdout().flush();
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+fn stty(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("stty").args(args).stdin(Stdio::inherit()).output()?;
+    if !output.status.success() {
+        return Err(io::Error::new(io::ErrorKind::Other,
+                                  "could not set up the terminal, is stdin a terminal?"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Plays in the terminal until the player quits. `new_game` deals the next board, and `finished`
+/// is called once for every game that ends.
+pub fn run(mut controller: GameboardController,
+           new_game: &mut dyn FnMut() -> GameboardController,
+           finished: &mut dyn FnMut(&GameboardController))
+           -> io::Result<()> {
+    let _terminal = Terminal::new()?;
+    let stdin = io::stdin();
+    let mut stdin = stdin.lock();
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut buf = [0; 256];
+    loop {
+        out.write_all(draw(&controller).as_bytes())?;
+        out.flush()?;
+        let len = stdin.read(&mut buf)?;
+        if len == 0 {
+            return Ok(());
+        }
+
+        for input in parse_input(&buf[..len]) {
+            let was_over = controller.gameboard.is_over();
+            match input {
+                Input::Interrupt => return Ok(()),
+                Input::Key(key) => {
+                    match controller.keys.action(key) {
+                        Some(Action::Menu) => return Ok(()),
+                        Some(action) => controller.key_press(action),
+                        None if key == Key::Q => return Ok(()),
+                        None if key == Key::N => controller = new_game(),
+                        None => {}
+                    }
+                }
+                Input::Click(button, pos) => {
+                    if let Some(cell) = cell_at(&controller, pos) {
+                        controller.selected_cell = Some(cell);
+                        let mv = match button {
+                            MouseButton::Left => Some(Move::Reveal(cell)),
+                            MouseButton::Right => Some(Move::Flag(cell)),
+                            MouseButton::Middle => Some(Move::Chord(cell)),
+                            _ => None,
+                        };
+                        if let Some(mv) = mv {
+                            controller.apply(mv);
+                        }
+                    }
+                }
+            }
+            if !was_over && controller.gameboard.is_over() {
+                finished(&controller);
+            }
+        }
+        // There is nothing to animate or play sounds for here.
+        controller.take_events();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(keys: &[Key]) -> Vec<Input> {
+        keys.iter().map(|&key| Input::Key(key)).collect()
+    }
+
+    #[test]
+    fn plain_keys_and_ctrl_c() {
+        assert_eq!(parse_input(b"f c\r\x1b"),
+                   keys(&[Key::F, Key::Space, Key::C, Key::Return, Key::Escape]));
+        assert_eq!(parse_input(b"q\x03n"),
+                   vec![Input::Key(Key::Q), Input::Interrupt, Input::Key(Key::N)]);
+        assert_eq!(parse_input(b"!~"), vec![]);
+    }
+
+    #[test]
+    fn arrows_and_other_named_keys() {
+        assert_eq!(parse_input(b"\x1b[A\x1b[B\x1b[C\x1b[D"),
+                   keys(&[Key::Up, Key::Down, Key::Right, Key::Left]));
+        // Application mode sends `ESC O` instead of `ESC [`.
+        assert_eq!(parse_input(b"\x1bOA\x1bOD"), keys(&[Key::Up, Key::Left]));
+        assert_eq!(parse_input(b"\x1b[5~\x1b[6~\x1b[3~\x1b[H\x1b[4~"),
+                   keys(&[Key::PageUp, Key::PageDown, Key::Delete, Key::Home, Key::End]));
+        // Modifiers are ignored.
+        assert_eq!(parse_input(b"\x1b[1;5C"), keys(&[Key::Right]));
+    }
+
+    #[test]
+    fn sgr_mouse_reports() {
+        assert_eq!(parse_input(b"\x1b[<0;5;3M\x1b[<0;5;3m"),
+                   vec![Input::Click(MouseButton::Left, [4, 2])]);
+        assert_eq!(parse_input(b"\x1b[<2;120;40M"),
+                   vec![Input::Click(MouseButton::Right, [119, 39])]);
+        assert_eq!(parse_input(b"\x1b[<1;1;1M"),
+                   vec![Input::Click(MouseButton::Middle, [0, 0])]);
+        // Dragging and the wheel are left out.
+        assert_eq!(parse_input(b"\x1b[<32;5;3M\x1b[<64;5;3M"), vec![]);
+    }
+
+    #[test]
+    fn legacy_mouse_reports() {
+        // Button, column and line are sent as single bytes, offset by 32 and counting from 1.
+        assert_eq!(parse_input(b"\x1b[M %#"), vec![Input::Click(MouseButton::Left, [4, 2])]);
+        assert_eq!(parse_input(b"\x1b[M\"!!f"),
+                   vec![Input::Click(MouseButton::Right, [0, 0]), Input::Key(Key::F)]);
+        // A release is reported as button 3.
+        assert_eq!(parse_input(b"\x1b[M#%#"), vec![]);
+    }
+
+    #[test]
+    fn partial_and_unknown_sequences_are_dropped() {
+        assert_eq!(parse_input(b"\x1b["), vec![]);
+        assert_eq!(parse_input(b"\x1b[12"), vec![]);
+        assert_eq!(parse_input(b"\x1b[<0;5"), vec![]);
+        assert_eq!(parse_input(b"\x1b[M a"), vec![]);
+        assert_eq!(parse_input(b"\x1b[99~f"), keys(&[Key::F]));
+        assert_eq!(parse_input(b"\x1b[Zf"), keys(&[Key::F]));
+    }
+}
@@ -0,0 +1,40 @@
+//! Opening the game window.
+
+use glutin_window::GlutinWindow;
+use opengl_graphics::OpenGL;
+use piston::window::WindowSettings;
+
+/// OpenGL versions to try, newest first, with the names they are given by on the command line.
+pub const OPENGL_VERSIONS: [(&'static str, OpenGL); 6] = [("4.4", OpenGL::V4_4),
+                                                          ("4.1", OpenGL::V4_1),
+                                                          ("3.3", OpenGL::V3_3),
+                                                          ("3.2", OpenGL::V3_2),
+                                                          ("3.0", OpenGL::V3_0),
+                                                          ("2.1", OpenGL::V2_1)];
+
+/// Looks up an OpenGL version by its name, like `3.2`.
+pub fn opengl_from_name(name: &str) -> Option<OpenGL> {
+    OPENGL_VERSIONS.iter().find(|&&(n, _)| n == name).map(|&(_, opengl)| opengl)
+}
+
+/// Name of an OpenGL version.
+pub fn opengl_name(opengl: OpenGL) -> &'static str {
+    OPENGL_VERSIONS.iter().find(|&&(_, v)| v == opengl).map_or("?", |&(name, _)| name)
+}
+
+/// Opens a window with the newest OpenGL version that works, going down the list from `first`.
+/// Multisampling is dropped for drivers that won't do it.
+pub fn open(title: &str, size: [u32; 2], first: OpenGL) -> Result<(GlutinWindow, OpenGL), String> {
+    let start = OPENGL_VERSIONS.iter().position(|&(_, opengl)| opengl == first).unwrap_or(0);
+    let mut errors = Vec::new();
+    for &(name, opengl) in &OPENGL_VERSIONS[start..] {
+        for &samples in &[2, 0] {
+            let settings = WindowSettings::new(title, size).opengl(opengl).samples(samples);
+            match settings.build() {
+                Ok(window) => return Ok((window, opengl)),
+                Err(e) => errors.push(format!("OpenGL {} with {} samples: {}", name, samples, e)),
+            }
+        }
+    }
+    Err(errors.join("\n"))
+}